@@ -0,0 +1,130 @@
+use crate::Result;
+use std::{
+    fmt::{self, Display, Formatter},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Somewhere a document can be read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    pub fn read(&self) -> Result<String> {
+        match self {
+            Input::Stdin => {
+                let mut buffer = String::new();
+                std::io::stdin().read_to_string(&mut buffer)?;
+                Ok(buffer)
+            },
+            Input::File(path) => std::fs::read_to_string(path).map_err(|e| {
+                format!("Unable to read \"{}\": {}", path.display(), e).into()
+            }),
+        }
+    }
+
+    pub fn write(&self, contents: &str) -> Result<()> {
+        match self {
+            Input::Stdin => Err("Stdin can't be written to".into()),
+            Input::File(path) => std::fs::write(path, contents).map_err(|e| {
+                format!("Unable to write to \"{}\": {}", path.display(), e)
+                    .into()
+            }),
+        }
+    }
+
+    /// The directory relative links are resolved against.
+    pub fn base_dir(&self) -> PathBuf {
+        match self {
+            Input::File(path) => {
+                path.parent().map(Path::to_path_buf).unwrap_or_default()
+            },
+            Input::Stdin => PathBuf::from("."),
+        }
+    }
+
+    /// The name used in a diff's header, where relative paths get a
+    /// git-style prefix (e.g. `a/README.md`).
+    pub fn diff_name(&self, prefix: &str) -> String {
+        match self {
+            Input::File(path) if path.is_relative() => {
+                format!("{}/{}", prefix, path.display())
+            },
+            other => other.to_string(),
+        }
+    }
+}
+
+impl Display for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Stdin => write!(f, "<stdin>"),
+            Input::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Turn the arguments from the command-line into a list of inputs.
+///
+/// Directories are searched recursively for `*.md` files, glob patterns are
+/// expanded, and stdin is used when there are no arguments.
+pub fn expand(patterns: &[String]) -> Result<Vec<Input>> {
+    if patterns.is_empty() {
+        return Ok(vec![Input::Stdin]);
+    }
+
+    let mut inputs = Vec::new();
+
+    for pattern in patterns {
+        let path = Path::new(pattern);
+
+        if pattern == "-" {
+            inputs.push(Input::Stdin);
+        } else if path.is_dir() {
+            let pattern = path.join("**").join("*.md");
+            inputs.extend(glob(&pattern.to_string_lossy())?);
+        } else if pattern.contains(['*', '?', '[']) {
+            let matches = glob(pattern)?;
+            if matches.is_empty() {
+                return Err(format!("Nothing matched \"{}\"", pattern).into());
+            }
+            inputs.extend(matches);
+        } else {
+            inputs.push(Input::File(path.to_path_buf()));
+        }
+    }
+
+    Ok(inputs)
+}
+
+fn glob(pattern: &str) -> Result<Vec<Input>> {
+    let mut paths = Vec::new();
+
+    for entry in glob::glob(pattern)? {
+        let path = entry?;
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+    Ok(paths.into_iter().map(Input::File).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_relative_paths_are_prefixed_in_diffs() {
+        let relative = Input::File(PathBuf::from("docs/README.md"));
+        let absolute = Input::File(std::env::temp_dir().join("in.md"));
+
+        assert_eq!(relative.diff_name("a"), "a/docs/README.md");
+        assert_eq!(absolute.diff_name("a"), absolute.to_string());
+        assert_eq!(Input::Stdin.diff_name("b"), "<stdin>");
+    }
+}
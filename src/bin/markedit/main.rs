@@ -0,0 +1,419 @@
+//! A command-line tool for editing Markdown documents with `markedit`.
+
+mod inputs;
+
+use crate::inputs::Input;
+use clap::{Args, Parser, Subcommand};
+use markedit::{
+    pulldown_cmark::{self, Event, Options},
+    Document, InsertAfterHeading, Rewriter, Rules,
+};
+use std::{error::Error, path::PathBuf, process::ExitCode};
+
+type Result<T, E = Box<dyn Error>> = std::result::Result<T, E>;
+
+/// Edit Markdown documents from the command-line.
+#[derive(Debug, Parser)]
+#[command(name = "markedit", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Insert a Markdown snippet after a heading.
+    Insert {
+        /// The text of the heading to insert after.
+        #[arg(long, value_name = "TEXT")]
+        after_heading: String,
+        #[command(flatten)]
+        snippet: Snippet,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Replace everything between `<!-- begin NAME -->` and
+    /// `<!-- end NAME -->`.
+    ReplaceRegion {
+        /// The region's name.
+        #[arg(long)]
+        name: String,
+        #[command(flatten)]
+        snippet: Snippet,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Generate a table of contents, updating the `<!-- begin toc -->`
+    /// region.
+    Toc {
+        /// Skip headings above this level (e.g. 2 to skip the title).
+        #[arg(long, default_value_t = 1)]
+        min_level: u32,
+        /// Skip headings below this level.
+        #[arg(long, default_value_t = 6)]
+        max_level: u32,
+        /// The region to put the table of contents in.
+        #[arg(long, default_value = "toc")]
+        region: String,
+        /// Print the table of contents instead of updating the document.
+        #[arg(long)]
+        print: bool,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Move every heading up or down a number of levels.
+    ShiftHeadings {
+        /// How many levels to shift by (negative numbers move headings up).
+        #[arg(long, allow_negative_numbers = true)]
+        by: i32,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Work with the links in a document.
+    Links {
+        #[command(subcommand)]
+        command: LinksCommand,
+    },
+    /// Apply the rewrite rules from a `markedit.toml` file.
+    Apply {
+        /// The file containing the rules.
+        #[arg(long, value_name = "PATH", default_value = "markedit.toml")]
+        rules: PathBuf,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// Reformat documents.
+    Fmt {
+        #[command(flatten)]
+        output: Output,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum LinksCommand {
+    /// Check for broken anchors and relative links, without touching the
+    /// network.
+    Check {
+        /// The files to check (files, directories or glob patterns, with `-`
+        /// meaning stdin).
+        inputs: Vec<String>,
+    },
+}
+
+/// The Markdown to insert.
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+struct Snippet {
+    /// Read the Markdown to insert from a file.
+    #[arg(long, value_name = "PATH")]
+    file: Option<PathBuf>,
+    /// The Markdown to insert.
+    #[arg(long, value_name = "MARKDOWN")]
+    text: Option<String>,
+}
+
+impl Snippet {
+    fn read(&self) -> Result<String> {
+        match (&self.file, &self.text) {
+            (Some(path), _) => std::fs::read_to_string(path).map_err(|e| {
+                format!("Unable to read \"{}\": {}", path.display(), e).into()
+            }),
+            (None, Some(text)) => Ok(text.clone()),
+            (None, None) => Err("No snippet was provided".into()),
+        }
+    }
+}
+
+/// What to do with each edited document.
+#[derive(Debug, Args)]
+struct Output {
+    /// The documents to edit (files, directories or glob patterns, with `-`
+    /// meaning stdin). Reads from stdin when nothing is provided.
+    inputs: Vec<String>,
+    /// Overwrite each file instead of printing the result.
+    #[arg(short, long, conflicts_with = "check")]
+    in_place: bool,
+    /// Don't write anything, exiting with an error if a document would be
+    /// changed.
+    #[arg(long)]
+    check: bool,
+    /// Print a unified diff instead of the edited document.
+    #[arg(long)]
+    diff: bool,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli.command) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+
+            let mut source = e.source();
+            while let Some(cause) = source {
+                eprintln!("  Caused by: {}", cause);
+                source = cause.source();
+            }
+
+            ExitCode::from(2)
+        },
+    }
+}
+
+/// Run a command, returning whether it succeeded.
+fn run(command: Command) -> Result<bool> {
+    match command {
+        Command::Insert {
+            after_heading,
+            snippet,
+            output,
+        } => {
+            let snippet = snippet.read()?;
+            edit_all(&output, |src| {
+                let mut rewriter =
+                    insert_after_heading(&after_heading, &snippet);
+                let markdown = splice(src, &mut rewriter);
+
+                if rewriter.inserted() == 0 {
+                    return Err(format!(
+                        "There is no \"{}\" heading",
+                        after_heading
+                    )
+                    .into());
+                }
+                Ok(markdown)
+            })
+        },
+        Command::ReplaceRegion {
+            name,
+            snippet,
+            output,
+        } => {
+            let snippet = snippet.read()?;
+            edit_all(&output, |src| replace_region(src, &name, &snippet))
+        },
+        Command::Toc {
+            min_level,
+            max_level,
+            region,
+            print,
+            output,
+        } => {
+            let toc = |src: &str| {
+                markedit::table_of_contents(events(src))
+                    .min_level(min_level)
+                    .max_level(max_level)
+                    .to_string()
+            };
+
+            if print {
+                for input in inputs::expand(&output.inputs)? {
+                    print!("{}", toc(&input.read()?));
+                }
+                Ok(true)
+            } else {
+                edit_all(&output, |src| replace_region(src, &region, &toc(src)))
+            }
+        },
+        Command::ShiftHeadings { by, output } => edit_all(&output, |src| {
+            Ok(splice(src, &mut markedit::shift_headings(by)))
+        }),
+        Command::Links {
+            command: LinksCommand::Check { inputs },
+        } => check_links(&inputs),
+        Command::Apply { rules, output } => {
+            let rules = Rules::load(&rules)?;
+            edit_all(&output, |src| Ok(splice(src, &mut rules.to_rewriter())))
+        },
+        Command::Fmt { output } => {
+            edit_all(&output, |src| Ok(to_markdown(src, events(src))))
+        },
+    }
+}
+
+/// Apply an edit to every input, then print, save, diff or check the
+/// results.
+fn edit_all<F>(output: &Output, mut edit: F) -> Result<bool>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let inputs = inputs::expand(&output.inputs)?;
+    let mut success = true;
+
+    if output.in_place && inputs.contains(&Input::Stdin) {
+        return Err("Stdin can't be edited in-place".into());
+    }
+
+    for input in inputs {
+        let original = input.read()?;
+        let edited = match edit(&original) {
+            Ok(edited) => edited,
+            Err(e) => {
+                eprintln!("{}: {}", input, e);
+                success = false;
+                continue;
+            },
+        };
+        let changed = edited != original;
+
+        if output.diff {
+            let diff = markedit::unified_diff(
+                &original,
+                &edited,
+                &input.diff_name("a"),
+                &input.diff_name("b"),
+            );
+            print!("{}", diff);
+        }
+
+        if output.check {
+            if changed {
+                eprintln!("{} would be changed", input);
+                success = false;
+            }
+        } else if output.in_place {
+            if changed {
+                input.write(&edited)?;
+            }
+        } else if !output.diff {
+            print!("{}", edited);
+        }
+    }
+
+    Ok(success)
+}
+
+fn check_links(patterns: &[String]) -> Result<bool> {
+    let mut success = true;
+
+    for input in inputs::expand(patterns)? {
+        let src = input.read()?;
+        // skip the front matter, remembering to adjust the spans
+        let body = Document::parse(&src).body();
+        let offset = src.len() - body.len();
+
+        let report = markedit::check_links(body, input.base_dir());
+
+        for diag in &report.diagnostics {
+            let (line, column) =
+                line_and_column(&src, offset + diag.span.start);
+            println!(
+                "{}:{}:{}: Broken link to \"{}\", {}",
+                input, line, column, diag.url, diag.problem
+            );
+            success = false;
+        }
+    }
+
+    Ok(success)
+}
+
+fn line_and_column(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |ix| ix + 1);
+    let column = before[line_start..].chars().count() + 1;
+
+    (line, column)
+}
+
+/// The extensions enabled when parsing a document.
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+/// Parse a document's body, ignoring any front matter.
+fn events(src: &str) -> impl Iterator<Item = Event<'_>> + '_ {
+    Document::parse(src).events(options())
+}
+
+/// Serialize a document's body, putting the original front matter back.
+fn to_markdown<'a, I>(src: &str, events: I) -> String
+where
+    I: IntoIterator<Item = Event<'a>>,
+{
+    Document::parse(src).to_markdown(events)
+}
+
+/// Rewrite a document's body, only reformatting the blocks which were
+/// changed and leaving the front matter alone.
+fn splice<'src, R>(src: &'src str, rewriter: &mut R) -> String
+where
+    R: Rewriter<'src>,
+{
+    let body = Document::parse(src).body();
+    let front_matter = &src[..src.len() - body.len()];
+
+    let events =
+        pulldown_cmark::Parser::new_ext(body, options()).into_offset_iter();
+    let edits = markedit::rewrite_to_edits(body, events, rewriter);
+
+    format!("{}{}", front_matter, edits.apply(body))
+}
+
+fn replace_region(src: &str, name: &str, markdown: &str) -> Result<String> {
+    let mut rewriter = markedit::replace_region(name, markdown);
+    let edited = splice(src, &mut rewriter);
+
+    if rewriter.replaced() == 0 {
+        return Err(format!(
+            "There is no \"<!-- begin {0} -->\" ... \"<!-- end {0} -->\" region",
+            name
+        )
+        .into());
+    }
+
+    Ok(edited)
+}
+
+/// Insert a snippet after any heading with the desired text.
+fn insert_after_heading<'src>(
+    heading: &str,
+    snippet: &'src str,
+) -> InsertAfterHeading<'src> {
+    let snippet = Document::parse(snippet).events(options()).collect();
+    markedit::insert_after_heading(heading, snippet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_after_the_matching_heading() {
+        let src = "# Title\n\n## Usage\n\nSome text.\n\n## Usage notes\n";
+
+        let mut rewriter =
+            insert_after_heading("Usage", "```console\n$ markedit\n```");
+        let got = splice(src, &mut rewriter);
+
+        assert_eq!(
+            got,
+            "# Title\n\n## Usage\n\n```console\n$ markedit\n```\n\nSome text.\n\n## Usage notes\n"
+        );
+        assert_eq!(rewriter.inserted(), 1);
+    }
+
+    #[test]
+    fn edits_leave_the_rest_of_the_document_alone() {
+        let src = "---\ntitle:   Notes\n---\nIntro\n=====\n\n* a\n* b\n\n<!-- begin x -->\n<!-- end x -->\n";
+
+        let got = replace_region(src, "x", "*New*").unwrap();
+
+        assert_eq!(
+            got,
+            "---\ntitle:   Notes\n---\nIntro\n=====\n\n* a\n* b\n\n<!-- begin x -->\n\n*New*\n\n<!-- end x -->\n"
+        );
+    }
+
+    #[test]
+    fn line_numbers_start_at_one() {
+        assert_eq!(line_and_column("abc\ndef", 0), (1, 1));
+        assert_eq!(line_and_column("abc\ndef", 5), (2, 2));
+    }
+}
@@ -0,0 +1,326 @@
+use crate::{
+    blocks::{self, Block, BlockKind},
+    diff::{self, Op},
+};
+use pulldown_cmark::Event;
+use std::fmt::{self, Display, Formatter};
+
+/// Compare two documents block by block, ignoring changes which only affect
+/// whitespace or line wrapping.
+///
+/// Blocks are aligned using the longest common subsequence, then any blocks
+/// which were removed from one place and added somewhere else are reported
+/// as moved, and removals followed by additions of the same kind of block
+/// are reported as modifications.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::DiffOp;
+///
+/// let old = "# Title\n\nSome text\nwrapped over two lines.\n\n## Usage\n\nRun it.\n";
+/// let new = "# Title\n\nSome text wrapped over two lines.\n\n## Usage\n\nRun it twice.\n";
+///
+/// let diff = markedit::diff(markedit::parse(old), markedit::parse(new));
+///
+/// let changes: Vec<_> = diff.changes().collect();
+/// assert_eq!(changes, &[&DiffOp::Modified { old: 3, new: 3 }]);
+/// assert_eq!(diff.old[3].heading_path, &["Title", "Usage"]);
+///
+/// assert_eq!(
+///     diff.to_string(),
+///     "Modified paragraph under \"Title > Usage\"\n  - Run it.\n  + Run it twice.\n",
+/// );
+/// ```
+pub fn diff<'src, O, N>(old: O, new: N) -> BlockDiff<'src>
+where
+    O: IntoIterator<Item = Event<'src>>,
+    N: IntoIterator<Item = Event<'src>>,
+{
+    let old = blocks::split_blocks(old);
+    let new = blocks::split_blocks(new);
+    let ops = align(&old, &new);
+
+    BlockDiff { old, new, ops }
+}
+
+/// The differences between two documents, as returned by [`diff()`].
+///
+/// Use [`Display`] to get a human-readable summary of the changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockDiff<'src> {
+    /// The blocks in the original document.
+    pub old: Vec<Block<'src>>,
+    /// The blocks in the new document.
+    pub new: Vec<Block<'src>>,
+    /// How to get from the old blocks to the new ones, in document order.
+    pub ops: Vec<DiffOp>,
+}
+
+impl<'src> BlockDiff<'src> {
+    /// Everything except the [`DiffOp::Unchanged`] blocks.
+    pub fn changes(&self) -> impl Iterator<Item = &DiffOp> + '_ {
+        self.ops
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Unchanged { .. }))
+    }
+
+    /// Are the two documents equivalent?
+    pub fn is_empty(&self) -> bool { self.changes().next().is_none() }
+}
+
+impl<'src> Display for BlockDiff<'src> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for op in self.changes() {
+            match *op {
+                DiffOp::Added { new } => {
+                    let block = &self.new[new];
+                    writeln!(f, "Added {}{}", block.kind, under(block))?;
+                    write_lines(f, "+", block)?;
+                },
+                DiffOp::Removed { old } => {
+                    let block = &self.old[old];
+                    writeln!(f, "Removed {}{}", block.kind, under(block))?;
+                    write_lines(f, "-", block)?;
+                },
+                DiffOp::Modified { old, new } => {
+                    let (old, new) = (&self.old[old], &self.new[new]);
+                    writeln!(f, "Modified {}{}", new.kind, under(new))?;
+                    write_lines(f, "-", old)?;
+                    write_lines(f, "+", new)?;
+                },
+                DiffOp::Moved { old, new } => {
+                    let (old, new) = (&self.old[old], &self.new[new]);
+                    if old.heading_path == new.heading_path {
+                        writeln!(f, "Moved {}{}", new.kind, under(new))?;
+                    } else {
+                        writeln!(
+                            f,
+                            "Moved {} from {} to {}",
+                            new.kind,
+                            path(old),
+                            path(new)
+                        )?;
+                    }
+                    write_lines(f, " ", new)?;
+                },
+                DiffOp::Unchanged { .. } => {},
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn path(block: &Block<'_>) -> String {
+    if block.heading_path.is_empty() {
+        String::from("the top level")
+    } else {
+        format!("\"{}\"", block.heading_path.join(" > "))
+    }
+}
+
+fn under(block: &Block<'_>) -> String {
+    if block.heading_path.is_empty() {
+        String::new()
+    } else {
+        format!(" under {}", path(block))
+    }
+}
+
+fn write_lines(
+    f: &mut Formatter<'_>,
+    prefix: &str,
+    block: &Block<'_>,
+) -> fmt::Result {
+    for line in block.markdown().lines() {
+        writeln!(f, "  {} {}", prefix, line)?;
+    }
+
+    Ok(())
+}
+
+/// A single step in a [`BlockDiff`], using indices into
+/// [`BlockDiff::old`] and [`BlockDiff::new`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DiffOp {
+    /// The block is the same in both documents.
+    Unchanged {
+        /// The block's index in the old document.
+        old: usize,
+        /// The block's index in the new document.
+        new: usize,
+    },
+    /// A block was added.
+    Added {
+        /// The block's index in the new document.
+        new: usize,
+    },
+    /// A block was removed.
+    Removed {
+        /// The block's index in the old document.
+        old: usize,
+    },
+    /// A block was changed.
+    Modified {
+        /// The block's index in the old document.
+        old: usize,
+        /// The block's index in the new document.
+        new: usize,
+    },
+    /// A block was moved somewhere else without being changed.
+    Moved {
+        /// The block's index in the old document.
+        old: usize,
+        /// The block's index in the new document.
+        new: usize,
+    },
+}
+
+/// How a removed or added block was paired up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Pairing {
+    Unpaired,
+    Modified(usize),
+    Moved(usize),
+}
+
+fn align(old: &[Block<'_>], new: &[Block<'_>]) -> Vec<DiffOp> {
+    let old_keys: Vec<&str> = old.iter().map(Block::key).collect();
+    let new_keys: Vec<&str> = new.iter().map(Block::key).collect();
+    let ops = diff::diff_lines(&old_keys, &new_keys);
+
+    let mut removed = vec![Pairing::Unpaired; old.len()];
+    let mut added = vec![Pairing::Unpaired; new.len()];
+
+    // anything removed in one place and added in another was moved
+    for op in &ops {
+        if let Op::Insert { new: n, .. } = *op {
+            let moved_from = ops.iter().find_map(|op| match *op {
+                Op::Delete { old: o, .. }
+                    if removed[o] == Pairing::Unpaired
+                        && old[o].same_content(&new[n]) =>
+                {
+                    Some(o)
+                },
+                _ => None,
+            });
+
+            if let Some(o) = moved_from {
+                removed[o] = Pairing::Moved(n);
+                added[n] = Pairing::Moved(o);
+            }
+        }
+    }
+
+    // within each run of changes, pair up removals and additions of the same
+    // kind of block
+    for run in ops.split(|op| matches!(op, Op::Equal { .. })) {
+        for op in run {
+            if let Op::Delete { old: o, .. } = *op {
+                if removed[o] != Pairing::Unpaired {
+                    continue;
+                }
+
+                let replacement = run.iter().find_map(|op| match *op {
+                    Op::Insert { new: n, .. }
+                        if added[n] == Pairing::Unpaired
+                            && comparable(old[o].kind, new[n].kind) =>
+                    {
+                        Some(n)
+                    },
+                    _ => None,
+                });
+
+                if let Some(n) = replacement {
+                    removed[o] = Pairing::Modified(n);
+                    added[n] = Pairing::Modified(o);
+                }
+            }
+        }
+    }
+
+    ops.iter()
+        .filter_map(|op| match *op {
+            Op::Equal { old, new } => Some(DiffOp::Unchanged { old, new }),
+            Op::Delete { old, .. } => match removed[old] {
+                Pairing::Unpaired => Some(DiffOp::Removed { old }),
+                Pairing::Modified(new) => Some(DiffOp::Modified { old, new }),
+                // moves are reported where the block ends up
+                Pairing::Moved(_) => None,
+            },
+            Op::Insert { new, .. } => match added[new] {
+                Pairing::Unpaired => Some(DiffOp::Added { new }),
+                Pairing::Moved(old) => Some(DiffOp::Moved { old, new }),
+                Pairing::Modified(_) => None,
+            },
+        })
+        .collect()
+}
+
+fn comparable(left: BlockKind, right: BlockKind) -> bool {
+    match (left, right) {
+        (BlockKind::Heading(_), BlockKind::Heading(_)) => true,
+        (left, right) => left == right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moved_sections() {
+        let old = "# A\n\nFirst\n\n# B\n\nSecond\n";
+        let new = "# B\n\nSecond\n\n# A\n\nFirst\n";
+
+        let diff = diff(crate::parse(old), crate::parse(new));
+
+        let changes: Vec<_> = diff.changes().copied().collect();
+        assert_eq!(
+            changes,
+            vec![
+                DiffOp::Moved { old: 2, new: 0 },
+                DiffOp::Moved { old: 3, new: 1 },
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "Moved h1\n    # B\nMoved paragraph under \"B\"\n    Second\n"
+        );
+    }
+
+    #[test]
+    fn added_removed_and_modified() {
+        let old =
+            "# Title\n\n## Old Name\n\nText\n\n```rust\nfn main() {}\n```\n";
+        let new = "# Title\n\n## New Name\n\nText\n\n- item\n";
+
+        let diff = diff(crate::parse(old), crate::parse(new));
+
+        let changes: Vec<_> = diff.changes().copied().collect();
+        assert_eq!(
+            changes,
+            vec![
+                DiffOp::Modified { old: 1, new: 1 },
+                DiffOp::Removed { old: 3 },
+                DiffOp::Added { new: 3 },
+            ]
+        );
+        assert!(diff.to_string().starts_with(
+            "Modified h2 under \"Title\"\n  - ## Old Name\n  + ## New Name\n"
+        ));
+    }
+
+    #[test]
+    fn code_block_whitespace_matters() {
+        let old = "```\nfn main() {\n    x\n}\n```\n";
+        let new = "```\nfn main() {\nx\n}\n```\n";
+
+        let changed = diff(crate::parse(old), crate::parse(new));
+        let same = diff(crate::parse(old), crate::parse(old));
+
+        assert!(!changed.is_empty());
+        assert!(same.is_empty());
+    }
+}
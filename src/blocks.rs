@@ -0,0 +1,219 @@
+use pulldown_cmark::{Event, Tag};
+use std::fmt::{self, Display, Formatter};
+
+/// A top-level block (paragraph, heading, list, etc.) from a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<'src> {
+    /// What sort of block this is.
+    pub kind: BlockKind,
+    /// The events making up this block.
+    pub events: Vec<Event<'src>>,
+    /// The text of the headings this block is nested under, outermost first.
+    pub heading_path: Vec<String>,
+    markdown: String,
+    key: String,
+}
+
+impl<'src> Block<'src> {
+    fn new(
+        kind: BlockKind,
+        events: Vec<Event<'src>>,
+        heading_path: Vec<String>,
+    ) -> Self {
+        let markdown = crate::to_markdown(&events).trim_end().to_string();
+
+        // whitespace inside code blocks is significant, but everywhere else
+        // reflowing text shouldn't count as a change
+        let key = match kind {
+            BlockKind::CodeBlock => markdown.clone(),
+            _ => markdown.split_whitespace().collect::<Vec<_>>().join(" "),
+        };
+
+        Block {
+            kind,
+            events,
+            heading_path,
+            markdown,
+            key,
+        }
+    }
+
+    /// The block, rendered as Markdown.
+    pub fn markdown(&self) -> &str { &self.markdown }
+
+    /// Are two blocks the same, ignoring differences in whitespace and line
+    /// breaks?
+    pub fn same_content(&self, other: &Block<'_>) -> bool {
+        self.key == other.key
+    }
+
+    /// The text used when comparing blocks.
+    pub(crate) fn key(&self) -> &str { &self.key }
+}
+
+/// The different kinds of [`Block`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    /// A paragraph.
+    Paragraph,
+    /// A heading with the given level.
+    Heading(u32),
+    /// A fenced or indented code block.
+    CodeBlock,
+    /// An ordered or unordered list.
+    List,
+    /// A block quote.
+    BlockQuote,
+    /// A table.
+    Table,
+    /// A footnote definition.
+    FootnoteDefinition,
+    /// A horizontal rule.
+    Rule,
+    /// Raw HTML, including comments.
+    Html,
+    /// Anything else.
+    Other,
+}
+
+impl Display for BlockKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockKind::Paragraph => write!(f, "paragraph"),
+            BlockKind::Heading(level) => write!(f, "h{}", level),
+            BlockKind::CodeBlock => write!(f, "code block"),
+            BlockKind::List => write!(f, "list"),
+            BlockKind::BlockQuote => write!(f, "block quote"),
+            BlockKind::Table => write!(f, "table"),
+            BlockKind::FootnoteDefinition => write!(f, "footnote definition"),
+            BlockKind::Rule => write!(f, "horizontal rule"),
+            BlockKind::Html => write!(f, "HTML"),
+            BlockKind::Other => write!(f, "block"),
+        }
+    }
+}
+
+fn kind_of(event: &Event<'_>) -> BlockKind {
+    match event {
+        Event::Start(Tag::Paragraph) => BlockKind::Paragraph,
+        Event::Start(Tag::Heading(level)) => BlockKind::Heading(*level),
+        Event::Start(Tag::CodeBlock(_)) => BlockKind::CodeBlock,
+        Event::Start(Tag::List(_)) => BlockKind::List,
+        Event::Start(Tag::BlockQuote) => BlockKind::BlockQuote,
+        Event::Start(Tag::Table(_)) => BlockKind::Table,
+        Event::Start(Tag::FootnoteDefinition(_)) => {
+            BlockKind::FootnoteDefinition
+        },
+        Event::Rule => BlockKind::Rule,
+        Event::Html(_) => BlockKind::Html,
+        _ => BlockKind::Other,
+    }
+}
+
+/// Break a document into its top-level [`Block`]s.
+pub(crate) fn split_blocks<'src, I>(events: I) -> Vec<Block<'src>>
+where
+    I: IntoIterator<Item = Event<'src>>,
+{
+    let mut blocks = Vec::new();
+    let mut headings: Vec<(u32, String)> = Vec::new();
+    let mut current: Vec<Event<'src>> = Vec::new();
+    let mut depth = 0;
+
+    for event in events {
+        // consecutive lines of HTML are all part of the same block
+        let continues_html = depth == 0
+            && matches!(event, Event::Html(_))
+            && matches!(current.first(), Some(Event::Html(_)));
+
+        if depth == 0 && !current.is_empty() && !continues_html {
+            push_block(
+                &mut blocks,
+                &mut headings,
+                std::mem::take(&mut current),
+            );
+        }
+
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {},
+        }
+
+        current.push(event);
+    }
+
+    if !current.is_empty() {
+        push_block(&mut blocks, &mut headings, current);
+    }
+
+    blocks
+}
+
+fn push_block<'src>(
+    blocks: &mut Vec<Block<'src>>,
+    headings: &mut Vec<(u32, String)>,
+    events: Vec<Event<'src>>,
+) {
+    let kind = kind_of(&events[0]);
+
+    if let BlockKind::Heading(level) = kind {
+        headings.retain(|(l, _)| *l < level);
+    }
+    let heading_path = headings.iter().map(|(_, text)| text.clone()).collect();
+
+    if let BlockKind::Heading(level) = kind {
+        headings.push((level, heading_text(&events)));
+    }
+
+    blocks.push(Block::new(kind, events, heading_path));
+}
+
+fn heading_text(events: &[Event<'_>]) -> String {
+    let text: String = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect();
+
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_know_which_section_they_are_in() {
+        let src = "# Title\n\nIntro\n\n## Usage\n\n- a\n- b\n\n<!-- x -->\n<!-- y -->\n\n## Other\n\n---\n";
+
+        let blocks = split_blocks(crate::parse(src));
+
+        let got: Vec<_> = blocks
+            .iter()
+            .map(|b| (b.kind, b.heading_path.join(" > ")))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                (BlockKind::Heading(1), String::new()),
+                (BlockKind::Paragraph, String::from("Title")),
+                (BlockKind::Heading(2), String::from("Title")),
+                (BlockKind::List, String::from("Title > Usage")),
+                (BlockKind::Html, String::from("Title > Usage")),
+                (BlockKind::Heading(2), String::from("Title")),
+                (BlockKind::Rule, String::from("Title > Other")),
+            ]
+        );
+    }
+
+    #[test]
+    fn reflowing_text_is_not_a_change() {
+        let first = split_blocks(crate::parse("Some\ntext  here.\n"));
+        let second = split_blocks(crate::parse("Some text\nhere.\n"));
+
+        assert!(first[0].same_content(&second[0]));
+    }
+}
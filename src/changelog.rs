@@ -0,0 +1,929 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    ops::Range,
+};
+
+/// The categories from [Keep a Changelog][kac], in the order they normally
+/// appear.
+///
+/// [kac]: https://keepachangelog.com/
+pub const CATEGORIES: [&str; 6] = [
+    "Added",
+    "Changed",
+    "Deprecated",
+    "Removed",
+    "Fixed",
+    "Security",
+];
+
+/// A `CHANGELOG.md` file in the [Keep a Changelog][kac] format.
+///
+/// Edits are made directly to the original text, so anything this type
+/// doesn't know about (an introduction, formatting, comments, etc.) is left
+/// untouched.
+///
+/// [kac]: https://keepachangelog.com/
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Changelog;
+///
+/// let src = concat!(
+///     "# Changelog\n\n",
+///     "## [Unreleased]\n\n",
+///     "## [0.1.0] - 2020-04-01\n\n",
+///     "### Added\n\n",
+///     "- Initial release\n\n",
+///     "[Unreleased]: https://github.com/me/project/compare/v0.1.0...HEAD\n",
+///     "[0.1.0]: https://github.com/me/project/releases/tag/v0.1.0\n",
+/// );
+///
+/// let mut changelog = Changelog::parse(src);
+/// changelog.add_entry("Fixed", "A nasty bug");
+/// changelog.cut_release("0.1.1", "2020-04-02").unwrap();
+///
+/// let expected = concat!(
+///     "# Changelog\n\n",
+///     "## [Unreleased]\n\n",
+///     "## [0.1.1] - 2020-04-02\n\n",
+///     "### Fixed\n\n",
+///     "- A nasty bug\n\n",
+///     "## [0.1.0] - 2020-04-01\n\n",
+///     "### Added\n\n",
+///     "- Initial release\n\n",
+///     "[Unreleased]: https://github.com/me/project/compare/v0.1.1...HEAD\n",
+///     "[0.1.1]: https://github.com/me/project/compare/v0.1.0...v0.1.1\n",
+///     "[0.1.0]: https://github.com/me/project/releases/tag/v0.1.0\n",
+/// );
+/// assert_eq!(changelog.to_string(), expected);
+/// assert!(changelog.validate().is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Changelog {
+    src: String,
+    title: Option<String>,
+    releases: Vec<Release>,
+    links: Vec<ReleaseLink>,
+}
+
+impl Changelog {
+    /// Parse a changelog.
+    pub fn parse<S: Into<String>>(src: S) -> Self {
+        let mut changelog = Changelog {
+            src: src.into(),
+            title: None,
+            releases: Vec::new(),
+            links: Vec::new(),
+        };
+        changelog.reparse();
+        changelog
+    }
+
+    /// The changelog's text.
+    pub fn as_str(&self) -> &str { &self.src }
+
+    /// The text from the changelog's level 1 heading.
+    pub fn title(&self) -> Option<&str> { self.title.as_deref() }
+
+    /// Every release, in the order they appear.
+    pub fn releases(&self) -> &[Release] { &self.releases }
+
+    /// The link reference definitions (e.g. `[1.0.0]: https://...`) at the
+    /// bottom of the changelog.
+    pub fn links(&self) -> &[ReleaseLink] { &self.links }
+
+    /// The `## [Unreleased]` section, if there is one.
+    pub fn unreleased(&self) -> Option<&Release> {
+        self.releases.iter().find(|r| r.is_unreleased())
+    }
+
+    /// Look up a release by version number.
+    pub fn release(&self, version: &str) -> Option<&Release> {
+        self.releases.iter().find(|r| r.version == version)
+    }
+
+    /// Add an entry to a category in the `## [Unreleased]` section.
+    ///
+    /// The `## [Unreleased]` and category headings are created if they don't
+    /// already exist, with categories being kept in the conventional order.
+    pub fn add_entry(&mut self, category: &str, entry: &str) {
+        if self.unreleased().is_none() {
+            self.insert_unreleased_section();
+        }
+
+        let release = self.unreleased().expect("Just inserted").clone();
+
+        match release.category(category) {
+            Some(existing) => match existing.list_end {
+                Some(end) => {
+                    let marker = match existing.marker {
+                        Some(ListMarker::Bullet(bullet)) => bullet.to_string(),
+                        Some(ListMarker::Ordered { start, delimiter }) => {
+                            let next = start + existing.entries.len() as u64;
+                            format!("{}{}", next, delimiter)
+                        },
+                        None => String::from("-"),
+                    };
+                    self.edit(end..end, &format!("\n{} {}", marker, entry));
+                },
+                None => {
+                    let end = existing.heading_span.end;
+                    self.edit(end..end, &format!("\n- {}\n", entry));
+                },
+            },
+            None => {
+                let text = format!("### {}\n\n- {}", category, entry);
+                let position = canonical_position(category);
+                let next_category = release
+                    .categories
+                    .iter()
+                    .find(|c| canonical_position(&c.name) > position);
+
+                match next_category {
+                    Some(next) => {
+                        let start = next.heading_span.start;
+                        self.edit(start..start, &format!("{}\n\n", text));
+                    },
+                    None => self.append_to(release.span, &text),
+                }
+            },
+        }
+    }
+
+    /// Turn the `## [Unreleased]` section into a release, adding a new (empty)
+    /// `## [Unreleased]` section above it.
+    ///
+    /// If there is an `[Unreleased]` link comparing the last release with
+    /// `HEAD` (e.g. `https://github.com/me/project/compare/v1.0.0...HEAD`),
+    /// it is updated and a compare link for the new release is added.
+    pub fn cut_release(
+        &mut self,
+        version: &str,
+        date: &str,
+    ) -> Result<(), ChangelogError> {
+        if self.release(version).is_some() {
+            return Err(ChangelogError::DuplicateVersion(version.to_string()));
+        }
+
+        let unreleased = self
+            .unreleased()
+            .ok_or(ChangelogError::NoUnreleasedSection)?
+            .heading_span
+            .clone();
+        let previous_version = self
+            .releases
+            .iter()
+            .find(|r| !r.is_unreleased())
+            .map(|r| r.version.clone());
+
+        // the links are at the bottom, so update them first to avoid
+        // invalidating the heading's location
+        self.update_compare_links(version, previous_version.as_deref());
+
+        let heading =
+            format!("## [Unreleased]\n\n## [{}] - {}\n", version, date);
+        self.edit(unreleased, &heading);
+
+        Ok(())
+    }
+
+    /// Check the changelog for common mistakes.
+    ///
+    /// Release links are only checked if the changelog has at least one link
+    /// definition.
+    pub fn validate(&self) -> Vec<ChangelogProblem> {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+
+        if self.title.is_none() {
+            problems.push(ChangelogProblem::MissingTitle);
+        }
+
+        for (i, release) in self.releases.iter().enumerate() {
+            let version = || release.version.clone();
+
+            if !seen.insert(release.version.to_lowercase()) {
+                problems.push(ChangelogProblem::DuplicateVersion(version()));
+            }
+
+            if release.is_unreleased() {
+                if i > 0 {
+                    problems.push(ChangelogProblem::UnreleasedNotFirst);
+                }
+            } else {
+                match release.date {
+                    Some(ref date) if !is_valid_date(date) => {
+                        problems.push(ChangelogProblem::InvalidDate {
+                            version: version(),
+                            date: date.clone(),
+                        })
+                    },
+                    Some(_) => {},
+                    None => {
+                        problems.push(ChangelogProblem::MissingDate(version()))
+                    },
+                }
+            }
+
+            for category in &release.categories {
+                if canonical_position(&category.name) == CATEGORIES.len() {
+                    problems.push(ChangelogProblem::UnknownCategory {
+                        version: version(),
+                        category: category.name.clone(),
+                    });
+                }
+                if category.entries.is_empty() {
+                    problems.push(ChangelogProblem::EmptyCategory {
+                        version: version(),
+                        category: category.name.clone(),
+                    });
+                }
+            }
+
+            let has_link = self
+                .links
+                .iter()
+                .any(|link| link.label.eq_ignore_ascii_case(&release.version));
+            if !self.links.is_empty() && !has_link {
+                problems.push(ChangelogProblem::MissingLink(version()));
+            }
+        }
+
+        let dated: Vec<_> = self
+            .releases
+            .iter()
+            .filter_map(|r| r.date.as_ref().map(|d| (r, d)))
+            .filter(|(_, date)| is_valid_date(date))
+            .collect();
+
+        for pair in dated.windows(2) {
+            let (newer, newer_date) = pair[0];
+            let (_, older_date) = pair[1];
+
+            if newer_date < older_date {
+                problems
+                    .push(ChangelogProblem::OutOfOrder(newer.version.clone()));
+            }
+        }
+
+        problems
+    }
+
+    fn insert_unreleased_section(&mut self) {
+        match self.releases.first() {
+            Some(first) => {
+                let start = first.heading_span.start;
+                self.edit(start..start, "## [Unreleased]\n\n");
+            },
+            None => {
+                let end = self.content_end();
+                self.append_to(0..end, "## [Unreleased]");
+            },
+        }
+    }
+
+    /// Add a block of text to the end of a section, separated from the
+    /// section's existing content by a blank line.
+    fn append_to(&mut self, section: Range<usize>, text: &str) {
+        let content = self.src[section.clone()].trim_end();
+        let end = section.start + content.len();
+
+        if content.is_empty() {
+            self.edit(end..end, &format!("{}\n", text));
+        } else if end == self.src.len() {
+            self.edit(end..end, &format!("\n\n{}\n", text));
+        } else {
+            self.edit(end..end, &format!("\n\n{}", text));
+        }
+    }
+
+    fn update_compare_links(
+        &mut self,
+        version: &str,
+        previous_version: Option<&str>,
+    ) {
+        let link = match self
+            .links
+            .iter()
+            .find(|link| link.label.eq_ignore_ascii_case("unreleased"))
+        {
+            Some(link) => link.clone(),
+            None => return,
+        };
+
+        let (base, previous_tag) = match link.url.rfind("/compare/") {
+            Some(ix) => match link.url[ix..].strip_prefix("/compare/") {
+                Some(rest) => match rest.strip_suffix("...HEAD") {
+                    Some(tag) => (&link.url[..ix], tag),
+                    None => return,
+                },
+                None => return,
+            },
+            None => return,
+        };
+
+        let prefix = match previous_version {
+            Some(v) if previous_tag.ends_with(v) => {
+                &previous_tag[..previous_tag.len() - v.len()]
+            },
+            _ if previous_tag.starts_with('v') => "v",
+            _ => "",
+        };
+        let tag = format!("{}{}", prefix, version);
+
+        let links = format!(
+            "[{label}]: {base}/compare/{tag}...HEAD\n[{version}]: {base}/compare/{previous}...{tag}",
+            label = link.label,
+            base = base,
+            tag = tag,
+            version = version,
+            previous = previous_tag,
+        );
+        self.edit(link.span, &links);
+    }
+
+    /// Where the releases end and the link definitions start.
+    fn content_end(&self) -> usize {
+        self.links
+            .first()
+            .map_or(self.src.len(), |link| link.span.start)
+    }
+
+    fn edit(&mut self, range: Range<usize>, replacement: &str) {
+        self.src.replace_range(range, replacement);
+        self.reparse();
+    }
+
+    fn reparse(&mut self) {
+        self.links = parse_link_definitions(&self.src);
+        self.title = None;
+        self.releases.clear();
+
+        let mut heading = None;
+        let mut heading_content: Option<Range<usize>> = None;
+        let mut list_depth = 0_usize;
+        let mut list_start = None;
+
+        for (event, range) in Parser::new(&self.src).into_offset_iter() {
+            let category = self
+                .releases
+                .last_mut()
+                .and_then(|release| release.categories.last_mut());
+
+            match event {
+                Event::Start(Tag::Heading(_)) => heading = Some(range),
+                Event::End(Tag::Heading(level)) => {
+                    let range = heading.take().unwrap_or(range);
+                    // only use the heading's contents, so we skip the `#`s
+                    // or setext underline
+                    let text = heading_content
+                        .take()
+                        .map(|content| self.src[content].trim().to_string())
+                        .unwrap_or_default();
+
+                    match level {
+                        1 if self.title.is_none() => self.title = Some(text),
+                        2 => self.releases.push(Release::new(&text, range)),
+                        3 => {
+                            if let Some(release) = self.releases.last_mut() {
+                                release.categories.push(Category {
+                                    name: text,
+                                    entries: Vec::new(),
+                                    span: range.clone(),
+                                    heading_span: range,
+                                    list_end: None,
+                                    marker: None,
+                                });
+                            }
+                        },
+                        _ => {},
+                    }
+                },
+                _ if heading.is_some() => {
+                    heading_content = Some(match heading_content.take() {
+                        Some(content) => content.start..range.end,
+                        None => range,
+                    });
+                },
+                Event::Start(Tag::List(start)) => {
+                    if list_depth == 0 {
+                        list_start = start;
+                    }
+                    list_depth += 1;
+                },
+                Event::End(Tag::List(_)) => {
+                    list_depth = list_depth.saturating_sub(1);
+
+                    if let (0, Some(category)) = (list_depth, category) {
+                        let list = self.src[range.clone()].trim_end();
+                        category.list_end = Some(range.start + list.len());
+                    }
+                },
+                Event::Start(Tag::Item) if list_depth == 1 => {
+                    if let Some(category) = category {
+                        let (marker, text) =
+                            split_item(&self.src[range], list_start);
+                        category.marker.get_or_insert(marker);
+                        category.entries.push(text);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        self.calculate_spans();
+    }
+
+    /// Each section ends where the next one starts.
+    fn calculate_spans(&mut self) {
+        let mut end = self.content_end();
+
+        for release in self.releases.iter_mut().rev() {
+            if end < release.heading_span.end {
+                // the link definitions weren't at the bottom
+                end = self.src.len();
+            }
+            release.span = release.heading_span.start..end;
+
+            let mut category_end = end;
+            for category in release.categories.iter_mut().rev() {
+                category.span = category.heading_span.start..category_end;
+                category_end = category.heading_span.start;
+            }
+
+            end = release.heading_span.start;
+        }
+    }
+}
+
+impl Display for Changelog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.src)
+    }
+}
+
+/// A single `## [version] - date` section in a [`Changelog`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    /// The version number, or `Unreleased`.
+    pub version: String,
+    /// When the version was released.
+    pub date: Option<String>,
+    /// Was the release marked as `[YANKED]`?
+    pub yanked: bool,
+    /// The `### Added`, `### Fixed`, etc. sections.
+    pub categories: Vec<Category>,
+    /// The location of the entire release, from the start of its heading to
+    /// the start of the next release.
+    pub span: Range<usize>,
+    /// The location of the release's heading.
+    pub heading_span: Range<usize>,
+}
+
+impl Release {
+    fn new(heading: &str, heading_span: Range<usize>) -> Self {
+        let (version, rest) = match heading.strip_prefix('[') {
+            Some(rest) => match rest.find(']') {
+                Some(ix) => (&rest[..ix], &rest[ix + 1..]),
+                None => (rest, ""),
+            },
+            None => match heading.find(char::is_whitespace) {
+                Some(ix) => (&heading[..ix], &heading[ix..]),
+                None => (heading, ""),
+            },
+        };
+
+        let rest = rest.trim().trim_start_matches(['-', '–', '—']).trim();
+        let yanked = rest.contains("[YANKED]");
+        let date = rest.split_whitespace().next().filter(|d| *d != "[YANKED]");
+
+        Release {
+            version: version.trim().to_string(),
+            date: date.map(String::from),
+            yanked,
+            categories: Vec::new(),
+            span: heading_span.clone(),
+            heading_span,
+        }
+    }
+
+    /// Is this the `## [Unreleased]` section?
+    pub fn is_unreleased(&self) -> bool {
+        self.version.eq_ignore_ascii_case("unreleased")
+    }
+
+    /// Look up a category by name (e.g. `Added` or `Fixed`).
+    pub fn category(&self, name: &str) -> Option<&Category> {
+        self.categories
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A `### Category` section in a [`Release`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Category {
+    /// The category's name (e.g. `Added`).
+    pub name: String,
+    /// The text for each entry, without the list marker.
+    pub entries: Vec<String>,
+    /// The location of the entire category.
+    pub span: Range<usize>,
+    /// The location of the category's heading.
+    pub heading_span: Range<usize>,
+    /// Where the category's list ends, ignoring trailing whitespace.
+    list_end: Option<usize>,
+    marker: Option<ListMarker>,
+}
+
+/// How the items in a [`Category`]'s list are marked.
+#[derive(Debug, Clone, PartialEq)]
+enum ListMarker {
+    /// A `-`, `*`, or `+`.
+    Bullet(char),
+    /// A number followed by a `.` or `)`.
+    Ordered { start: u64, delimiter: char },
+}
+
+/// A link reference definition (e.g. `[1.0.0]: https://...`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseLink {
+    /// The link's label (normally a version number or `Unreleased`).
+    pub label: String,
+    /// Where the link points to.
+    pub url: String,
+    /// The location of the definition, not including the trailing newline.
+    pub span: Range<usize>,
+}
+
+/// The reasons a [`Changelog`] edit may fail.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChangelogError {
+    /// There is no `## [Unreleased]` section to turn into a release.
+    NoUnreleasedSection,
+    /// A release with this version already exists.
+    DuplicateVersion(String),
+}
+
+impl Display for ChangelogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangelogError::NoUnreleasedSection => {
+                write!(f, "There is no \"Unreleased\" section")
+            },
+            ChangelogError::DuplicateVersion(version) => {
+                write!(f, "Version {} has already been released", version)
+            },
+        }
+    }
+}
+
+impl Error for ChangelogError {}
+
+/// Problems found by [`Changelog::validate()`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChangelogProblem {
+    /// There is no `# Changelog` heading.
+    MissingTitle,
+    /// The `## [Unreleased]` section should come before every release.
+    UnreleasedNotFirst,
+    /// The same version appears more than once.
+    DuplicateVersion(String),
+    /// A release doesn't say when it was released.
+    MissingDate(String),
+    /// A release date isn't in `YYYY-MM-DD` format.
+    InvalidDate {
+        /// The release's version.
+        version: String,
+        /// The date, as written.
+        date: String,
+    },
+    /// A release is dated before the release which comes after it.
+    OutOfOrder(String),
+    /// A category isn't one of the [`CATEGORIES`] defined by Keep a
+    /// Changelog.
+    UnknownCategory {
+        /// The release's version.
+        version: String,
+        /// The category's name.
+        category: String,
+    },
+    /// A category doesn't have any entries.
+    EmptyCategory {
+        /// The release's version.
+        version: String,
+        /// The category's name.
+        category: String,
+    },
+    /// There is no link definition for a release.
+    MissingLink(String),
+}
+
+impl Display for ChangelogProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangelogProblem::MissingTitle => {
+                write!(f, "The changelog has no title")
+            },
+            ChangelogProblem::UnreleasedNotFirst => {
+                write!(f, "The \"Unreleased\" section should come first")
+            },
+            ChangelogProblem::DuplicateVersion(version) => {
+                write!(f, "Version {} appears more than once", version)
+            },
+            ChangelogProblem::MissingDate(version) => {
+                write!(f, "Version {} has no release date", version)
+            },
+            ChangelogProblem::InvalidDate { version, date } => write!(
+                f,
+                "Version {} has an invalid release date, \"{}\" (expected YYYY-MM-DD)",
+                version, date
+            ),
+            ChangelogProblem::OutOfOrder(version) => write!(
+                f,
+                "Version {} was released before the version after it",
+                version
+            ),
+            ChangelogProblem::UnknownCategory { version, category } => write!(
+                f,
+                "Version {} has an unknown category, \"{}\"",
+                version, category
+            ),
+            ChangelogProblem::EmptyCategory { version, category } => write!(
+                f,
+                "The \"{}\" category in version {} is empty",
+                category, version
+            ),
+            ChangelogProblem::MissingLink(version) => {
+                write!(f, "Version {} has no link", version)
+            },
+        }
+    }
+}
+
+fn canonical_position(category: &str) -> usize {
+    CATEGORIES
+        .iter()
+        .position(|c| c.eq_ignore_ascii_case(category.trim()))
+        .unwrap_or(CATEGORIES.len())
+}
+
+/// Split a list item's source into its marker and text, where `start` is the
+/// first number in an ordered list.
+fn split_item(src: &str, start: Option<u64>) -> (ListMarker, String) {
+    let src = src.trim();
+    let rest = src.trim_start_matches(|c: char| c.is_ascii_digit());
+
+    let (marker, text) = match (start, rest.chars().next()) {
+        (Some(start), Some(delimiter)) => {
+            (ListMarker::Ordered { start, delimiter }, &rest[1..])
+        },
+        (None, Some(bullet)) => (ListMarker::Bullet(bullet), &rest[1..]),
+        (_, None) => (ListMarker::Bullet('-'), rest),
+    };
+
+    (marker, text.trim().to_string())
+}
+
+fn parse_link_definitions(src: &str) -> Vec<ReleaseLink> {
+    let mut links = Vec::new();
+    let mut offset = 0;
+
+    for line in src.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+
+        let content = line.trim_end();
+        let trimmed = content.trim_start();
+        if content.len() - trimmed.len() > 3 {
+            continue;
+        }
+
+        let (label, rest) = match trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.find("]:").map(|ix| rest.split_at(ix)))
+        {
+            Some(split) => split,
+            None => continue,
+        };
+
+        if let Some(url) = rest[2..].split_whitespace().next() {
+            links.push(ReleaseLink {
+                label: label.to_string(),
+                url: url.to_string(),
+                span: start..start + content.len(),
+            });
+        }
+    }
+
+    links
+}
+
+fn is_valid_date(date: &str) -> bool {
+    let parts: Vec<_> = date.split('-').collect();
+
+    match parts.as_slice() {
+        [year, month, day] => {
+            let is_number = |s: &str, len: usize| {
+                s.len() == len && s.chars().all(|c| c.is_ascii_digit())
+            };
+            is_number(year, 4)
+                && is_number(month, 2)
+                && is_number(day, 2)
+                && (1..=12).contains(&month.parse::<u32>().unwrap_or(0))
+                && (1..=31).contains(&day.parse::<u32>().unwrap_or(0))
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_releases_and_categories() {
+        let src = "# Changelog
+
+Some introduction.
+
+## [Unreleased]
+
+### Fixed
+
+* One
+* Two
+  continued
+
+## [1.0.0] - 2020-01-01 [YANKED]
+### Added
+- x
+";
+        let changelog = Changelog::parse(src);
+
+        assert_eq!(changelog.title(), Some("Changelog"));
+        let releases = changelog.releases();
+        assert_eq!(releases.len(), 2);
+        assert!(releases[0].is_unreleased());
+        let fixed = releases[0].category("fixed").unwrap();
+        assert_eq!(fixed.entries, vec!["One", "Two\n  continued"]);
+        assert_eq!(releases[1].version, "1.0.0");
+        assert_eq!(releases[1].date.as_deref(), Some("2020-01-01"));
+        assert!(releases[1].yanked);
+        assert_eq!(
+            &src[releases[1].span.clone()],
+            "## [1.0.0] - 2020-01-01 [YANKED]\n### Added\n- x\n"
+        );
+    }
+
+    #[test]
+    fn add_entries_creating_sections_in_order() {
+        let src = "# Changelog
+
+## [1.0.0] - 2020-01-01
+
+### Added
+
+- x
+";
+        let mut changelog = Changelog::parse(src);
+
+        changelog.add_entry("Fixed", "a bug");
+        changelog.add_entry("Added", "a feature");
+        changelog.add_entry("Fixed", "another bug");
+
+        assert_eq!(
+            changelog.as_str(),
+            "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- a feature
+
+### Fixed
+
+- a bug
+- another bug
+
+## [1.0.0] - 2020-01-01
+
+### Added
+
+- x
+"
+        );
+    }
+
+    #[test]
+    fn add_an_entry_to_an_empty_changelog() {
+        let mut changelog = Changelog::parse("# Changelog\n");
+
+        changelog.add_entry("Added", "Everything");
+
+        assert_eq!(
+            changelog.as_str(),
+            "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Everything\n"
+        );
+    }
+
+    #[test]
+    fn ordered_lists_get_the_next_number() {
+        let src = "## [Unreleased]\n\n### Added\n\n1. First\n2. Second\n\n### Fixed\n\n3) Third\n";
+        let mut changelog = Changelog::parse(src);
+
+        changelog.add_entry("Added", "Third thing");
+        changelog.add_entry("Fixed", "Fourth");
+
+        assert_eq!(
+            changelog.as_str(),
+            "## [Unreleased]\n\n### Added\n\n1. First\n2. Second\n3. Third thing\n\n### Fixed\n\n3) Third\n4) Fourth\n"
+        );
+        assert_eq!(
+            changelog.unreleased().unwrap().category("Added").unwrap().entries,
+            vec!["First", "Second", "Third thing"]
+        );
+    }
+
+    #[test]
+    fn setext_headings() {
+        let src = "Changelog\n=========\n\n[Unreleased]\n------------\n\n### Added\n\n- x\n";
+        let changelog = Changelog::parse(src);
+
+        assert_eq!(changelog.title(), Some("Changelog"));
+        let release = &changelog.releases()[0];
+        assert!(release.is_unreleased());
+        assert_eq!(release.category("Added").unwrap().entries, vec!["x"]);
+    }
+
+    #[test]
+    fn cutting_a_release_needs_an_unreleased_section() {
+        let mut changelog =
+            Changelog::parse("# Changelog\n\n## [1.0.0] - 2020-01-01\n");
+
+        assert_eq!(
+            changelog.cut_release("1.0.1", "2020-01-02"),
+            Err(ChangelogError::NoUnreleasedSection)
+        );
+        changelog.add_entry("Fixed", "x");
+        assert_eq!(
+            changelog.cut_release("1.0.0", "2020-01-02"),
+            Err(ChangelogError::DuplicateVersion(String::from("1.0.0")))
+        );
+    }
+
+    #[test]
+    fn validate_a_messy_changelog() {
+        let src = "## [1.1.0] - 2020-01-01
+
+### Improved
+
+- x
+
+### Fixed
+
+## [Unreleased]
+
+## [1.0.0] - 2020-02-30
+
+## [0.9.0]
+
+## [0.9.0] - 20/01/2019
+
+[1.1.0]: https://example.com/
+";
+        let problems = Changelog::parse(src).validate();
+
+        assert_eq!(
+            problems,
+            vec![
+                ChangelogProblem::MissingTitle,
+                ChangelogProblem::UnknownCategory {
+                    version: String::from("1.1.0"),
+                    category: String::from("Improved"),
+                },
+                ChangelogProblem::EmptyCategory {
+                    version: String::from("1.1.0"),
+                    category: String::from("Fixed"),
+                },
+                ChangelogProblem::UnreleasedNotFirst,
+                ChangelogProblem::MissingLink(String::from("Unreleased")),
+                ChangelogProblem::MissingLink(String::from("1.0.0")),
+                ChangelogProblem::MissingDate(String::from("0.9.0")),
+                ChangelogProblem::MissingLink(String::from("0.9.0")),
+                ChangelogProblem::DuplicateVersion(String::from("0.9.0")),
+                ChangelogProblem::InvalidDate {
+                    version: String::from("0.9.0"),
+                    date: String::from("20/01/2019"),
+                },
+                ChangelogProblem::MissingLink(String::from("0.9.0")),
+                ChangelogProblem::OutOfOrder(String::from("1.1.0")),
+            ]
+        );
+    }
+}
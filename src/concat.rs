@@ -0,0 +1,248 @@
+use crate::{slugs::Slugger, split, Link, Pipeline, TableOfContents, Writer};
+use pulldown_cmark::{CowStr, Event, Tag};
+use std::collections::{HashMap, HashSet};
+
+/// Combine several documents into one, the reverse of
+/// [`split_by_heading()`][crate::split_by_heading].
+///
+/// Each document is added under a level 1 heading with its title, and its
+/// own headings are moved down a level using
+/// [`shift_headings()`][crate::shift_headings]. Along the way,
+///
+/// - headings get anchors which are unique across the whole document, and
+///   `#anchor` links are updated to match
+/// - footnotes which would collide with another document's footnotes are
+///   renamed (e.g. `[^note]` becomes `[^note-1]`)
+///
+/// Link reference definitions don't need any special handling because
+/// their URLs are already attached to each link, and
+/// [`to_markdown()`][crate::to_markdown] numbers any labels which are used
+/// for different URLs.
+///
+/// # Examples
+///
+/// ```rust
+/// let install = "# Requirements\n\nSee [below](#usage).\n\n# Usage\n\nRun the installer.\n";
+/// let usage = "# Usage\n\nRun `app`, as described in [usage](#usage).\n";
+///
+/// let combined = markedit::concat_documents(vec![
+///     ("Installation", markedit::parse(install)),
+///     ("Usage", markedit::parse(usage)),
+/// ])
+/// .with_table_of_contents(2);
+///
+/// assert_eq!(
+///     combined.to_markdown(),
+///     "- [Installation](#installation)\n  - [Requirements](#requirements)\n  - [Usage](#usage)\n- [Usage](#usage-1)\n  - [Usage](#usage-2)\n\
+///      \n# Installation\n\n## Requirements\n\nSee [below](#usage).\n\n## Usage\n\nRun the installer.\n\
+///      \n# Usage\n\n## Usage\n\nRun `app`, as described in [usage](#usage-2).\n",
+/// );
+/// ```
+pub fn concat_documents<'src, D, T, E>(documents: D) -> Concatenated<'src>
+where
+    D: IntoIterator<Item = (T, E)>,
+    T: AsRef<str>,
+    E: IntoIterator<Item = Event<'src>>,
+{
+    let mut anchors = Slugger::default();
+    let mut footnotes = HashSet::new();
+    let mut events = Vec::new();
+
+    for (title, document) in documents {
+        let title = title.as_ref();
+        let document: Vec<Event<'src>> = document.into_iter().collect();
+
+        let heading = Tag::Heading(1);
+        events.push(Event::Start(heading.clone()));
+        events.push(Event::Text(CowStr::from(title.to_string())));
+        events.push(Event::End(heading));
+        anchors.slug(title);
+
+        // the anchors this document used on its own, and what they are now
+        let mut original_anchors = Slugger::default();
+        let renamed_anchors: HashMap<String, String> =
+            split::headings(&document)
+                .iter()
+                .map(|(_, text)| {
+                    (original_anchors.slug(text), anchors.slug(text))
+                })
+                .collect();
+
+        let renamed_footnotes = rename_footnotes(&document, &mut footnotes);
+
+        let rewriter = Pipeline::new()
+            .with(crate::shift_headings(1))
+            .with(crate::rewrite_links(move |link: &mut Link<'src>| {
+                let renamed = link
+                    .url
+                    .strip_prefix('#')
+                    .and_then(|anchor| renamed_anchors.get(anchor));
+
+                if let Some(anchor) = renamed {
+                    link.url = format!("#{}", anchor).into();
+                }
+            }))
+            .with(move |event: Event<'src>, writer: &mut Writer<'src>| {
+                let rename = |label: CowStr<'src>| match renamed_footnotes
+                    .get(label.as_ref())
+                {
+                    Some(new_label) => CowStr::from(new_label.clone()),
+                    None => label,
+                };
+
+                let renamed = match event {
+                    Event::FootnoteReference(label) => {
+                        Event::FootnoteReference(rename(label))
+                    },
+                    Event::Start(Tag::FootnoteDefinition(label)) => {
+                        Event::Start(Tag::FootnoteDefinition(rename(label)))
+                    },
+                    Event::End(Tag::FootnoteDefinition(label)) => {
+                        Event::End(Tag::FootnoteDefinition(rename(label)))
+                    },
+                    other => return writer.pass_through(other),
+                };
+                writer.push(renamed);
+            });
+
+        events.extend(crate::rewrite(document, rewriter));
+    }
+
+    Concatenated { events }
+}
+
+/// Find new names for any footnotes which have already been used by another
+/// document.
+fn rename_footnotes(
+    events: &[Event<'_>],
+    used: &mut HashSet<String>,
+) -> HashMap<String, String> {
+    let mut labels: Vec<&str> = Vec::new();
+    for event in events {
+        if let Event::FootnoteReference(label)
+        | Event::Start(Tag::FootnoteDefinition(label)) = event
+        {
+            if !labels.contains(&label.as_ref()) {
+                labels.push(label.as_ref());
+            }
+        }
+    }
+
+    let mut renamed = HashMap::new();
+    // labels from this document which we'll need to reserve, including
+    // unchanged ones so nothing gets renamed to them
+    let mut taken: Vec<String> = labels
+        .iter()
+        .filter(|label| !used.contains(**label))
+        .map(|label| label.to_string())
+        .collect();
+
+    for label in labels {
+        if used.contains(label) {
+            let new_label = (1..)
+                .map(|n| format!("{}-{}", label, n))
+                .find(|candidate| {
+                    !used.contains(candidate) && !taken.contains(candidate)
+                })
+                .expect("there are infinitely many candidates");
+
+            taken.push(new_label.clone());
+            renamed.insert(label.to_string(), new_label);
+        }
+    }
+
+    used.extend(taken);
+    renamed
+}
+
+/// Several documents combined by [`concat_documents()`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Concatenated<'src> {
+    /// The combined document.
+    pub events: Vec<Event<'src>>,
+}
+
+impl<'src> Concatenated<'src> {
+    /// The table of contents for the combined document.
+    pub fn table_of_contents(&self) -> TableOfContents {
+        crate::table_of_contents(&self.events)
+    }
+
+    /// Insert a table of contents at the start of the document, including
+    /// headings down to `max_level` (e.g. `1` to only list each document's
+    /// title).
+    pub fn with_table_of_contents(mut self, max_level: u32) -> Self {
+        let toc = self.table_of_contents().max_level(max_level).to_string();
+        let mut events: Vec<Event<'src>> = crate::parse(&toc)
+            .map(crate::rewriters::owned_event)
+            .collect();
+
+        events.append(&mut self.events);
+        Concatenated { events }
+    }
+
+    /// Render the combined document as Markdown.
+    pub fn to_markdown(&self) -> String { crate::to_markdown(&self.events) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Options;
+
+    #[test]
+    fn colliding_footnotes_are_renamed() {
+        let first = "Text[^note].\n\n[^note]: First.\n";
+        let second = "More[^note] and[^note-1].\n\n[^note]: Second.\n\n[^note-1]: Third.\n";
+
+        let combined = concat_documents(vec![
+            (
+                "A",
+                crate::parse_with_options(first, Options::ENABLE_FOOTNOTES),
+            ),
+            (
+                "B",
+                crate::parse_with_options(second, Options::ENABLE_FOOTNOTES),
+            ),
+        ]);
+
+        assert_eq!(
+            combined.to_markdown(),
+            "# A\n\nText[^note].\n\n[^note]: First.\n\n# B\n\nMore[^note-2] and[^note-1].\n\n[^note-2]: Second.\n\n[^note-1]: Third.\n"
+        );
+    }
+
+    #[test]
+    fn reference_definitions_do_not_collide() {
+        let first = "See [docs].\n\n[docs]: https://example.com/a\n";
+        let second = "See [docs].\n\n[docs]: https://example.com/b\n";
+
+        let combined = concat_documents(vec![
+            ("A", crate::parse(first)),
+            ("B", crate::parse(second)),
+        ]);
+
+        assert_eq!(
+            combined.to_markdown(),
+            "# A\n\nSee [docs].\n\n# B\n\nSee [docs][1].\n\n[docs]: https://example.com/a\n[1]: https://example.com/b\n"
+        );
+    }
+
+    #[test]
+    fn headings_are_nested_under_the_title() {
+        let doc = "# Intro\n\n## Details\n";
+
+        let combined = concat_documents(Some(("Chapter 1", crate::parse(doc))));
+
+        let toc = combined.table_of_contents();
+        let levels: Vec<_> = toc
+            .entries
+            .iter()
+            .map(|e| (e.level, e.slug.as_str()))
+            .collect();
+        assert_eq!(
+            levels,
+            vec![(1, "chapter-1"), (2, "intro"), (3, "details")]
+        );
+    }
+}
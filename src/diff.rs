@@ -0,0 +1,302 @@
+use std::fmt::Write;
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+/// The largest table [`diff_lines()`] will use to find the longest common
+/// subsequence (about 64 MB), so huge inputs can't exhaust memory.
+const MAX_TABLE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Generate a unified diff (like `diff -u`) showing how to get from
+/// `original` to `modified`.
+///
+/// An empty string is returned when the two texts are identical.
+///
+/// # Examples
+///
+/// ```rust
+/// let original = "# Title\n\nSome text.\n";
+/// let modified = "# Title\n\nSome new text.\n";
+///
+/// let diff = markedit::unified_diff(original, modified, "a/README.md", "b/README.md");
+///
+/// assert_eq!(
+///     diff,
+///     "--- a/README.md\n+++ b/README.md\n@@ -1,3 +1,3 @@\n # Title\n \n-Some text.\n+Some new text.\n",
+/// );
+/// ```
+pub fn unified_diff(
+    original: &str,
+    modified: &str,
+    original_name: &str,
+    modified_name: &str,
+) -> String {
+    if original == modified {
+        return String::new();
+    }
+
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = modified.split_inclusive('\n').collect();
+    let ops = diff_lines(&old, &new);
+
+    let mut diff = String::new();
+    let _ = writeln!(diff, "--- {}", original_name);
+    let _ = writeln!(diff, "+++ {}", modified_name);
+
+    for hunk in hunks(&ops) {
+        write_hunk(&mut diff, &ops[hunk], &old, &new);
+    }
+
+    diff
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Op {
+    Equal { old: usize, new: usize },
+    Delete { old: usize, new: usize },
+    Insert { old: usize, new: usize },
+}
+
+impl Op {
+    /// The position in the original and modified text before this operation
+    /// is applied.
+    pub(crate) fn position(self) -> (usize, usize) {
+        match self {
+            Op::Equal { old, new }
+            | Op::Delete { old, new }
+            | Op::Insert { old, new } => (old, new),
+        }
+    }
+}
+
+/// Find the longest common subsequence of lines and turn it into a list of
+/// operations.
+///
+/// Matching lines at the start and end are skipped first. If what remains is
+/// too big to compare (see [`MAX_TABLE_SIZE`]), it is reported as one big
+/// deletion and insertion rather than a minimal diff.
+pub(crate) fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let table_size =
+        (old_middle.len() + 1).saturating_mul(new_middle.len() + 1);
+    if table_size > MAX_TABLE_SIZE {
+        return replace_everything(old.len(), new.len(), prefix, suffix);
+    }
+
+    // lengths[i][j] is the length of the LCS of old_middle[i..] and
+    // new_middle[j..]
+    let width = new_middle.len() + 1;
+    let mut lengths = vec![0_u32; (old_middle.len() + 1) * width];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops: Vec<Op> =
+        (0..prefix).map(|i| Op::Equal { old: i, new: i }).collect();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old_middle.len() || j < new_middle.len() {
+        let (old_ix, new_ix) = (prefix + i, prefix + j);
+
+        if i < old_middle.len()
+            && j < new_middle.len()
+            && old_middle[i] == new_middle[j]
+        {
+            ops.push(Op::Equal {
+                old: old_ix,
+                new: new_ix,
+            });
+            i += 1;
+            j += 1;
+        } else if j < new_middle.len()
+            && (i == old_middle.len()
+                || lengths[i * width + j + 1] >= lengths[(i + 1) * width + j])
+        {
+            ops.push(Op::Insert {
+                old: old_ix,
+                new: new_ix,
+            });
+            j += 1;
+        } else {
+            ops.push(Op::Delete {
+                old: old_ix,
+                new: new_ix,
+            });
+            i += 1;
+        }
+    }
+
+    let (old_end, new_end) = (prefix + i, prefix + j);
+    ops.extend((0..suffix).map(|k| Op::Equal {
+        old: old_end + k,
+        new: new_end + k,
+    }));
+
+    // make sure deletions come before insertions in each run of changes
+    for run in ops.split_mut(|op| matches!(op, Op::Equal { .. })) {
+        run.sort_by_key(|op| matches!(op, Op::Insert { .. }));
+    }
+
+    ops
+}
+
+/// Delete everything between the common prefix and suffix, then insert the
+/// new lines.
+fn replace_everything(
+    old_len: usize,
+    new_len: usize,
+    prefix: usize,
+    suffix: usize,
+) -> Vec<Op> {
+    let (old_end, new_end) = (old_len - suffix, new_len - suffix);
+
+    let mut ops: Vec<Op> =
+        (0..prefix).map(|i| Op::Equal { old: i, new: i }).collect();
+    ops.extend((prefix..old_end).map(|old| Op::Delete { old, new: prefix }));
+    ops.extend((prefix..new_end).map(|new| Op::Insert { old: old_end, new }));
+    ops.extend((0..suffix).map(|k| Op::Equal {
+        old: old_end + k,
+        new: new_end + k,
+    }));
+
+    ops
+}
+
+/// Group changes into hunks, each with up to [`CONTEXT`] lines of unchanged
+/// text on either side.
+fn hunks(ops: &[Op]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+
+    for (ix, op) in ops.iter().enumerate() {
+        if let Op::Equal { .. } = op {
+            continue;
+        }
+
+        let start = ix.saturating_sub(CONTEXT);
+        let end = (ix + 1 + CONTEXT).min(ops.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    hunks
+}
+
+fn write_hunk(diff: &mut String, ops: &[Op], old: &[&str], new: &[&str]) {
+    let old_count = ops
+        .iter()
+        .filter(|op| !matches!(op, Op::Insert { .. }))
+        .count();
+    let new_count = ops
+        .iter()
+        .filter(|op| !matches!(op, Op::Delete { .. }))
+        .count();
+
+    // sorting deletions before insertions means the positions may not be
+    // monotonic, so use the smallest
+    let old_start = ops.iter().map(|op| op.position().0).min().unwrap_or(0);
+    let new_start = ops.iter().map(|op| op.position().1).min().unwrap_or(0);
+    let line_number = |start: usize, count: usize| {
+        if count == 0 {
+            start
+        } else {
+            start + 1
+        }
+    };
+
+    let _ = writeln!(
+        diff,
+        "@@ -{},{} +{},{} @@",
+        line_number(old_start, old_count),
+        old_count,
+        line_number(new_start, new_count),
+        new_count
+    );
+
+    for op in ops {
+        let (prefix, line) = match *op {
+            Op::Equal { old: ix, .. } => (' ', old[ix]),
+            Op::Delete { old: ix, .. } => ('-', old[ix]),
+            Op::Insert { new: ix, .. } => ('+', new[ix]),
+        };
+
+        diff.push(prefix);
+        diff.push_str(line);
+        if !line.ends_with('\n') {
+            diff.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separate_hunks_for_distant_changes() {
+        let original: String = (1..=12).map(|i| format!("{}\n", i)).collect();
+        let modified: String = (1..=12)
+            .filter(|&i| i != 11)
+            .map(|i| {
+                if i == 2 {
+                    String::from("two\n")
+                } else {
+                    format!("{}\n", i)
+                }
+            })
+            .collect();
+
+        let got = unified_diff(&original, &modified, "a", "b");
+
+        assert_eq!(
+            got,
+            "--- a\n+++ b\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n\
+             @@ -8,5 +8,4 @@\n 8\n 9\n 10\n-11\n 12\n"
+        );
+    }
+
+    #[test]
+    fn huge_changes_are_replaced_wholesale() {
+        let old: Vec<String> = (0..5000).map(|i| format!("{}\n", i)).collect();
+        let new: Vec<String> = (0..5000).map(|i| format!("{}\n", -i)).collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+
+        let ops = diff_lines(&old, &new);
+
+        // "0" is the same in both
+        assert_eq!(ops[0], Op::Equal { old: 0, new: 0 });
+        assert_eq!(ops[1], Op::Delete { old: 1, new: 1 });
+        assert_eq!(ops[5000], Op::Insert { old: 5000, new: 1 });
+        assert_eq!(ops.len(), 1 + 2 * 4999);
+    }
+
+    #[test]
+    fn missing_trailing_newline() {
+        let got = unified_diff("a\nb", "a\nc\n", "a", "b");
+
+        assert_eq!(
+            got,
+            "--- a\n+++ b\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    ops::Range,
+};
+
+/// A change to the original source text, replacing a range of bytes with
+/// something else.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Edit;
+///
+/// let src = "### Heading\n";
+/// let edit = Edit::new(0..3, "##");
+///
+/// assert_eq!(edit.apply(src), "## Heading\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edit {
+    /// The byte range being replaced.
+    pub span: Range<usize>,
+    /// The text to replace it with.
+    pub replacement: String,
+}
+
+impl Edit {
+    /// Create a new [`Edit`].
+    pub fn new<S: Into<String>>(span: Range<usize>, replacement: S) -> Self {
+        Edit {
+            span,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// Insert some text at a particular offset.
+    pub fn insert<S: Into<String>>(offset: usize, text: S) -> Self {
+        Edit::new(offset..offset, text)
+    }
+
+    /// Remove a range of text.
+    pub fn delete(span: Range<usize>) -> Self { Edit::new(span, "") }
+
+    /// Apply this edit to the text it was created for.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if the span is out of bounds or doesn't lie on a
+    /// `char` boundary.
+    pub fn apply(&self, src: &str) -> String {
+        let mut edited = String::with_capacity(
+            src.len() - self.span.len() + self.replacement.len(),
+        );
+        edited.push_str(&src[..self.span.start]);
+        edited.push_str(&self.replacement);
+        edited.push_str(&src[self.span.end..]);
+
+        edited
+    }
+}
+
+/// A set of non-overlapping [`Edit`]s to the same source text.
+///
+/// Edits are kept sorted by location, with edits to the same location (e.g.
+/// two insertions at the same offset) applied in the order they were added.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{Edit, EditSet};
+///
+/// let src = "# Title.\n\nSee https://example.com\n";
+///
+/// let mut edits = EditSet::new();
+/// edits.insert(Edit::new(14..33, "<https://example.com>")).unwrap();
+/// edits.insert(Edit::delete(7..8)).unwrap();
+///
+/// // this would clobber the first edit
+/// assert!(edits.insert(Edit::new(16..20, "")).is_err());
+///
+/// assert_eq!(edits.apply(src), "# Title\n\nSee <https://example.com>\n");
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EditSet {
+    edits: Vec<Edit>,
+}
+
+impl EditSet {
+    /// Create an empty [`EditSet`].
+    pub fn new() -> Self { EditSet::default() }
+
+    /// Add an [`Edit`], failing if it overlaps one we already have.
+    ///
+    /// Adding the same edit twice is a no-op.
+    pub fn insert(&mut self, edit: Edit) -> Result<(), Overlap> {
+        if let Some(existing) = self.edits.iter().find(|e| overlaps(e, &edit)) {
+            if *existing == edit {
+                return Ok(());
+            }
+
+            return Err(Overlap {
+                existing: existing.clone(),
+                rejected: edit,
+            });
+        }
+
+        let key = |e: &Edit| (e.span.start, e.span.end);
+        let ix = self.edits.partition_point(|e| key(e) <= key(&edit));
+        self.edits.insert(ix, edit);
+
+        Ok(())
+    }
+
+    /// Add as many [`Edit`]s as possible, returning the ones which overlapped.
+    ///
+    /// Those can normally be applied in another pass (see
+    /// [`apply_until_stable()`]).
+    pub fn insert_all<I>(&mut self, edits: I) -> Vec<Overlap>
+    where
+        I: IntoIterator<Item = Edit>,
+    {
+        edits
+            .into_iter()
+            .filter_map(|edit| self.insert(edit).err())
+            .collect()
+    }
+
+    /// The edits, in the order they'll be applied.
+    pub fn edits(&self) -> &[Edit] { &self.edits }
+
+    /// The number of edits.
+    pub fn len(&self) -> usize { self.edits.len() }
+
+    /// Are there any edits?
+    pub fn is_empty(&self) -> bool { self.edits.is_empty() }
+
+    /// Apply every edit to the text they were created for.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if an edit's span is out of bounds or doesn't lie on a
+    /// `char` boundary.
+    pub fn apply(&self, src: &str) -> String {
+        let mut edited = String::with_capacity(src.len());
+        let mut cursor = 0;
+
+        for edit in &self.edits {
+            edited.push_str(&src[cursor..edit.span.start]);
+            edited.push_str(&edit.replacement);
+            cursor = edit.span.end;
+        }
+        edited.push_str(&src[cursor..]);
+
+        edited
+    }
+
+    /// Preview the edits as a unified diff (see
+    /// [`unified_diff()`][crate::unified_diff]).
+    pub fn to_unified_diff(
+        &self,
+        src: &str,
+        original_name: &str,
+        modified_name: &str,
+    ) -> String {
+        crate::unified_diff(src, &self.apply(src), original_name, modified_name)
+    }
+}
+
+impl Extend<Edit> for EditSet {
+    /// Add [`Edit`]s, silently dropping any which overlap.
+    fn extend<I: IntoIterator<Item = Edit>>(&mut self, edits: I) {
+        self.insert_all(edits);
+    }
+}
+
+fn overlaps(a: &Edit, b: &Edit) -> bool {
+    if a.span.is_empty() && b.span.is_empty() {
+        // two insertions never overlap
+        return false;
+    }
+
+    a.span.start < b.span.end && b.span.start < a.span.end
+}
+
+/// The error returned when an [`Edit`] would overlap one that is already in
+/// an [`EditSet`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlap {
+    /// The edit that was already there.
+    pub existing: Edit,
+    /// The edit that couldn't be added.
+    pub rejected: Edit,
+}
+
+impl Display for Overlap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The edit to {:?} overlaps an existing edit to {:?}",
+            self.rejected.span, self.existing.span
+        )
+    }
+}
+
+impl Error for Overlap {}
+
+/// The result of [`apply_until_stable()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixpoint {
+    /// The final text.
+    pub text: String,
+    /// How many times edits were applied.
+    pub passes: usize,
+    /// Did we stop because there was nothing left to change, rather than
+    /// hitting the maximum number of passes?
+    pub converged: bool,
+}
+
+/// Repeatedly generate and apply edits until the text stops changing.
+///
+/// The `edits` callback is given the current text (e.g. so it can be
+/// reparsed and linted) and returns the edits to make. This is useful when
+/// applying one fix may reveal another, or when some edits overlapped and
+/// had to wait for the next pass.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{Edit, EditSet};
+///
+/// // remove one trailing "!" at a time
+/// let fixpoint = markedit::apply_until_stable("Hello!!!", 10, |text| {
+///     let mut edits = EditSet::new();
+///     if text.ends_with('!') {
+///         edits.insert(Edit::delete(text.len() - 1..text.len())).unwrap();
+///     }
+///     edits
+/// });
+///
+/// assert_eq!(fixpoint.text, "Hello");
+/// assert_eq!(fixpoint.passes, 3);
+/// assert!(fixpoint.converged);
+/// ```
+pub fn apply_until_stable<F>(
+    src: &str,
+    max_passes: usize,
+    mut edits: F,
+) -> Fixpoint
+where
+    F: FnMut(&str) -> EditSet,
+{
+    let mut text = src.to_string();
+
+    for passes in 0..max_passes {
+        let edited = edits(&text).apply(&text);
+
+        if edited == text {
+            return Fixpoint {
+                text,
+                passes,
+                converged: true,
+            };
+        }
+
+        text = edited;
+    }
+
+    Fixpoint {
+        text,
+        passes: max_passes,
+        converged: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insertions_at_the_same_offset_keep_their_order() {
+        let mut edits = EditSet::new();
+        edits.insert(Edit::insert(3, "b")).unwrap();
+        edits.insert(Edit::new(3..5, "X")).unwrap();
+        edits.insert(Edit::insert(0, ">")).unwrap();
+        edits.insert(Edit::insert(3, "c")).unwrap();
+
+        assert_eq!(edits.apply("aaadd!"), ">aaabcX!");
+    }
+
+    #[test]
+    fn detect_overlaps() {
+        let mut edits = EditSet::new();
+        edits.insert(Edit::new(2..6, "x")).unwrap();
+
+        // touching is fine
+        edits.insert(Edit::new(0..2, "y")).unwrap();
+        edits.insert(Edit::insert(6, "z")).unwrap();
+        // but inserting inside a replacement isn't
+        let err = edits.insert(Edit::insert(4, "w")).unwrap_err();
+        assert_eq!(err.existing, Edit::new(2..6, "x"));
+        // and duplicates are ignored
+        edits.insert(Edit::new(2..6, "x")).unwrap();
+
+        let rejected =
+            edits.insert_all(vec![Edit::delete(5..8), Edit::delete(8..9)]);
+
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(edits.len(), 4);
+    }
+
+    #[test]
+    fn give_up_after_max_passes() {
+        let fixpoint = apply_until_stable("", 3, |text| {
+            let mut edits = EditSet::new();
+            edits.insert(Edit::insert(text.len(), "a")).unwrap();
+            edits
+        });
+
+        assert_eq!(fixpoint.text, "aaa");
+        assert!(!fixpoint.converged);
+    }
+}
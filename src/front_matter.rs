@@ -0,0 +1,488 @@
+use pulldown_cmark::{Event, Options, Parser};
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    rc::Rc,
+};
+
+/// The different kinds of front matter a document can start with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FrontMatterFormat {
+    /// YAML, delimited by `---` lines (Jekyll, Hugo, etc.).
+    Yaml,
+    /// TOML, delimited by `+++` lines (Hugo, Zola, etc.).
+    Toml,
+}
+
+impl FrontMatterFormat {
+    /// The line used to start and end this kind of front matter.
+    pub fn delimiter(self) -> &'static str {
+        match self {
+            FrontMatterFormat::Yaml => "---",
+            FrontMatterFormat::Toml => "+++",
+        }
+    }
+
+    fn is_closing_delimiter(self, line: &str) -> bool {
+        line == self.delimiter()
+            || (self == FrontMatterFormat::Yaml && line == "...")
+    }
+}
+
+/// The block of metadata at the top of a document.
+///
+/// The original text is always available via [`FrontMatter::raw`], with the
+/// `yaml` and `toml` cargo features adding methods for reading and updating
+/// it as structured data.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FrontMatter {
+    /// Which format the front matter is written in.
+    pub format: FrontMatterFormat,
+    /// The text between the delimiters.
+    pub raw: String,
+}
+
+impl FrontMatter {
+    /// Create new front matter.
+    pub fn new<S: Into<String>>(format: FrontMatterFormat, raw: S) -> Self {
+        FrontMatter {
+            format,
+            raw: raw.into(),
+        }
+    }
+
+    /// Deserialize the front matter into some strongly typed value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Page {
+    ///     title: String,
+    ///     #[serde(default)]
+    ///     draft: bool,
+    /// }
+    ///
+    /// # #[cfg(feature = "yaml")] {
+    /// let src = "---\ntitle: Hello, World\n---\n\nSome text.\n";
+    ///
+    /// let (front_matter, _body) = markedit::split_front_matter(src);
+    /// let page: Page = front_matter.unwrap().deserialize().unwrap();
+    ///
+    /// assert_eq!(page.title, "Hello, World");
+    /// assert!(!page.draft);
+    /// # }
+    /// ```
+    #[cfg(any(feature = "yaml", feature = "toml"))]
+    pub fn deserialize<T>(&self) -> Result<T, FrontMatterError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match self.format {
+            #[cfg(feature = "yaml")]
+            FrontMatterFormat::Yaml => {
+                serde_yaml_ng::from_str(&self.raw).map_err(FrontMatterError::Yaml)
+            },
+            #[cfg(feature = "toml")]
+            FrontMatterFormat::Toml => {
+                toml::from_str(&self.raw).map_err(FrontMatterError::TomlDe)
+            },
+            #[allow(unreachable_patterns)]
+            other => Err(FrontMatterError::Unsupported(other)),
+        }
+    }
+
+    /// Replace the front matter with a serialized value, keeping the same
+    /// format.
+    ///
+    /// Note that the original formatting and any comments will be lost.
+    #[cfg(any(feature = "yaml", feature = "toml"))]
+    pub fn serialize<T>(&mut self, value: &T) -> Result<(), FrontMatterError>
+    where
+        T: serde::Serialize,
+    {
+        let raw = match self.format {
+            #[cfg(feature = "yaml")]
+            FrontMatterFormat::Yaml => {
+                serde_yaml_ng::to_string(value).map_err(FrontMatterError::Yaml)?
+            },
+            #[cfg(feature = "toml")]
+            FrontMatterFormat::Toml => {
+                toml::to_string(value).map_err(FrontMatterError::TomlSer)?
+            },
+            #[allow(unreachable_patterns)]
+            other => return Err(FrontMatterError::Unsupported(other)),
+        };
+
+        self.raw = raw;
+        Ok(())
+    }
+
+    /// Parse YAML front matter into a generic [`serde_yaml_ng::Value`].
+    #[cfg(feature = "yaml")]
+    pub fn yaml(&self) -> Result<serde_yaml_ng::Value, FrontMatterError> {
+        self.deserialize()
+    }
+
+    /// Read the YAML front matter, let `update` modify it, then save the
+    /// result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let src = "---\ntitle: Hello\ndraft: true\n---\nSome text.\n";
+    ///
+    /// let (front_matter, _body) = markedit::split_front_matter(src);
+    /// let mut front_matter = front_matter.unwrap();
+    /// front_matter
+    ///     .update_yaml(|value| {
+    ///         value["draft"] = false.into();
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(front_matter.to_string(), "---\ntitle: Hello\ndraft: false\n---\n");
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn update_yaml<F>(&mut self, update: F) -> Result<(), FrontMatterError>
+    where
+        F: FnOnce(&mut serde_yaml_ng::Value),
+    {
+        let mut value = self.yaml()?;
+        update(&mut value);
+        self.serialize(&value)
+    }
+
+    /// Parse TOML front matter into a generic [`toml::Table`].
+    #[cfg(feature = "toml")]
+    pub fn toml(&self) -> Result<toml::Table, FrontMatterError> {
+        self.deserialize()
+    }
+
+    /// Read the TOML front matter, let `update` modify it, then save the
+    /// result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let src = "+++\ntitle = \"Hello\"\n+++\nSome text.\n";
+    ///
+    /// let (front_matter, _body) = markedit::split_front_matter(src);
+    /// let mut front_matter = front_matter.unwrap();
+    /// front_matter
+    ///     .update_toml(|table| {
+    ///         table.insert("weight".into(), 10.into());
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     front_matter.to_string(),
+    ///     "+++\ntitle = \"Hello\"\nweight = 10\n+++\n",
+    /// );
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn update_toml<F>(&mut self, update: F) -> Result<(), FrontMatterError>
+    where
+        F: FnOnce(&mut toml::Table),
+    {
+        let mut table = self.toml()?;
+        update(&mut table);
+        self.serialize(&table)
+    }
+}
+
+impl Display for FrontMatter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let delimiter = self.format.delimiter();
+
+        writeln!(f, "{}", delimiter)?;
+        f.write_str(&self.raw)?;
+        if !self.raw.is_empty() && !self.raw.ends_with('\n') {
+            writeln!(f)?;
+        }
+        writeln!(f, "{}", delimiter)
+    }
+}
+
+/// Split a document into its front matter and the remaining Markdown.
+///
+/// Front matter must start on the very first line with a `---` (YAML) or
+/// `+++` (TOML) delimiter and is closed by a matching delimiter on its own
+/// line (YAML may also use `...`). If there is no closing delimiter, the
+/// document is assumed not to have any front matter.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::FrontMatterFormat;
+///
+/// let src = "---\ntitle: Hello\n---\n\nSome text.\n";
+///
+/// let (front_matter, body) = markedit::split_front_matter(src);
+///
+/// let front_matter = front_matter.unwrap();
+/// assert_eq!(front_matter.format, FrontMatterFormat::Yaml);
+/// assert_eq!(front_matter.raw, "title: Hello\n");
+/// assert_eq!(body, "\nSome text.\n");
+/// ```
+pub fn split_front_matter(src: &str) -> (Option<FrontMatter>, &str) {
+    let text = src.strip_prefix('\u{feff}').unwrap_or(src);
+
+    let first_line = text.lines().next().unwrap_or_default().trim_end();
+    let format = match first_line {
+        "---" => FrontMatterFormat::Yaml,
+        "+++" => FrontMatterFormat::Toml,
+        _ => return (None, src),
+    };
+
+    let content_start = match text.find('\n') {
+        Some(ix) => ix + 1,
+        None => return (None, src),
+    };
+
+    let mut offset = content_start;
+    for line in text[content_start..].split_inclusive('\n') {
+        if format.is_closing_delimiter(line.trim_end()) {
+            let front_matter =
+                FrontMatter::new(format, &text[content_start..offset]);
+            return (Some(front_matter), &text[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+
+    (None, src)
+}
+
+/// A shared handle to a [`Document`]'s front matter.
+///
+/// A [`Rewriter`][crate::Rewriter] can hold onto a copy of the handle to read
+/// or update the front matter (or add some, if there wasn't any) while the
+/// body is being rewritten.
+pub type SharedFrontMatter = Rc<RefCell<Option<FrontMatter>>>;
+
+/// A Markdown document which may start with some [`FrontMatter`].
+///
+/// The front matter is split off before parsing so it doesn't get
+/// interpreted as a thematic break and paragraph (or a setext heading), then
+/// [`Document::to_markdown()`] puts it back in front of the serialized body.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{Document, pulldown_cmark::{Event, Options}, Writer};
+///
+/// let src = "+++\ntitle = \"Notes\"\n+++\n\n# Heading\n";
+///
+/// let doc = Document::parse(src);
+///
+/// // the rewriter can update the front matter as it goes
+/// let front_matter = doc.front_matter();
+/// let rewriter = move |event: Event<'static>, writer: &mut Writer<'static>| {
+///     if let Event::Text(text) = &event {
+///         if let Some(fm) = front_matter.borrow_mut().as_mut() {
+///             fm.raw.push_str(&format!("heading = \"{}\"\n", text));
+///         }
+///     }
+///     writer.pass_through(event);
+/// };
+/// let events = markedit::rewrite(doc.events(Options::empty()), rewriter);
+///
+/// assert_eq!(
+///     doc.to_markdown(events),
+///     "+++\ntitle = \"Notes\"\nheading = \"Heading\"\n+++\n\n# Heading\n",
+/// );
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct Document<'src> {
+    front_matter: SharedFrontMatter,
+    body: &'src str,
+}
+
+impl<'src> Document<'src> {
+    /// Split a document into its front matter and body.
+    pub fn parse(src: &'src str) -> Self {
+        let (front_matter, body) = split_front_matter(src);
+        Document {
+            front_matter: Rc::new(RefCell::new(front_matter)),
+            body,
+        }
+    }
+
+    /// Get a handle to the document's front matter, which can be given to a
+    /// [`Rewriter`][crate::Rewriter] so it can be read and updated during
+    /// rewriting.
+    pub fn front_matter(&self) -> SharedFrontMatter {
+        Rc::clone(&self.front_matter)
+    }
+
+    /// The Markdown after the front matter.
+    pub fn body(&self) -> &'src str { self.body }
+
+    /// Parse the body into [`Event`]s.
+    ///
+    /// The events only borrow from the original text, so the front matter
+    /// can still be updated while they are being processed.
+    pub fn events(
+        &self,
+        options: Options,
+    ) -> impl Iterator<Item = Event<'src>> + 'src {
+        Parser::new_ext(self.body, options)
+    }
+
+    /// Serialize the front matter and a (possibly rewritten) body.
+    ///
+    /// The events are serialized before the front matter is read, so any
+    /// changes made while rewriting them are included.
+    pub fn to_markdown<'a, I>(&self, events: I) -> String
+    where
+        I: IntoIterator<Item = Event<'a>>,
+    {
+        let body = crate::to_markdown(events);
+
+        match *self.front_matter.borrow() {
+            Some(ref front_matter) if body.is_empty() => {
+                front_matter.to_string()
+            },
+            Some(ref front_matter) => format!("{}\n{}", front_matter, body),
+            None => body,
+        }
+    }
+}
+
+/// The errors that may occur when reading or writing structured
+/// [`FrontMatter`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FrontMatterError {
+    /// Support for this format wasn't enabled (see the `yaml` and `toml`
+    /// cargo features).
+    Unsupported(FrontMatterFormat),
+    /// The YAML couldn't be parsed or serialized.
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml_ng::Error),
+    /// The TOML couldn't be parsed.
+    #[cfg(feature = "toml")]
+    TomlDe(toml::de::Error),
+    /// The value couldn't be serialized as TOML.
+    #[cfg(feature = "toml")]
+    TomlSer(toml::ser::Error),
+}
+
+impl Display for FrontMatterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FrontMatterError::Unsupported(format) => {
+                write!(f, "Support for {:?} front matter isn't enabled", format)
+            },
+            #[cfg(feature = "yaml")]
+            FrontMatterError::Yaml(_) => write!(f, "Invalid YAML front matter"),
+            #[cfg(feature = "toml")]
+            FrontMatterError::TomlDe(_) => {
+                write!(f, "Invalid TOML front matter")
+            },
+            #[cfg(feature = "toml")]
+            FrontMatterError::TomlSer(_) => {
+                write!(f, "Unable to serialize the front matter as TOML")
+            },
+        }
+    }
+}
+
+impl Error for FrontMatterError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(feature = "yaml")]
+            FrontMatterError::Yaml(e) => Some(e),
+            #[cfg(feature = "toml")]
+            FrontMatterError::TomlDe(e) => Some(e),
+            #[cfg(feature = "toml")]
+            FrontMatterError::TomlSer(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_recognise_closed_front_matter_on_the_first_line() {
+        let inputs = vec![
+            "---\ntitle: x\n",
+            "\n---\ntitle: x\n---\n",
+            "----\ntitle: x\n----\n",
+            "---\ntitle: x\n+++\n",
+            "Some text\n\n---\n",
+        ];
+
+        for src in inputs {
+            assert_eq!(split_front_matter(src), (None, src), "{:?}", src);
+        }
+    }
+
+    #[test]
+    fn front_matter_isnt_parsed_as_markdown() {
+        let src = "---\ntitle: x\n...\n# Heading\n";
+
+        let doc = Document::parse(src);
+        let got = doc.to_markdown(doc.events(Options::empty()));
+
+        assert_eq!(got, "---\ntitle: x\n---\n\n# Heading\n");
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn yaml_front_matter_needs_the_yaml_feature() {
+        let front_matter = FrontMatter::new(FrontMatterFormat::Yaml, "x: 1\n");
+
+        let got = front_matter.deserialize::<toml::Table>();
+
+        #[cfg(feature = "yaml")]
+        assert!(got.is_ok());
+        #[cfg(not(feature = "yaml"))]
+        assert!(matches!(
+            got,
+            Err(FrontMatterError::Unsupported(FrontMatterFormat::Yaml))
+        ));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn rewriters_can_update_front_matter_fields() {
+        let src = "---\ntitle: Draft\n---\n\n# Release Notes\n";
+        let doc = Document::parse(src);
+        let front_matter = doc.front_matter();
+        let mut in_heading = false;
+
+        let rewriter = move |ev: Event<'static>,
+                             writer: &mut crate::Writer<'static>| {
+            match &ev {
+                Event::Start(pulldown_cmark::Tag::Heading(1)) => {
+                    in_heading = true
+                },
+                Event::Text(text) if in_heading => {
+                    let title = text.to_string();
+                    front_matter
+                        .borrow_mut()
+                        .as_mut()
+                        .unwrap()
+                        .update_yaml(|value| value["title"] = title.into())
+                        .unwrap();
+                    in_heading = false;
+                },
+                _ => {},
+            }
+            writer.pass_through(ev);
+        };
+        let events = crate::rewrite(doc.events(Options::empty()), rewriter);
+        let got = doc.to_markdown(events);
+
+        assert_eq!(
+            got,
+            "---\ntitle: Release Notes\n---\n\n# Release Notes\n"
+        );
+    }
+}
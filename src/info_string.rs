@@ -0,0 +1,312 @@
+use std::fmt::{self, Display, Formatter};
+
+/// The parsed form of a fenced code block's info string (the `rust,ignore`
+/// in ` ```rust,ignore `).
+///
+/// The first word is treated as the language, with the remaining words being
+/// attributes. Words may be separated by commas or whitespace, and an
+/// attribute may either be a simple flag (e.g. `ignore`) or a `key=value`
+/// pair, where the value may be quoted.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::InfoString;
+///
+/// let info = InfoString::parse("toml,ignore title=\"Cargo.toml\"");
+///
+/// assert_eq!(info.language.as_deref(), Some("toml"));
+/// assert!(info.has_flag("ignore"));
+/// assert_eq!(info.get("title"), Some("Cargo.toml"));
+/// assert_eq!(info.get("missing"), None);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct InfoString {
+    /// The code block's language, if one was provided.
+    pub language: Option<String>,
+    /// Any other attributes, in the order they were written.
+    pub attributes: Vec<Attribute>,
+}
+
+impl InfoString {
+    /// Parse an info string.
+    pub fn parse(info: &str) -> InfoString {
+        let mut words = split_words(info).into_iter();
+        let mut info_string = InfoString::default();
+
+        if let Some(first) = words.next() {
+            match Attribute::parse(&first) {
+                attr @ Attribute { value: Some(_), .. } => {
+                    info_string.attributes.push(attr)
+                },
+                _ => info_string.language = Some(first),
+            }
+        }
+
+        info_string
+            .attributes
+            .extend(words.map(|word| Attribute::parse(&word)));
+
+        info_string
+    }
+
+    /// Is there an attribute with this name that has no value (e.g. the
+    /// `ignore` in `rust,ignore`)?
+    pub fn has_flag(&self, name: &str) -> bool {
+        self.attributes
+            .iter()
+            .any(|attr| attr.key == name && attr.value.is_none())
+    }
+
+    /// Get the value of a `key=value` attribute.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.key == key)
+            .and_then(|attr| attr.value.as_deref())
+    }
+
+    /// Add a `key=value` attribute, replacing any existing attributes with
+    /// the same key.
+    pub fn set<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        self.remove(&key);
+        self.attributes.push(Attribute {
+            key,
+            value: Some(value.into()),
+        });
+    }
+
+    /// Add a flag (an attribute without a value), if it isn't already set.
+    pub fn add_flag<S: Into<String>>(&mut self, name: S) {
+        let name = name.into();
+
+        if !self.has_flag(&name) {
+            self.attributes.push(Attribute {
+                key: name,
+                value: None,
+            });
+        }
+    }
+
+    /// Remove any attributes with this name.
+    pub fn remove(&mut self, key: &str) {
+        self.attributes.retain(|attr| attr.key != key);
+    }
+
+    /// Does this info string contain everything required by `other`?
+    ///
+    /// The language must match exactly (if `other` has a language), and
+    /// every attribute in `other` must be present.
+    pub fn satisfies(&self, other: &InfoString) -> bool {
+        let language_matches = match other.language {
+            Some(ref lang) => self.language.as_ref() == Some(lang),
+            None => true,
+        };
+
+        language_matches
+            && other
+                .attributes
+                .iter()
+                .all(|attr| self.attributes.contains(attr))
+    }
+}
+
+impl Display for InfoString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // rustdoc-style flags are normally comma-separated, while key-value
+        // pairs are normally separated by spaces
+        let separator = if self.attributes.iter().all(|a| a.value.is_none()) {
+            ","
+        } else {
+            " "
+        };
+        let mut first = true;
+
+        if let Some(ref lang) = self.language {
+            write!(f, "{}", lang)?;
+            first = false;
+        }
+
+        for attr in &self.attributes {
+            if !first {
+                write!(f, "{}", separator)?;
+            }
+            write!(f, "{}", attr)?;
+            first = false;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a str> for InfoString {
+    fn from(info: &'a str) -> InfoString { InfoString::parse(info) }
+}
+
+/// An attribute in an [`InfoString`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Attribute {
+    /// The attribute's name.
+    pub key: String,
+    /// The attribute's value, if it is a `key=value` pair.
+    pub value: Option<String>,
+}
+
+impl Attribute {
+    fn parse(word: &str) -> Attribute {
+        match word.find('=') {
+            Some(ix) => Attribute {
+                key: word[..ix].to_string(),
+                value: Some(unquote(&word[ix + 1..])),
+            },
+            None => Attribute {
+                key: word.to_string(),
+                value: None,
+            },
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(ref value) if needs_quotes(value) => write!(
+                f,
+                "{}=\"{}\"",
+                self.key,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            ),
+            Some(ref value) => write!(f, "{}={}", self.key, value),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty()
+        || value.contains(|c: char| {
+            c.is_whitespace() || c == ',' || c == '"' || c == '\\'
+        })
+}
+
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        let mut unquoted = String::new();
+        let mut chars = value[1..value.len() - 1].chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => unquoted.extend(chars.next()),
+                other => unquoted.push(other),
+            }
+        }
+
+        unquoted
+    } else {
+        value.to_string()
+    }
+}
+
+/// Split an info string into words, separated by commas or whitespace but
+/// respecting quotes.
+fn split_words(info: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = info.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            },
+            '\\' if in_quotes => {
+                current.push(c);
+                current.extend(chars.next());
+            },
+            ',' if !in_quotes => words.push(std::mem::take(&mut current)),
+            c if c.is_whitespace() && !in_quotes => {
+                words.push(std::mem::take(&mut current))
+            },
+            other => current.push(other),
+        }
+    }
+
+    words.push(current);
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_info_strings() {
+        let inputs = vec![
+            ("", InfoString::default()),
+            (
+                "rust,ignore",
+                InfoString {
+                    language: Some(String::from("rust")),
+                    attributes: vec![Attribute {
+                        key: String::from("ignore"),
+                        value: None,
+                    }],
+                },
+            ),
+            (
+                "  toml  title=\"My Cargo.toml\", hidden",
+                InfoString {
+                    language: Some(String::from("toml")),
+                    attributes: vec![
+                        Attribute {
+                            key: String::from("title"),
+                            value: Some(String::from("My Cargo.toml")),
+                        },
+                        Attribute {
+                            key: String::from("hidden"),
+                            value: None,
+                        },
+                    ],
+                },
+            ),
+            (
+                "file=src/main.rs",
+                InfoString {
+                    language: None,
+                    attributes: vec![Attribute {
+                        key: String::from("file"),
+                        value: Some(String::from("src/main.rs")),
+                    }],
+                },
+            ),
+        ];
+
+        for (src, should_be) in inputs {
+            assert_eq!(InfoString::parse(src), should_be, "{}", src);
+        }
+    }
+
+    #[test]
+    fn info_strings_round_trip() {
+        let inputs = vec![
+            "rust",
+            "rust,ignore,should_panic",
+            "toml title=\"My Cargo.toml\" hidden",
+            "text quote=\"say \\\"hi\\\"\"",
+        ];
+
+        for src in inputs {
+            let info = InfoString::parse(src);
+            assert_eq!(info.to_string(), src);
+            assert_eq!(InfoString::parse(&info.to_string()), info);
+        }
+    }
+}
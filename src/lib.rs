@@ -82,16 +82,94 @@
 
 pub use pulldown_cmark;
 
+mod block_diff;
+mod blocks;
+mod changelog;
+mod concat;
+mod diff;
+mod edit;
+mod front_matter;
+mod info_string;
+mod link_checker;
+// a namespace rather than re-exports, see the module docs for why
+pub mod lint;
+mod markdown;
 mod matchers;
+mod merge;
 mod rewriters;
+#[cfg(feature = "rules")]
+mod rules;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod slugs;
+mod split;
+mod table;
+mod tangle;
+mod task_progress;
+mod toc;
+mod urls;
 
+pub use block_diff::{diff, BlockDiff, DiffOp};
+pub use blocks::{Block, BlockKind};
+pub use changelog::{
+    Category, Changelog, ChangelogError, ChangelogProblem, Release,
+    ReleaseLink, CATEGORIES,
+};
+pub use concat::{concat_documents, Concatenated};
+pub use diff::unified_diff;
+pub use edit::{apply_until_stable, Edit, EditSet, Fixpoint, Overlap};
+pub use front_matter::{
+    split_front_matter, Document, FrontMatter, FrontMatterError,
+    FrontMatterFormat, SharedFrontMatter,
+};
+pub use info_string::{Attribute, InfoString};
+pub use link_checker::{
+    check_links, FoundLink, LinkDiagnostic, LinkProblem, LinkReport,
+};
+pub use markdown::{to_markdown, to_markdown_with_source, write_markdown};
 pub use matchers::*;
+pub use merge::{merge, Conflict, Merge};
 pub use rewriters::*;
+#[cfg(feature = "rules")]
+pub use rules::{RuleError, Rules};
+#[cfg(feature = "scripting")]
+pub use scripting::{ScriptError, ScriptRewriter};
+pub use slugs::{heading_slugs, slugify};
+pub use split::{split_by_heading, Chunk, Split};
+pub use table::{Cell, CsvError, Table};
+pub use tangle::{tangle, TangleError};
+pub use task_progress::{
+    task_progress, ListProgress, Progress, SectionProgress, TaskReport,
+};
+pub use toc::{table_of_contents, TableOfContents, TocEntry};
 
-use pulldown_cmark::{Event, Parser};
+use pulldown_cmark::{Event, Options, Parser};
 
 /// A convenience function for parsing some text into [`Event`]s without
 /// needing to add [`pulldown_cmark`] as an explicit dependency.
 pub fn parse(text: &str) -> impl Iterator<Item = Event<'_>> + '_ {
     Parser::new(text)
 }
+
+/// Parse some text into [`Event`]s, enabling extensions like footnotes or
+/// tables.
+///
+/// ```rust
+/// use markedit::{Matcher, pulldown_cmark::{Event, Options}};
+///
+/// let src = "Some text[^note].\n\n[^note]: A footnote.";
+///
+/// let events = markedit::parse_with_options(src, Options::ENABLE_FOOTNOTES);
+///
+/// let is_footnote = |ev: &Event<'_>| match ev {
+///     Event::FootnoteReference(_) => true,
+///     _ => false,
+/// };
+/// assert!(is_footnote.is_in(events));
+/// ```
+pub fn parse_with_options(
+    text: &str,
+    options: Options,
+) -> impl Iterator<Item = Event<'_>> + '_ {
+    Parser::new_ext(text, options)
+}
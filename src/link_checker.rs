@@ -0,0 +1,273 @@
+use crate::urls;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+/// Check the links and images in a Markdown document without touching the
+/// network.
+///
+/// Links to a `#fragment` are checked against the document's own heading
+/// slugs (see [`heading_slugs()`][crate::heading_slugs]), and relative paths
+/// are resolved against `base_dir`. If a relative link points to an anchor
+/// inside another local Markdown file, that file's headings are checked too.
+///
+/// External URLs (anything with a scheme, like `https:` or `mailto:`) and
+/// absolute paths can't be checked offline, so they are only collected.
+///
+/// # Examples
+///
+/// ```rust
+/// let src = "# Usage\n\nSee [usage](#usage) and [the API](#api).";
+///
+/// let report = markedit::check_links(src, ".");
+///
+/// assert_eq!(report.links.len(), 2);
+/// assert_eq!(report.diagnostics.len(), 1);
+///
+/// let diag = &report.diagnostics[0];
+/// assert_eq!(&src[diag.span.clone()], "[the API](#api)");
+/// assert_eq!(
+///     diag.problem,
+///     markedit::LinkProblem::UnknownAnchor(String::from("api")),
+/// );
+/// ```
+pub fn check_links<P: AsRef<Path>>(src: &str, base_dir: P) -> LinkReport {
+    let base_dir = base_dir.as_ref();
+    let links = collect_links(src);
+    let local_anchors = crate::heading_slugs(Parser::new(src));
+    let mut other_files = HashMap::new();
+    let mut diagnostics = Vec::new();
+
+    for link in &links {
+        let problem =
+            check_link(&link.url, &local_anchors, base_dir, &mut other_files);
+
+        if let Some(problem) = problem {
+            diagnostics.push(LinkDiagnostic {
+                url: link.url.clone(),
+                span: link.span.clone(),
+                problem,
+            });
+        }
+    }
+
+    LinkReport { links, diagnostics }
+}
+
+fn collect_links(src: &str) -> Vec<FoundLink> {
+    Parser::new(src)
+        .into_offset_iter()
+        .filter_map(|(ev, span)| match ev {
+            Event::Start(Tag::Link(_, url, title)) => Some(FoundLink {
+                url: url.into_string(),
+                title: title.into_string(),
+                is_image: false,
+                span,
+            }),
+            Event::Start(Tag::Image(_, url, title)) => Some(FoundLink {
+                url: url.into_string(),
+                title: title.into_string(),
+                is_image: true,
+                span,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn check_link(
+    url: &str,
+    local_anchors: &[String],
+    base_dir: &Path,
+    other_files: &mut HashMap<PathBuf, Option<Vec<String>>>,
+) -> Option<LinkProblem> {
+    if url.trim().is_empty() {
+        return Some(LinkProblem::EmptyUrl);
+    }
+
+    if let Some(fragment) = url.strip_prefix('#') {
+        let anchor = urls::percent_decode(fragment);
+
+        return if local_anchors.contains(&anchor) {
+            None
+        } else {
+            Some(LinkProblem::UnknownAnchor(anchor))
+        };
+    }
+
+    if !urls::is_relative(url) {
+        // external links and absolute paths can't be checked offline
+        return None;
+    }
+
+    let (path, _) = urls::split_path(url);
+    let (_, fragment) = urls::split_fragment(url);
+    let path = base_dir.join(urls::percent_decode(path));
+
+    if !path.exists() {
+        return Some(LinkProblem::MissingFile(path));
+    }
+
+    let anchor = match fragment {
+        Some(fragment) if is_markdown_file(&path) => {
+            urls::percent_decode(fragment)
+        },
+        _ => return None,
+    };
+
+    let anchors = other_files.entry(path.clone()).or_insert_with(|| {
+        std::fs::read_to_string(&path)
+            .ok()
+            .map(|src| crate::heading_slugs(Parser::new(&src)))
+    });
+
+    match anchors {
+        Some(anchors) if !anchors.contains(&anchor) => {
+            Some(LinkProblem::UnknownAnchorInFile { path, anchor })
+        },
+        Some(_) => None,
+        None => Some(LinkProblem::UnreadableFile(path)),
+    }
+}
+
+fn is_markdown_file(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => {
+            ext.eq_ignore_ascii_case("md")
+                || ext.eq_ignore_ascii_case("markdown")
+        },
+        None => false,
+    }
+}
+
+/// The results from [`check_links()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkReport {
+    /// Every link and image in the document, in the order they appear.
+    pub links: Vec<FoundLink>,
+    /// Any broken links which were found.
+    pub diagnostics: Vec<LinkDiagnostic>,
+}
+
+impl LinkReport {
+    /// Were all the links valid?
+    pub fn is_ok(&self) -> bool { self.diagnostics.is_empty() }
+
+    /// Links to external URLs, which haven't been checked.
+    pub fn external_links(&self) -> impl Iterator<Item = &FoundLink> + '_ {
+        self.links.iter().filter(|link| urls::has_scheme(&link.url))
+    }
+}
+
+/// A link or image found by [`check_links()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoundLink {
+    /// The URL being linked to.
+    pub url: String,
+    /// The link's title, or an empty string if there is none.
+    pub title: String,
+    /// Was this an image (as opposed to a normal link)?
+    pub is_image: bool,
+    /// Where the link is in the source text.
+    pub span: Range<usize>,
+}
+
+/// A broken link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkDiagnostic {
+    /// The URL being linked to.
+    pub url: String,
+    /// Where the link is in the source text.
+    pub span: Range<usize>,
+    /// What is wrong with the link.
+    pub problem: LinkProblem,
+}
+
+impl Display for LinkDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}..{}: Broken link to \"{}\", {}",
+            self.span.start, self.span.end, self.url, self.problem
+        )
+    }
+}
+
+/// The reasons a link may be broken.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkProblem {
+    /// The link doesn't go anywhere.
+    EmptyUrl,
+    /// There is no heading in this document with the desired anchor.
+    UnknownAnchor(String),
+    /// The linked file doesn't exist.
+    MissingFile(PathBuf),
+    /// The linked file exists, but there was an error reading it.
+    UnreadableFile(PathBuf),
+    /// The linked Markdown file doesn't contain the desired anchor.
+    UnknownAnchorInFile {
+        /// The linked file.
+        path: PathBuf,
+        /// The anchor that couldn't be found.
+        anchor: String,
+    },
+}
+
+impl Display for LinkProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkProblem::EmptyUrl => write!(f, "the URL is empty"),
+            LinkProblem::UnknownAnchor(anchor) => {
+                write!(f, "there is no heading with the anchor \"{}\"", anchor)
+            },
+            LinkProblem::MissingFile(path) => {
+                write!(f, "\"{}\" doesn't exist", path.display())
+            },
+            LinkProblem::UnreadableFile(path) => {
+                write!(f, "unable to read \"{}\"", path.display())
+            },
+            LinkProblem::UnknownAnchorInFile { path, anchor } => write!(
+                f,
+                "\"{}\" has no heading with the anchor \"{}\"",
+                path.display(),
+                anchor
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_relative_links_against_this_repo() {
+        let src = "[readme](README.md) [license](README.md#license)
+[missing](does-not-exist.md) [bad anchor](README.md#nope)
+![logo](https://example.com/logo.png) [crate](./src/lib.rs)";
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+        let report = check_links(src, manifest_dir);
+
+        assert_eq!(report.links.len(), 6);
+        assert_eq!(report.external_links().count(), 1);
+        let problems: Vec<_> =
+            report.diagnostics.iter().map(|d| &d.problem).collect();
+        assert_eq!(
+            problems,
+            vec![
+                &LinkProblem::MissingFile(
+                    manifest_dir.join("does-not-exist.md")
+                ),
+                &LinkProblem::UnknownAnchorInFile {
+                    path: manifest_dir.join("README.md"),
+                    anchor: String::from("nope"),
+                },
+            ]
+        );
+    }
+}
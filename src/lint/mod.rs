@@ -0,0 +1,382 @@
+//! Markdownlint-style checks for Markdown documents.
+//!
+//! A [`Linter`] feeds each [`Event`] (and its location in the source text) to
+//! a set of [`Rule`]s, which report any problems as [`Diagnostic`]s.
+//!
+//! Rules can be turned off for an entire document with a
+//! `<!-- markedit-disable rule-id another-rule -->` comment, or by leaving
+//! out the rule IDs to disable everything.
+//!
+//! Unlike the rest of the crate, linting lives in its own namespace instead
+//! of being re-exported from the crate root. Names like [`check()`],
+//! [`Rule`] and [`SingleH1`] only make sense in the context of linting, and
+//! [`Rule`] would be easy to confuse with the rewrite rules loaded from
+//! `markedit.toml`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use markedit::lint::Severity;
+//!
+//! let src = "# Title\n\n### Usage.\n";
+//!
+//! let diagnostics = markedit::lint::check(src);
+//!
+//! let ids: Vec<_> = diagnostics.iter().map(|d| d.rule).collect();
+//! assert_eq!(ids, &["heading-increment", "no-trailing-punctuation"]);
+//! assert_eq!(diagnostics[0].severity, Severity::Warning);
+//! assert_eq!(&src[diagnostics[0].span.clone()], "### Usage.\n");
+//!
+//! // some diagnostics know how to fix themselves
+//! let fix = diagnostics[0].fix.as_ref().unwrap();
+//! assert_eq!(fix.apply(src), "# Title\n\n## Usage.\n");
+//!
+//! // or you can apply every fix at once
+//! let mut linter = markedit::lint::Linter::default();
+//! assert_eq!(linter.fix(src), "# Title\n\n## Usage\n");
+//! ```
+
+mod rules;
+
+pub use rules::{
+    FencedCodeLanguage, HeadingIncrement, NoAltText, NoBareUrls,
+    NoDuplicateHeading, NoEmptyLinks, NoTrailingPunctuation, SingleH1,
+};
+
+use crate::{Document, Edit, EditSet};
+use pulldown_cmark::{Event, Options, Parser};
+use std::{
+    collections::HashSet,
+    fmt::{self, Debug, Display, Formatter},
+    ops::Range,
+};
+
+/// The most times [`Linter::fix()`] will re-lint a document.
+const MAX_FIX_PASSES: usize = 10;
+
+/// Check a document against the [`builtin_rules()`].
+pub fn check(src: &str) -> Vec<Diagnostic> { Linter::default().lint(src) }
+
+/// Every rule that comes with `markedit`.
+pub fn builtin_rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(HeadingIncrement::default()),
+        Box::new(SingleH1::default()),
+        Box::new(NoTrailingPunctuation::default()),
+        Box::new(NoEmptyLinks),
+        Box::new(NoAltText::default()),
+        Box::new(NoBareUrls::default()),
+        Box::new(NoDuplicateHeading::default()),
+        Box::new(FencedCodeLanguage),
+    ]
+}
+
+/// A check which can be run against a stream of [`Event`]s.
+pub trait Rule {
+    /// A short, unique name for this rule (e.g. `heading-increment`), used
+    /// in [`Diagnostic`]s and disable comments.
+    fn id(&self) -> &'static str;
+
+    /// How serious problems found by this rule are.
+    ///
+    /// The default is [`Severity::Warning`].
+    fn severity(&self) -> Severity { Severity::Warning }
+
+    /// Inspect an [`Event`] and the range of source text it came from.
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    );
+
+    /// Called after the last [`Event`] in a document.
+    ///
+    /// Rules may be used for more than one document, so this is where any
+    /// state should be reset. The default implementation does nothing.
+    fn finish(&mut self, _ctx: &mut Context<'_>) {}
+}
+
+/// Information passed to a [`Rule`] while it's checking a document.
+#[derive(Debug)]
+pub struct Context<'a> {
+    src: &'a str,
+    rule: &'static str,
+    severity: Severity,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> Context<'a> {
+    /// The document being checked, without any front matter.
+    pub fn src(&self) -> &'a str { self.src }
+
+    /// Report a problem, returning the [`Diagnostic`] so a fix can be
+    /// attached.
+    pub fn report<S: Into<String>>(
+        &mut self,
+        span: Range<usize>,
+        message: S,
+    ) -> &mut Diagnostic {
+        self.diagnostics.push(Diagnostic {
+            rule: self.rule,
+            severity: self.severity,
+            message: message.into(),
+            span,
+            fix: None,
+        });
+
+        self.diagnostics.last_mut().unwrap()
+    }
+}
+
+/// A problem found by a [`Rule`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The [`Rule::id()`] of the rule that found this problem.
+    pub rule: &'static str,
+    /// How serious the problem is.
+    pub severity: Severity,
+    /// A human-readable description of the problem.
+    pub message: String,
+    /// Where the problem is in the source text.
+    pub span: Range<usize>,
+    /// An [`Edit`] which would fix the problem, if one is known.
+    pub fix: Option<Edit>,
+}
+
+impl Diagnostic {
+    /// Attach a fix to this [`Diagnostic`].
+    pub fn with_fix(&mut self, fix: Edit) -> &mut Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} [{}]", self.severity, self.message, self.rule)
+    }
+}
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something which should probably be fixed.
+    Warning,
+    /// Something which must be fixed.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Runs a set of [`Rule`]s over a document.
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Linter {
+    /// Create a [`Linter`] using the [`builtin_rules()`].
+    pub fn new() -> Self {
+        Linter {
+            rules: builtin_rules(),
+        }
+    }
+
+    /// Create a [`Linter`] with no rules.
+    pub fn empty() -> Self { Linter { rules: Vec::new() } }
+
+    /// Add a [`Rule`].
+    pub fn push<R: Rule + 'static>(&mut self, rule: R) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Add a [`Rule`], builder-style.
+    pub fn with<R: Rule + 'static>(mut self, rule: R) -> Self {
+        self.push(rule);
+        self
+    }
+
+    /// Check a document, returning any problems sorted by location.
+    ///
+    /// Any front matter is skipped, although spans are still relative to the
+    /// start of `src`.
+    pub fn lint(&mut self, src: &str) -> Vec<Diagnostic> {
+        let body = Document::parse(src).body();
+        let offset = src.len() - body.len();
+        let mut diagnostics = self.lint_body(body);
+
+        for diag in &mut diagnostics {
+            diag.span = diag.span.start + offset..diag.span.end + offset;
+
+            if let Some(ref mut fix) = diag.fix {
+                fix.span = fix.span.start + offset..fix.span.end + offset;
+            }
+        }
+
+        diagnostics
+    }
+
+    fn lint_body(&mut self, src: &str) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut disabled = Disabled::default();
+
+        for (event, span) in Parser::new_ext(src, options()).into_offset_iter()
+        {
+            if let Event::Html(html) = &event {
+                disabled.update(html);
+            }
+
+            for rule in &mut self.rules {
+                let mut ctx = Context {
+                    src,
+                    rule: rule.id(),
+                    severity: rule.severity(),
+                    diagnostics: &mut diagnostics,
+                };
+                rule.check(&event, span.clone(), &mut ctx);
+            }
+        }
+
+        for rule in &mut self.rules {
+            let mut ctx = Context {
+                src,
+                rule: rule.id(),
+                severity: rule.severity(),
+                diagnostics: &mut diagnostics,
+            };
+            rule.finish(&mut ctx);
+        }
+
+        diagnostics.retain(|diag| !disabled.contains(diag.rule));
+        diagnostics.sort_by_key(|diag| (diag.span.start, diag.span.end));
+
+        diagnostics
+    }
+
+    /// Apply every available fix, re-linting the document until there is
+    /// nothing left to fix.
+    pub fn fix(&mut self, src: &str) -> String {
+        let fixpoint = crate::apply_until_stable(src, MAX_FIX_PASSES, |text| {
+            let mut edits = EditSet::new();
+            edits.extend(self.lint(text).into_iter().filter_map(|d| d.fix));
+            edits
+        });
+
+        fixpoint.text
+    }
+}
+
+impl Default for Linter {
+    /// A [`Linter`] using the [`builtin_rules()`].
+    fn default() -> Self { Linter::new() }
+}
+
+impl Debug for Linter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ids: Vec<_> = self.rules.iter().map(|rule| rule.id()).collect();
+
+        f.debug_struct("Linter").field("rules", &ids).finish()
+    }
+}
+
+fn options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+}
+
+/// The rules turned off by `<!-- markedit-disable ... -->` comments.
+#[derive(Debug, Default)]
+struct Disabled {
+    everything: bool,
+    rules: HashSet<String>,
+}
+
+impl Disabled {
+    fn update(&mut self, html: &str) {
+        let comment = match html
+            .trim()
+            .strip_prefix("<!--")
+            .and_then(|rest| rest.strip_suffix("-->"))
+        {
+            Some(comment) => comment,
+            None => return,
+        };
+
+        let mut words = comment.split_whitespace();
+        if words.next() != Some("markedit-disable") {
+            return;
+        }
+
+        let rules: Vec<_> = words.map(String::from).collect();
+        if rules.is_empty() {
+            self.everything = true;
+        }
+        self.rules.extend(rules);
+    }
+
+    fn contains(&self, rule: &str) -> bool {
+        self.everything || self.rules.contains(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disable_comments_apply_to_the_whole_file() {
+        let src = "# One\n\n# Two\n\n### Three\n\n<!-- markedit-disable single-h1 -->\n";
+
+        let ids: Vec<_> = check(src).into_iter().map(|d| d.rule).collect();
+
+        assert_eq!(ids, &["heading-increment"]);
+    }
+
+    #[test]
+    fn disable_everything() {
+        let src = "<!-- markedit-disable -->\n\n# One\n\n# Two.\n";
+
+        assert!(check(src).is_empty());
+    }
+
+    #[test]
+    fn fixing_one_problem_can_reveal_another() {
+        let src = "# Title\n\n#### Deep.\n\n##### Deeper!\n";
+
+        let got = Linter::default().fix(src);
+
+        assert_eq!(got, "# Title\n\n## Deep\n\n### Deeper\n");
+    }
+
+    #[test]
+    fn front_matter_is_ignored() {
+        let src = "---\ntitle: Hello world.\n---\n\n# Title\n\n### Usage.\n";
+
+        let diagnostics = check(src);
+
+        let spans: Vec<_> =
+            diagnostics.iter().map(|d| &src[d.span.clone()]).collect();
+        assert_eq!(spans, &["### Usage.\n", "### Usage.\n"]);
+        assert_eq!(
+            Linter::new().fix(src),
+            "---\ntitle: Hello world.\n---\n\n# Title\n\n## Usage\n"
+        );
+    }
+
+    #[test]
+    fn rules_are_reset_between_documents() {
+        let mut linter = Linter::empty().with(SingleH1::default());
+
+        assert!(linter.lint("# Title\n").is_empty());
+        assert!(linter.lint("# Title\n").is_empty());
+    }
+}
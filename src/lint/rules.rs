@@ -0,0 +1,480 @@
+use super::{Context, Rule};
+use crate::Edit;
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+use std::{collections::HashSet, ops::Range};
+
+/// Characters which shouldn't end a heading.
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!'];
+
+/// Heading levels should only increase one at a time (e.g. a `##` followed
+/// by a `####` skips a level).
+///
+/// ATX headings (`## Like this`) are fixed by changing their level.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HeadingIncrement {
+    previous: Option<u32>,
+}
+
+impl Rule for HeadingIncrement {
+    fn id(&self) -> &'static str { "heading-increment" }
+
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    ) {
+        let level = match event {
+            Event::Start(Tag::Heading(level)) => *level,
+            _ => return,
+        };
+
+        match self.previous {
+            Some(previous) if level > previous + 1 => {
+                let expected = previous + 1;
+                let fix =
+                    atx_hashes(ctx.src(), span.clone(), level).map(|hashes| {
+                        Edit::new(hashes, "#".repeat(expected as usize))
+                    });
+
+                let diag = ctx.report(
+                    span,
+                    format!(
+                        "Heading levels should only increase by one (expected h{}, found h{})",
+                        expected, level
+                    ),
+                );
+                if let Some(fix) = fix {
+                    diag.with_fix(fix);
+                }
+            },
+            _ => {},
+        }
+
+        self.previous = Some(level);
+    }
+
+    fn finish(&mut self, _ctx: &mut Context<'_>) {
+        *self = HeadingIncrement::default();
+    }
+}
+
+/// Find the `#`s at the start of an ATX heading.
+fn atx_hashes(
+    src: &str,
+    span: Range<usize>,
+    level: u32,
+) -> Option<Range<usize>> {
+    let heading = &src[span.clone()];
+    let indent = heading.len() - heading.trim_start_matches(' ').len();
+    let after_indent = &heading[indent..];
+    let hashes =
+        after_indent.len() - after_indent.trim_start_matches('#').len();
+
+    if hashes == level as usize {
+        let start = span.start + indent;
+        Some(start..start + hashes)
+    } else {
+        None
+    }
+}
+
+/// A document should only have one top-level heading.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SingleH1 {
+    seen_h1: bool,
+}
+
+impl Rule for SingleH1 {
+    fn id(&self) -> &'static str { "single-h1" }
+
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    ) {
+        if let Event::Start(Tag::Heading(1)) = event {
+            if self.seen_h1 {
+                ctx.report(
+                    span,
+                    "Multiple top-level headings in the same document",
+                );
+            }
+            self.seen_h1 = true;
+        }
+    }
+
+    fn finish(&mut self, _ctx: &mut Context<'_>) {
+        *self = SingleH1::default();
+    }
+}
+
+/// Collects a heading's text, remembering where the heading and its last
+/// piece of text came from.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct HeadingText {
+    span: Range<usize>,
+    text: String,
+    last_text: Option<Range<usize>>,
+    inside: bool,
+}
+
+impl HeadingText {
+    /// Process an event, returning `true` when we reach the end of a
+    /// heading.
+    fn update(&mut self, event: &Event<'_>, span: Range<usize>) -> bool {
+        match event {
+            Event::Start(Tag::Heading(_)) => {
+                *self = HeadingText {
+                    span,
+                    inside: true,
+                    ..HeadingText::default()
+                };
+            },
+            Event::Text(text) | Event::Code(text) if self.inside => {
+                self.text.push_str(text);
+                self.last_text = Some(span);
+            },
+            Event::End(Tag::Heading(_)) => {
+                self.inside = false;
+                return true;
+            },
+            _ => {},
+        }
+
+        false
+    }
+}
+
+/// Headings shouldn't end with punctuation like `.` or `:`.
+///
+/// The punctuation is removed when fixing.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NoTrailingPunctuation {
+    heading: HeadingText,
+}
+
+impl Rule for NoTrailingPunctuation {
+    fn id(&self) -> &'static str { "no-trailing-punctuation" }
+
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    ) {
+        if !self.heading.update(event, span) {
+            return;
+        }
+
+        let punctuation = match self.heading.text.trim_end().chars().last() {
+            Some(c) if TRAILING_PUNCTUATION.contains(&c) => c,
+            _ => return,
+        };
+
+        // only fix it when the punctuation is at the end of the last bit of
+        // text (i.e. not escaped or inside an inline code span)
+        let fix = self.heading.last_text.clone().and_then(|text| {
+            let source = ctx.src()[text.clone()].trim_end();
+            if source.ends_with(punctuation) {
+                let end = text.start + source.len();
+                Some(Edit::delete(end - punctuation.len_utf8()..end))
+            } else {
+                None
+            }
+        });
+
+        let diag = ctx.report(
+            self.heading.span.clone(),
+            format!("Trailing punctuation in heading (\"{}\")", punctuation),
+        );
+        if let Some(fix) = fix {
+            diag.with_fix(fix);
+        }
+    }
+
+    fn finish(&mut self, _ctx: &mut Context<'_>) {
+        *self = NoTrailingPunctuation::default();
+    }
+}
+
+/// Links should go somewhere (e.g. `[text]()` or `[text](#)`).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NoEmptyLinks;
+
+impl Rule for NoEmptyLinks {
+    fn id(&self) -> &'static str { "no-empty-links" }
+
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    ) {
+        if let Event::Start(Tag::Link(_, url, _)) = event {
+            if url.trim().is_empty() || url.as_ref() == "#" {
+                ctx.report(span, "Empty link");
+            }
+        }
+    }
+}
+
+/// Images should have alternate text.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NoAltText {
+    image: Option<(Range<usize>, String)>,
+}
+
+impl Rule for NoAltText {
+    fn id(&self) -> &'static str { "no-alt-text" }
+
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    ) {
+        match event {
+            Event::Start(Tag::Image(..)) => {
+                self.image = Some((span, String::new()))
+            },
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, alt)) = &mut self.image {
+                    alt.push_str(text);
+                }
+            },
+            Event::End(Tag::Image(..)) => {
+                if let Some((span, alt)) = self.image.take() {
+                    if alt.trim().is_empty() {
+                        ctx.report(span, "Image has no alternate text");
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn finish(&mut self, _ctx: &mut Context<'_>) {
+        *self = NoAltText::default();
+    }
+}
+
+/// URLs should be wrapped in angle brackets or turned into proper links,
+/// otherwise some renderers won't make them clickable.
+///
+/// Bare URLs are fixed by wrapping them in angle brackets.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NoBareUrls {
+    /// How many links or code blocks we're inside.
+    depth: usize,
+}
+
+impl Rule for NoBareUrls {
+    fn id(&self) -> &'static str { "no-bare-urls" }
+
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    ) {
+        match event {
+            Event::Start(Tag::Link(..))
+            | Event::Start(Tag::Image(..))
+            | Event::Start(Tag::CodeBlock(_)) => self.depth += 1,
+            Event::End(Tag::Link(..))
+            | Event::End(Tag::Image(..))
+            | Event::End(Tag::CodeBlock(_)) => {
+                self.depth = self.depth.saturating_sub(1)
+            },
+            Event::Text(text) if self.depth == 0 => {
+                // we can only point at the URL itself when the text is
+                // exactly what was written in the source
+                let verbatim = ctx.src()[span.clone()] == **text;
+
+                for url in find_urls(text) {
+                    let url_text = &text[url.clone()];
+
+                    if verbatim {
+                        let url_span =
+                            span.start + url.start..span.start + url.end;
+                        ctx.report(
+                            url_span.clone(),
+                            format!("Bare URL, {}", url_text),
+                        )
+                        .with_fix(Edit::new(
+                            url_span,
+                            format!("<{}>", url_text),
+                        ));
+                    } else {
+                        ctx.report(
+                            span.clone(),
+                            format!("Bare URL, {}", url_text),
+                        );
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn finish(&mut self, _ctx: &mut Context<'_>) {
+        *self = NoBareUrls::default();
+    }
+}
+
+/// Find anything which looks like a `http://` or `https://` URL.
+fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut offset = 0;
+
+    while let Some(start) = ["http://", "https://"]
+        .iter()
+        .filter_map(|scheme| text[offset..].find(scheme))
+        .min()
+    {
+        let start = offset + start;
+        let rest = &text[start..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '<' || c == '>')
+            .unwrap_or(rest.len());
+        let url = rest[..len].trim_end_matches(|c| ".,;:!?)'\"".contains(c));
+
+        urls.push(start..start + url.len());
+        offset = start + len;
+    }
+
+    urls
+}
+
+/// Two headings shouldn't have the same text, because it makes it hard to
+/// link to them.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NoDuplicateHeading {
+    heading: HeadingText,
+    seen: HashSet<String>,
+}
+
+impl Rule for NoDuplicateHeading {
+    fn id(&self) -> &'static str { "no-duplicate-heading" }
+
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    ) {
+        if !self.heading.update(event, span) {
+            return;
+        }
+
+        let text = self.heading.text.trim().to_string();
+        if !self.seen.insert(text.clone()) {
+            ctx.report(
+                self.heading.span.clone(),
+                format!("Duplicate heading, \"{}\"", text),
+            );
+        }
+    }
+
+    fn finish(&mut self, _ctx: &mut Context<'_>) {
+        *self = NoDuplicateHeading::default();
+    }
+}
+
+/// Fenced code blocks should say which language they contain, so they can be
+/// syntax highlighted.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FencedCodeLanguage;
+
+impl Rule for FencedCodeLanguage {
+    fn id(&self) -> &'static str { "fenced-code-language" }
+
+    fn check(
+        &mut self,
+        event: &Event<'_>,
+        span: Range<usize>,
+        ctx: &mut Context<'_>,
+    ) {
+        if let Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) = event
+        {
+            if info.trim().is_empty() {
+                ctx.report(span, "Fenced code block has no language");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::{Diagnostic, Linter};
+
+    fn lint<R: Rule + 'static>(rule: R, src: &str) -> Vec<Diagnostic> {
+        Linter::empty().with(rule).lint(src)
+    }
+
+    fn fixed(src: &str, diag: &Diagnostic) -> String {
+        diag.fix.as_ref().unwrap().apply(src)
+    }
+
+    #[test]
+    fn heading_jumps() {
+        let src = "# Title\n\n### Skipped\n\n## Fine\n\nSetext\n------\n\n#### Skipped\n";
+
+        let diags = lint(HeadingIncrement::default(), src);
+
+        assert_eq!(diags.len(), 2);
+        assert_eq!(&src[diags[0].span.clone()], "### Skipped\n");
+        assert_eq!(
+            fixed(src, &diags[0]),
+            "# Title\n\n## Skipped\n\n## Fine\n\nSetext\n------\n\n#### Skipped\n"
+        );
+        assert!(diags[1].fix.is_some());
+    }
+
+    #[test]
+    fn trailing_punctuation_is_removed() {
+        let src = "# Usage:\n\n## Why?\n\n## `code`.\n";
+
+        let diags = lint(NoTrailingPunctuation::default(), src);
+
+        assert_eq!(diags.len(), 2);
+        assert_eq!(fixed(src, &diags[0]), "# Usage\n\n## Why?\n\n## `code`.\n");
+        assert_eq!(fixed(src, &diags[1]), "# Usage:\n\n## Why?\n\n## `code`\n");
+    }
+
+    #[test]
+    fn bare_urls_get_angle_brackets() {
+        let src = "See https://example.com/docs. Or <https://example.com> or\n`https://example.com` or [this](http://example.com).\n";
+
+        let diags = lint(NoBareUrls::default(), src);
+
+        assert_eq!(diags.len(), 1);
+        assert_eq!(&src[diags[0].span.clone()], "https://example.com/docs");
+        assert!(fixed(src, &diags[0])
+            .starts_with("See <https://example.com/docs>. Or"));
+    }
+
+    #[test]
+    fn links_images_headings_and_code_blocks() {
+        let src = "# A\n\n# A\n\n[empty]() ![](img.png) ![alt](img.png)\n\n```\ncode\n```\n";
+
+        let ids: Vec<_> = crate::lint::check(src)
+            .into_iter()
+            .map(|d| d.rule)
+            .collect();
+
+        assert_eq!(
+            ids,
+            &[
+                "single-h1",
+                "no-duplicate-heading",
+                "no-empty-links",
+                "no-alt-text",
+                "fenced-code-language"
+            ]
+        );
+    }
+}
@@ -0,0 +1,1141 @@
+use crate::SpannedEvent;
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, LinkType, Tag};
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    fmt::{self, Write},
+    ops::Range,
+};
+
+/// Turn a stream of [`Event`]s back into Markdown text.
+///
+/// The output is normalised rather than a faithful copy of the original
+/// document (e.g. headings always use the `#` style and code blocks are
+/// always fenced), but parsing it again should give you the same events.
+///
+/// Reference-style links ([`LinkType::Reference`], [`LinkType::Collapsed`]
+/// and [`LinkType::Shortcut`]) are written using a label, with the
+/// corresponding link reference definitions emitted at the end of the
+/// document. Links to the same URL share a definition.
+///
+/// The events don't say which label a [`LinkType::Reference`] link used, so
+/// the link's text is used as its label. Labels are only numbered when that
+/// would clash with a definition for a different URL. Use
+/// [`to_markdown_with_source()`] to keep the original labels.
+///
+/// # Examples
+///
+/// ```rust
+/// let src = "Some *emphasised* text with [a link][google].\n\n[google]: https://google.com/";
+///
+/// let events = markedit::parse(src);
+/// let markdown = markedit::to_markdown(events);
+///
+/// assert_eq!(
+///     markdown,
+///     "Some *emphasised* text with [a link][a link].\n\n[a link]: https://google.com/\n",
+/// );
+/// ```
+pub fn to_markdown<'src, I, E>(events: I) -> String
+where
+    I: IntoIterator<Item = E>,
+    E: Borrow<Event<'src>>,
+{
+    let mut buffer = String::new();
+    write_markdown(events, &mut buffer)
+        .expect("Writing to a String never fails");
+    buffer
+}
+
+/// Write a stream of [`Event`]s to something as Markdown text.
+///
+/// See [`to_markdown()`] for more details.
+pub fn write_markdown<'src, I, E, W>(events: I, mut writer: W) -> fmt::Result
+where
+    I: IntoIterator<Item = E>,
+    E: Borrow<Event<'src>>,
+    W: Write,
+{
+    let mut serializer = Serializer::default();
+
+    for event in events {
+        serializer.process(event.borrow(), None);
+    }
+
+    serializer.finish();
+    writer.write_str(&serializer.out)
+}
+
+/// Turn a stream of [`Event`]s back into Markdown text, using their spans in
+/// the original source text to keep the labels of reference-style links.
+///
+/// Events without a span are serialized the same way as [`to_markdown()`].
+///
+/// # Examples
+///
+/// ```rust
+/// use pulldown_cmark::Parser;
+///
+/// let src = "Some *emphasised* text with [a link][google].\n\n[google]: https://google.com/";
+///
+/// let events = Parser::new(src).into_offset_iter();
+/// let markdown = markedit::to_markdown_with_source(src, events);
+///
+/// assert_eq!(
+///     markdown,
+///     "Some *emphasised* text with [a link][google].\n\n[google]: https://google.com/\n",
+/// );
+/// ```
+pub fn to_markdown_with_source<'src, I>(src: &str, events: I) -> String
+where
+    I: IntoIterator,
+    I::Item: SpannedEvent<'src>,
+{
+    let mut serializer = Serializer::default();
+
+    for item in events {
+        let (event, provenance) = item.into_parts();
+        let original = provenance.span().and_then(|span| src.get(span.clone()));
+        serializer.process(&event, original);
+    }
+
+    serializer.finish();
+    serializer.out
+}
+
+/// Find the link reference definitions [`to_markdown_with_source()`] would
+/// use for a document, so blocks spliced back into it (see
+/// [`to_markdown_fragment()`]) can reuse them.
+pub(crate) fn existing_definitions<'a, 'src: 'a, I>(
+    src: &str,
+    events: I,
+) -> LinkDefinitions
+where
+    I: IntoIterator<Item = &'a (Event<'src>, Range<usize>)>,
+{
+    let mut serializer = Serializer::default();
+
+    for (event, span) in events {
+        serializer.process(event, src.get(span.clone()));
+    }
+
+    let mut definitions = serializer.definitions;
+    definitions.written = definitions.definitions.len();
+    definitions
+}
+
+/// Serialize some blocks which will be spliced into the original document,
+/// only writing the link reference definitions it doesn't already have.
+pub(crate) fn to_markdown_fragment<'src, I>(
+    src: &str,
+    definitions: &mut LinkDefinitions,
+    events: I,
+) -> String
+where
+    I: IntoIterator,
+    I::Item: SpannedEvent<'src>,
+{
+    let mut serializer = Serializer {
+        definitions: std::mem::take(definitions),
+        ..Serializer::default()
+    };
+
+    for item in events {
+        let (event, provenance) = item.into_parts();
+        let original = provenance.span().and_then(|span| src.get(span.clone()));
+        serializer.process(&event, original);
+    }
+
+    serializer.finish();
+
+    *definitions = serializer.definitions;
+    definitions.written = definitions.definitions.len();
+
+    serializer.out
+}
+
+/// The amount of space wanted between the previous block and the next one.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Separation {
+    Newline,
+    BlankLine,
+}
+
+#[derive(Debug)]
+struct ListState {
+    next_number: Option<u64>,
+    marker: char,
+    /// Loose lists have blank lines between their items (pulldown-cmark
+    /// wraps the item's contents in a paragraph).
+    loose: bool,
+}
+
+/// Block-level elements which may contain other blocks.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Container {
+    BlockQuote,
+    Item,
+    FootnoteDefinition,
+}
+
+#[derive(Debug, Default)]
+struct CodeBlock {
+    info: Option<String>,
+    contents: String,
+}
+
+#[derive(Debug)]
+struct LinkState {
+    /// Where the link's text starts in the output.
+    text_start: usize,
+    /// The label used in the original source text, if known.
+    label: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Serializer {
+    out: String,
+    /// The text to write at the start of each line (e.g. `"> "` inside a
+    /// block quote).
+    padding: Vec<String>,
+    containers: Vec<Container>,
+    at_line_start: bool,
+    /// Has anything other than padding or a block marker (e.g. `"- "`) been
+    /// written to the current line?
+    line_has_content: bool,
+    /// Have we just written a list item marker or footnote label, meaning
+    /// the next block should go on the same line?
+    after_marker: bool,
+    separation: Option<Separation>,
+    lists: Vec<ListState>,
+    /// The marker used by the most recently closed list at each nesting
+    /// level, so consecutive lists don't get merged into one.
+    previous_list_markers: HashMap<usize, char>,
+    links: Vec<LinkState>,
+    code_block: Option<CodeBlock>,
+    in_html_block: bool,
+    in_heading: bool,
+    in_table_cell: bool,
+    /// Was the previous event a shortcut link (e.g. `[foo]`)?
+    after_shortcut_link: bool,
+    table_alignments: Vec<Alignment>,
+    definitions: LinkDefinitions,
+}
+
+impl Serializer {
+    /// Serialize an [`Event`], where `original` is the text it was parsed
+    /// from (if known).
+    fn process(&mut self, event: &Event<'_>, original: Option<&str>) {
+        let after_shortcut_link = self.after_shortcut_link;
+        self.after_shortcut_link = false;
+
+        if self.in_html_block && !matches!(event, Event::Html(_)) {
+            self.in_html_block = false;
+            self.end_block();
+        }
+
+        match event {
+            Event::Start(tag) => self.start_tag(tag, original),
+            Event::End(tag) => self.end_tag(tag),
+            Event::Text(text) if self.code_block.is_some() => {
+                if let Some(code_block) = self.code_block.as_mut() {
+                    code_block.contents.push_str(text);
+                }
+            },
+            Event::Text(text) => {
+                let escaped = escape_text(
+                    text,
+                    !self.line_has_content,
+                    self.in_table_cell,
+                    after_shortcut_link,
+                );
+                self.write(&escaped);
+            },
+            Event::Code(code) => self.write_inline_code(code),
+            Event::Html(html) if self.is_html_block(html) => {
+                if !self.in_html_block {
+                    self.start_block();
+                    self.in_html_block = true;
+                }
+                self.write_lines(html);
+            },
+            Event::Html(html) => self.write(html),
+            Event::FootnoteReference(label) => {
+                self.write(&format!("[^{}]", label))
+            },
+            Event::SoftBreak if self.in_heading => self.write(" "),
+            Event::SoftBreak => self.newline(),
+            Event::HardBreak => {
+                self.write("\\");
+                self.newline();
+            },
+            Event::Rule => {
+                self.start_block();
+                self.write("***");
+                self.end_block();
+            },
+            Event::TaskListMarker(checked) => {
+                // the checkbox is part of the list marker, so a loose item's
+                // paragraph still goes on the same line
+                self.write_marker(if *checked { "[x] " } else { "[ ] " })
+            },
+        }
+    }
+
+    fn is_html_block(&self, html: &str) -> bool {
+        // Note: pulldown-cmark doesn't distinguish between inline HTML and
+        // HTML blocks, so we need to use some heuristics
+        self.in_html_block
+            || html.ends_with('\n')
+            || self.separation.is_some()
+            || self.out.is_empty()
+    }
+
+    fn start_tag(&mut self, tag: &Tag<'_>, original: Option<&str>) {
+        match tag {
+            Tag::Paragraph => {
+                if self.containers.last() == Some(&Container::Item) {
+                    if let Some(list) = self.lists.last_mut() {
+                        list.loose = true;
+                    }
+                }
+                self.start_block();
+            },
+            Tag::Heading(level) => {
+                self.start_block();
+                self.in_heading = true;
+                self.write(&"#".repeat(*level as usize));
+                self.write(" ");
+            },
+            Tag::BlockQuote => {
+                self.start_block();
+                self.write_marker("> ");
+                self.padding.push(String::from("> "));
+                self.containers.push(Container::BlockQuote);
+                self.after_marker = true;
+            },
+            Tag::CodeBlock(kind) => {
+                self.start_block();
+                let info = match kind {
+                    CodeBlockKind::Fenced(info) => Some(info.to_string()),
+                    CodeBlockKind::Indented => None,
+                };
+                self.code_block = Some(CodeBlock {
+                    info,
+                    contents: String::new(),
+                });
+            },
+            Tag::List(start) => {
+                let depth = self.lists.len();
+                let previous = self.previous_list_markers.get(&depth).copied();
+                if !self.after_marker {
+                    // Note: a list can start on the same line as its parent's
+                    // marker (e.g. "- - nested")
+                    self.start_block();
+                }
+                let marker = match (start, previous) {
+                    (Some(_), Some('.')) => ')',
+                    (Some(_), _) => '.',
+                    (None, Some('-')) => '*',
+                    (None, _) => '-',
+                };
+                self.lists.push(ListState {
+                    next_number: *start,
+                    marker,
+                    loose: false,
+                });
+            },
+            Tag::Item => {
+                self.start_block();
+                let list = self.lists.last_mut().expect("Items are in lists");
+                let marker = match list.next_number {
+                    Some(number) => {
+                        list.next_number = Some(number + 1);
+                        format!("{}{} ", number, list.marker)
+                    },
+                    None => format!("{} ", list.marker),
+                };
+                self.write_marker(&marker);
+                self.padding.push(" ".repeat(marker.len()));
+                self.containers.push(Container::Item);
+                self.after_marker = true;
+            },
+            Tag::FootnoteDefinition(label) => {
+                self.start_block();
+                self.write_marker(&format!("[^{}]: ", label));
+                self.padding.push(String::from("    "));
+                self.containers.push(Container::FootnoteDefinition);
+                self.after_marker = true;
+            },
+            Tag::Table(alignments) => {
+                self.start_block();
+                self.table_alignments = alignments.clone();
+            },
+            Tag::TableHead => self.write("|"),
+            Tag::TableRow => {
+                self.newline();
+                self.write("|");
+            },
+            Tag::TableCell => {
+                self.in_table_cell = true;
+                self.write(" ");
+            },
+            Tag::Emphasis => self.write("*"),
+            Tag::Strong => self.write("**"),
+            Tag::Strikethrough => self.write("~~"),
+            Tag::Link(..) | Tag::Image(..) => {
+                if let Tag::Image(..) = tag {
+                    self.write("![");
+                } else {
+                    if self.out.ends_with('!') && !self.out.ends_with("\\!") {
+                        // don't accidentally turn the link into an image
+                        self.out.pop();
+                        self.write("\\!");
+                    }
+                    self.write("[");
+                }
+                let label = match tag {
+                    Tag::Link(LinkType::Reference, ..)
+                    | Tag::Image(LinkType::Reference, ..) => {
+                        original.and_then(reference_label).map(String::from)
+                    },
+                    _ => None,
+                };
+                self.links.push(LinkState {
+                    text_start: self.out.len(),
+                    label,
+                });
+            },
+        }
+    }
+
+    fn end_tag(&mut self, tag: &Tag<'_>) {
+        match tag {
+            Tag::Paragraph => self.end_block(),
+            Tag::Heading(_) => {
+                self.in_heading = false;
+                if self.out.ends_with('#') && !self.out.ends_with("\\#") {
+                    // stop trailing #'s being treated as a closing sequence
+                    self.out.pop();
+                    self.write("\\#");
+                }
+                self.end_block();
+            },
+            Tag::BlockQuote | Tag::FootnoteDefinition(_) => {
+                self.padding.pop();
+                self.containers.pop();
+                self.after_marker = false;
+                self.end_block();
+            },
+            Tag::CodeBlock(_) => {
+                let code_block = self.code_block.take().unwrap_or_default();
+                self.write_code_block(code_block);
+                self.end_block();
+            },
+            Tag::List(_) => {
+                let list = self.lists.pop().expect("Unbalanced list tags");
+                self.previous_list_markers
+                    .insert(self.lists.len(), list.marker);
+                self.end_block();
+            },
+            Tag::Item => {
+                self.padding.pop();
+                self.containers.pop();
+                self.after_marker = false;
+
+                let loose = self.lists.last().is_some_and(|list| list.loose);
+                self.separation = Some(if loose {
+                    Separation::BlankLine
+                } else {
+                    Separation::Newline
+                });
+            },
+            Tag::Table(_) => self.end_block(),
+            Tag::TableHead => {
+                self.newline();
+                self.write("|");
+                for alignment in &self.table_alignments {
+                    let delimiter = match alignment {
+                        Alignment::None => " --- |",
+                        Alignment::Left => " :-- |",
+                        Alignment::Center => " :-: |",
+                        Alignment::Right => " --: |",
+                    };
+                    self.out.push_str(delimiter);
+                }
+            },
+            Tag::TableRow => {},
+            Tag::TableCell => {
+                self.in_table_cell = false;
+                self.write(" |");
+            },
+            Tag::Emphasis => self.write("*"),
+            Tag::Strong => self.write("**"),
+            Tag::Strikethrough => self.write("~~"),
+            Tag::Link(link_type, url, title)
+            | Tag::Image(link_type, url, title) => {
+                let link = self.links.pop().expect("Unbalanced link tags");
+                let text = self.out[link.text_start..].to_string();
+                self.end_link(*link_type, url, title, &text, link.label);
+            },
+        }
+    }
+
+    fn end_link(
+        &mut self,
+        link_type: LinkType,
+        url: &str,
+        title: &str,
+        text: &str,
+        label: Option<String>,
+    ) {
+        match link_type {
+            LinkType::Inline => {
+                self.write(&format!("]({})", link_destination(url, title)))
+            },
+            LinkType::Autolink | LinkType::Email => {
+                // autolinks are written as <url>, so we need to replace the
+                // "[" and text we've already written
+                let start = self.out.len() - text.len() - 1;
+                self.out.truncate(start);
+                self.line_has_content = true;
+                self.write(&format!("<{}>", url));
+            },
+            LinkType::Shortcut
+            | LinkType::ShortcutUnknown
+            | LinkType::Collapsed
+            | LinkType::CollapsedUnknown => {
+                if self.definitions.define(text, url, title) {
+                    if link_type == LinkType::Shortcut
+                        || link_type == LinkType::ShortcutUnknown
+                    {
+                        self.write("]");
+                        self.after_shortcut_link = true;
+                    } else {
+                        self.write("][]");
+                    }
+                } else {
+                    // the label is already used for a different URL
+                    let label = self.definitions.numbered(url, title);
+                    self.write(&format!("][{}]", label));
+                }
+            },
+            LinkType::Reference | LinkType::ReferenceUnknown => {
+                let label = self.definitions.reference(
+                    label.as_deref(),
+                    text,
+                    url,
+                    title,
+                );
+                self.write(&format!("][{}]", label));
+            },
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.in_html_block {
+            self.in_html_block = false;
+            self.end_block();
+        }
+        self.ensure_newline();
+
+        if self.definitions.unwritten().is_empty() {
+            return;
+        }
+
+        self.padding.clear();
+        self.start_block();
+
+        for (label, url, title) in self.definitions.unwritten() {
+            self.out.push_str(&format!(
+                "[{}]: {}\n",
+                label,
+                link_destination(url, title)
+            ));
+        }
+    }
+
+    fn write_code_block(&mut self, code_block: CodeBlock) {
+        let CodeBlock { info, contents } = code_block;
+        let info = info.unwrap_or_default();
+        let fence_char = if info.contains('`') { '~' } else { '`' };
+        let longest_fence = contents
+            .lines()
+            .map(|line| {
+                let line = line.trim_start();
+                line.chars().take_while(|&c| c == fence_char).count()
+            })
+            .max()
+            .unwrap_or(0);
+        let fence = fence_char.to_string().repeat(longest_fence.max(2) + 1);
+
+        self.write(&fence);
+        self.write(&info);
+        self.newline();
+        self.write_lines(&contents);
+        if !self.at_line_start {
+            self.newline();
+        }
+        self.write(&fence);
+    }
+
+    fn write_inline_code(&mut self, code: &str) {
+        let longest_run = longest_run_of(code, '`');
+        let fence = "`".repeat(longest_run + 1);
+        let needs_padding = code.starts_with('`')
+            || code.ends_with('`')
+            || (code.starts_with(' ')
+                && code.ends_with(' ')
+                && !code.trim().is_empty());
+
+        let code = if self.in_table_cell {
+            // pulldown-cmark leaves the backslash in "\|" when it appears
+            // inside inline code in a table
+            code.replace("\\|", "|").replace('|', "\\|")
+        } else {
+            code.to_string()
+        };
+
+        if needs_padding {
+            self.write(&format!("{} {} {}", fence, code, fence));
+        } else {
+            self.write(&format!("{}{}{}", fence, code, fence));
+        }
+    }
+
+    /// Prepare to write a new block, inserting any blank lines or newlines
+    /// needed to separate it from the previous block.
+    fn start_block(&mut self) {
+        // only lists which are directly next to each other need different
+        // markers
+        let depth = self.lists.len();
+        self.previous_list_markers.retain(|&d, _| d < depth);
+
+        if self.after_marker {
+            // the first block in a list item goes on the same line as the
+            // list marker
+            self.after_marker = false;
+            self.separation = None;
+            return;
+        }
+
+        match self.separation.take() {
+            Some(Separation::BlankLine) => {
+                self.ensure_newline();
+                self.blank_line();
+            },
+            Some(Separation::Newline) => self.ensure_newline(),
+            None if !self.out.is_empty() => self.ensure_newline(),
+            None => {},
+        }
+    }
+
+    /// Remember how much space the next block will need, based on the
+    /// element we're currently inside.
+    fn end_block(&mut self) {
+        let in_tight_item = self.containers.last() == Some(&Container::Item)
+            && self.lists.last().is_some_and(|list| !list.loose);
+
+        self.separation = Some(if in_tight_item {
+            Separation::Newline
+        } else {
+            Separation::BlankLine
+        });
+    }
+
+    fn ensure_newline(&mut self) {
+        if !self.at_line_start && !self.out.is_empty() {
+            self.newline();
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        self.at_line_start = true;
+        self.line_has_content = false;
+    }
+
+    fn blank_line(&mut self) {
+        let padding: String = self.padding.concat();
+        self.out.push_str(padding.trim_end());
+        self.newline();
+    }
+
+    fn write(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        self.write_marker(text);
+        self.line_has_content = true;
+        self.after_marker = false;
+    }
+
+    /// Write some text without counting it as the line's content (e.g. list
+    /// markers).
+    fn write_marker(&mut self, text: &str) {
+        if self.at_line_start {
+            for padding in &self.padding {
+                self.out.push_str(padding);
+            }
+            self.at_line_start = false;
+        }
+
+        self.out.push_str(text);
+    }
+
+    /// Write some text which may span multiple lines.
+    fn write_lines(&mut self, text: &str) {
+        for line in text.split_inclusive('\n') {
+            match line.strip_suffix('\n') {
+                Some("") => self.blank_line(),
+                Some(line) => {
+                    self.write(line);
+                    self.newline();
+                },
+                None => self.write(line),
+            }
+        }
+    }
+}
+
+/// Keeps track of the link reference definitions which need to be written at
+/// the end of the document.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LinkDefinitions {
+    definitions: Vec<(String, String, String)>,
+    labels: HashMap<String, usize>,
+    next_number: usize,
+    /// How many of the definitions are already in the document.
+    written: usize,
+}
+
+impl LinkDefinitions {
+    fn unwritten(&self) -> &[(String, String, String)] {
+        &self.definitions[self.written..]
+    }
+
+    /// Try to add a definition, returning `false` if the label is already
+    /// used by a different URL.
+    fn define(&mut self, label: &str, url: &str, title: &str) -> bool {
+        let key = normalize_label(label);
+
+        if let Some(&ix) = self.labels.get(&key) {
+            let (_, existing_url, existing_title) = &self.definitions[ix];
+            return existing_url == url && existing_title == title;
+        }
+
+        self.labels.insert(key, self.definitions.len());
+        self.definitions.push((
+            label.to_string(),
+            url.to_string(),
+            title.to_string(),
+        ));
+        true
+    }
+
+    /// Pick the label for a [`LinkType::Reference`] link.
+    ///
+    /// The original label is preferred, then any label already defined for
+    /// this URL, then the link's text. Labels are only numbered when all of
+    /// those clash with a definition for a different URL.
+    fn reference(
+        &mut self,
+        original: Option<&str>,
+        text: &str,
+        url: &str,
+        title: &str,
+    ) -> String {
+        if let Some(label) = original {
+            if self.define(label, url, title) {
+                return label.to_string();
+            }
+        }
+
+        let existing = self
+            .definitions
+            .iter()
+            .find(|(_, u, t)| u == url && t == title);
+        if let Some((label, _, _)) = existing {
+            return label.clone();
+        }
+
+        if is_valid_label(text) && self.define(text, url, title) {
+            return text.to_string();
+        }
+
+        self.numbered(url, title)
+    }
+
+    /// Get the numbered label for a URL, reusing an existing label if the
+    /// URL has already been seen.
+    fn numbered(&mut self, url: &str, title: &str) -> String {
+        let existing = self.definitions.iter().find(|(label, u, t)| {
+            u == url && t == title && label.parse::<usize>().is_ok()
+        });
+        if let Some((label, _, _)) = existing {
+            return label.clone();
+        }
+
+        loop {
+            self.next_number += 1;
+            let label = self.next_number.to_string();
+
+            if self.define(&label, url, title) {
+                return label;
+            }
+        }
+    }
+}
+
+/// Get the label from the source text of a full reference link (e.g.
+/// `[text][label]`).
+fn reference_label(original: &str) -> Option<&str> {
+    let inner = original.strip_suffix(']')?;
+    let start = inner
+        .char_indices()
+        .rev()
+        .filter(|&(ix, c)| c == '[' && !inner[..ix].ends_with('\\'))
+        .map(|(ix, _)| ix + 1)
+        .next()?;
+    let label = &inner[start..];
+
+    if is_valid_label(label) {
+        Some(label)
+    } else {
+        None
+    }
+}
+
+/// Can this text be used as a link label without escaping?
+fn is_valid_label(label: &str) -> bool {
+    !label.trim().is_empty()
+        && label.chars().count() <= 999
+        && !label.contains(['[', ']', '\n'])
+}
+
+/// Labels are case-insensitive and consecutive whitespace is collapsed.
+fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn link_destination(url: &str, title: &str) -> String {
+    let url = if url.is_empty()
+        || url.contains(|c: char| c.is_whitespace() || c == '(' || c == ')')
+    {
+        format!("<{}>", url.replace('<', "\\<").replace('>', "\\>"))
+    } else {
+        url.to_string()
+    };
+
+    if title.is_empty() {
+        url
+    } else {
+        format!(
+            "{} \"{}\"",
+            url,
+            title.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    }
+}
+
+fn longest_run_of(text: &str, needle: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+
+    for c in text.chars() {
+        if c == needle {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+
+    longest
+}
+
+/// Escape any characters which would otherwise be interpreted as Markdown.
+pub(crate) fn escape_text(
+    text: &str,
+    at_line_start: bool,
+    in_table_cell: bool,
+    after_shortcut_link: bool,
+) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let chars: Vec<char> = text.chars().collect();
+
+    if at_line_start {
+        if let Some(prefix_len) = block_marker_length(text) {
+            // escape the last character of the marker (e.g. "1\." or "\#")
+            let (marker, rest) = text.split_at(prefix_len);
+            let mut marker_chars = marker.chars();
+            let last = marker_chars.next_back().unwrap();
+            let first_bit = marker_chars.as_str();
+
+            if first_bit.chars().all(|c| c.is_ascii_digit()) {
+                escaped.push_str(first_bit);
+                escaped.push('\\');
+                escaped.push(last);
+            } else {
+                escaped.push('\\');
+                escaped.push_str(marker);
+            }
+
+            escaped.push_str(&escape_text(rest, false, in_table_cell, false));
+            return escaped;
+        }
+    }
+
+    for (i, &c) in chars.iter().enumerate() {
+        let previous = if i == 0 { None } else { Some(chars[i - 1]) };
+        let next = chars.get(i + 1).copied();
+
+        let needs_escaping = match c {
+            '\\' | '*' | '`' | '[' | ']' => true,
+            '_' => {
+                !previous.is_some_and(char::is_alphanumeric)
+                    || !next.is_some_and(char::is_alphanumeric)
+            },
+            '<' => next.is_some_and(|n| {
+                n.is_ascii_alphabetic() || n == '/' || n == '!' || n == '?'
+            }),
+            '&' => looks_like_entity(&chars[i..]),
+            '|' => in_table_cell,
+            '~' => next == Some('~') || previous == Some('~'),
+            '(' if i == 0 => after_shortcut_link,
+            _ => false,
+        };
+
+        if needs_escaping {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+fn looks_like_entity(chars: &[char]) -> bool {
+    let body: Vec<char> = chars
+        .iter()
+        .skip(1)
+        .take_while(|c| c.is_ascii_alphanumeric() || **c == '#')
+        .copied()
+        .collect();
+
+    !body.is_empty() && chars.get(body.len() + 1) == Some(&';')
+}
+
+/// If this line starts with something that looks like the start of a block
+/// (e.g. a `#` for a heading or `-` for a list), how long is it?
+fn block_marker_length(text: &str) -> Option<usize> {
+    let first = text.chars().next()?;
+
+    match first {
+        '#' | '>' | '+' | '-' | '=' | '~' | '|' => {
+            let run = text.chars().take_while(|&c| c == first).count();
+            Some(run)
+        },
+        c if c.is_ascii_digit() => {
+            let digits = text.chars().take_while(char::is_ascii_digit).count();
+            match text[digits..].chars().next() {
+                Some('.') | Some(')') => Some(digits + 1),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{CowStr, Options, Parser};
+
+    /// Parse some text, merging consecutive [`Event::Text`] nodes so we can
+    /// compare event streams which were split differently.
+    fn events(src: &str) -> Vec<Event<'_>> {
+        let mut events: Vec<Event<'_>> = Vec::new();
+
+        for event in Parser::new_ext(src, Options::all()) {
+            match (events.last_mut(), event) {
+                (Some(Event::Text(previous)), Event::Text(text)) => {
+                    *previous = CowStr::from(format!("{}{}", previous, text));
+                },
+                (_, event) => events.push(event),
+            }
+        }
+
+        events
+    }
+
+    fn assert_round_trips(src: &str) {
+        let original = events(src);
+        let markdown = to_markdown(&original);
+        let round_tripped = events(&markdown);
+
+        assert_eq!(
+            round_tripped, original,
+            "Round trip failed\n\n{}\n\n{}",
+            src, markdown
+        );
+    }
+
+    #[test]
+    fn round_trip_blocks() {
+        let inputs = vec![
+            "# Heading\n\nSome *emphasis*, **strong** and ~~strike~~.",
+            "Setext heading\n===\n\nParagraph with\nsoft break",
+            "> Quote\n> spanning lines\n>\n> > Nested quote",
+            "```rust,ignore\nfn main() {}\n\n// with a blank line\n```",
+            "````\n```\nnested fences\n```\n````",
+            "---\n\n<div>\n*html*\n</div>\n\ntext",
+            "| a | b |\n|---|:-:|\n| 1 | 2 \\| 3 |",
+            "Footnote[^note].\n\n[^note]: The note\n    with two lines.",
+        ];
+
+        for src in inputs {
+            assert_round_trips(src);
+        }
+    }
+
+    #[test]
+    fn indented_code_is_fenced() {
+        let markdown = to_markdown(events("    indented\n    code\n"));
+
+        assert_eq!(markdown, "```\nindented\ncode\n```\n");
+    }
+
+    #[test]
+    fn round_trip_lists() {
+        let inputs = vec![
+            "- a\n- b\n  - nested\n  - items\n- c",
+            "1. one\n2. two\n\n   second paragraph\n3. three",
+            "3) three\n4) four",
+            "- a\n\n* separate list",
+            "1. a\n\n2) separate list",
+            "- [ ] task\n- [x] done",
+            "- ```\n  code in a list\n  ```\n- > quote in a list",
+        ];
+
+        for src in inputs {
+            assert_round_trips(src);
+        }
+    }
+
+    #[test]
+    fn round_trip_inlines_and_escaping() {
+        let inputs = vec![
+            "Text with `code`, `` double `tick` `` and `` `start``",
+            "1\\. not a list\n\n\\# not a heading\n\n\\- not a list",
+            "Line with \\*stars\\* and snake_case and \\_under\\_",
+            "Wow\\![not an image](http://example.com)",
+            "<https://example.com/> and <me@example.com>",
+            "[inline](<url with spaces> \"a \\\"title\\\"\")",
+            "![image](image.png \"title\") and <span>inline html</span>",
+            "&amp; &copy; \\&notanentity; 1 < 2",
+            "Hard  \nbreak and trailing \\#",
+        ];
+
+        for src in inputs {
+            assert_round_trips(src);
+        }
+    }
+
+    #[test]
+    fn reference_links_share_definitions() {
+        let src = "[a][x], [b][y], [c] and [d][]
+
+[x]: https://example.com/
+[y]: https://example.com/
+[c]: https://c.com/ \"C\"
+[d]: https://d.com/";
+        let expected = "[a][a], [b][a], [c] and [d][]
+
+[a]: https://example.com/
+[c]: https://c.com/ \"C\"
+[d]: https://d.com/
+";
+
+        let markdown = to_markdown(events(src));
+
+        assert_eq!(markdown, expected);
+        assert_round_trips(src);
+    }
+
+    #[test]
+    fn shortcut_links_with_conflicting_labels_are_numbered() {
+        let events = vec![
+            Event::Start(Tag::Paragraph),
+            Event::Start(Tag::Link(LinkType::Shortcut, "a".into(), "".into())),
+            Event::Text("foo".into()),
+            Event::End(Tag::Link(LinkType::Shortcut, "a".into(), "".into())),
+            Event::Text(" (not a link) ".into()),
+            Event::Start(Tag::Link(LinkType::Shortcut, "b".into(), "".into())),
+            Event::Text("foo".into()),
+            Event::End(Tag::Link(LinkType::Shortcut, "b".into(), "".into())),
+            Event::End(Tag::Paragraph),
+        ];
+
+        let markdown = to_markdown(&events);
+
+        assert_eq!(
+            markdown,
+            "[foo] (not a link) [foo][1]\n\n[foo]: a\n[1]: b\n"
+        );
+    }
+
+    #[test]
+    fn original_reference_labels_are_kept() {
+        let src = "[a][x], ![b][y] and [c][x]
+
+[x]: https://example.com/
+[y]: image.png
+";
+        let events = Parser::new(src).into_offset_iter();
+
+        let markdown = to_markdown_with_source(src, events);
+
+        assert_eq!(markdown, src);
+    }
+
+    #[test]
+    fn only_clashing_reference_labels_are_numbered() {
+        let link = |url: &'static str| {
+            Tag::Link(LinkType::Reference, url.into(), "".into())
+        };
+        let events = vec![
+            Event::Start(Tag::Paragraph),
+            Event::Start(link("a")),
+            Event::Text("foo".into()),
+            Event::End(link("a")),
+            Event::Text(" ".into()),
+            Event::Start(link("b")),
+            Event::Text("foo".into()),
+            Event::End(link("b")),
+            Event::End(Tag::Paragraph),
+        ];
+
+        let markdown = to_markdown(&events);
+
+        assert_eq!(markdown, "[foo][foo] [foo][1]\n\n[foo]: a\n[1]: b\n");
+    }
+
+    #[test]
+    fn only_adjacent_lists_use_different_markers() {
+        let src = "- a\n\nSome text.\n\n- b\n\n<!-- -->\n\n- c\n* d\n";
+
+        let markdown = to_markdown(events(src));
+
+        assert_eq!(
+            markdown,
+            "- a\n\nSome text.\n\n- b\n\n<!-- -->\n\n- c\n\n* d\n"
+        );
+        assert_round_trips(src);
+    }
+}
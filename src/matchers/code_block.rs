@@ -0,0 +1,158 @@
+use crate::{matchers::Matcher, InfoString};
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+
+/// Matches the items inside a code block, including the start and end tags.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{CodeBlock, Matcher};
+///
+/// let src = "```rust,ignore\nfn main() {}\n```\n\n```toml\n[package]\n```";
+///
+/// assert!(CodeBlock::fenced_with_lang("rust").is_in(markedit::parse(src)));
+/// assert!(CodeBlock::fenced_with_lang("rust")
+///     .with_flag("ignore")
+///     .is_in(markedit::parse(src)));
+/// assert!(!CodeBlock::fenced_with_lang("python").is_in(markedit::parse(src)));
+/// assert!(!CodeBlock::indented().is_in(markedit::parse(src)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    inside_code_block: bool,
+    kind: Kind,
+    required: InfoString,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Kind {
+    Any,
+    Indented,
+    Fenced,
+}
+
+impl CodeBlock {
+    fn new(kind: Kind) -> Self {
+        CodeBlock {
+            inside_code_block: false,
+            kind,
+            required: InfoString::default(),
+        }
+    }
+
+    /// Matches any code block.
+    pub fn any() -> Self { CodeBlock::new(Kind::Any) }
+
+    /// Matches only indented code blocks.
+    pub fn indented() -> Self { CodeBlock::new(Kind::Indented) }
+
+    /// Matches only fenced code blocks.
+    pub fn fenced() -> Self { CodeBlock::new(Kind::Fenced) }
+
+    /// Matches fenced code blocks written in a particular language.
+    pub fn fenced_with_lang<S: Into<String>>(lang: S) -> Self {
+        let mut matcher = CodeBlock::fenced();
+        matcher.required.language = Some(lang.into());
+        matcher
+    }
+
+    /// Only match code blocks with a particular flag in their info string
+    /// (e.g. the `ignore` in `rust,ignore`).
+    pub fn with_flag<S: Into<String>>(mut self, name: S) -> Self {
+        self.required.add_flag(name);
+        self
+    }
+
+    /// Only match code blocks with a particular `key=value` attribute in their
+    /// info string (e.g. `title="Cargo.toml"`).
+    pub fn with_attribute<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.required.set(key, value);
+        self
+    }
+
+    fn matches_kind(&self, kind: &CodeBlockKind<'_>) -> bool {
+        match (self.kind, kind) {
+            (Kind::Indented, CodeBlockKind::Fenced(_))
+            | (Kind::Fenced, CodeBlockKind::Indented) => false,
+            (_, CodeBlockKind::Fenced(info)) => {
+                InfoString::parse(info).satisfies(&self.required)
+            },
+            (_, CodeBlockKind::Indented) => {
+                InfoString::default().satisfies(&self.required)
+            },
+        }
+    }
+}
+
+impl Matcher for CodeBlock {
+    fn matches_event(&mut self, event: &Event<'_>) -> bool {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) if self.matches_kind(kind) => {
+                self.inside_code_block = true;
+            },
+            Event::End(Tag::CodeBlock(_)) if self.inside_code_block => {
+                self.inside_code_block = false;
+                // make sure the end tag is also matched
+                return true;
+            },
+            _ => {},
+        }
+
+        self.inside_code_block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_everything_inside_a_code_block() {
+        // The original text for these events was:
+        //
+        // Some text.
+        //
+        // ```toml title="Cargo.toml"
+        // [package]
+        // ```
+        //
+        //     indented
+        let fenced = Tag::CodeBlock(CodeBlockKind::Fenced(
+            "toml title=\"Cargo.toml\"".into(),
+        ));
+        let indented = Tag::CodeBlock(CodeBlockKind::Indented);
+        let events = vec![
+            Event::Start(Tag::Paragraph),
+            Event::Text("Some text.".into()),
+            Event::End(Tag::Paragraph),
+            Event::Start(fenced.clone()),
+            Event::Text("[package]\n".into()),
+            Event::End(fenced),
+            Event::Start(indented.clone()),
+            Event::Text("indented\n".into()),
+            Event::End(indented),
+        ];
+        let inputs = vec![
+            (CodeBlock::any(), vec![3, 4, 5, 6, 7, 8]),
+            (CodeBlock::fenced(), vec![3, 4, 5]),
+            (CodeBlock::indented(), vec![6, 7, 8]),
+            (CodeBlock::fenced_with_lang("toml"), vec![3, 4, 5]),
+            (
+                CodeBlock::any().with_attribute("title", "Cargo.toml"),
+                vec![3, 4, 5],
+            ),
+            (CodeBlock::fenced_with_lang("rust"), vec![]),
+            (CodeBlock::any().with_flag("ignore"), vec![]),
+        ];
+
+        for (matcher, should_be) in inputs {
+            let got: Vec<_> =
+                crate::match_indices(matcher.clone(), &events).collect();
+            assert_eq!(got, should_be, "{:?}", matcher);
+        }
+    }
+}
@@ -1,14 +1,23 @@
 mod and;
+mod code_block;
 mod falling_edge;
 mod heading;
 mod one_shot;
 mod start_of_next_line;
+mod table;
+mod task_item;
 
 pub use and::And;
+pub use code_block::CodeBlock;
 pub use falling_edge::FallingEdge;
 pub use heading::Heading;
 pub use one_shot::OneShot;
 pub use start_of_next_line::StartOfNextLine;
+pub use table::{
+    table_cell, table_row, table_row_where, Column, MatchingRow, TableCell,
+    TablePosition, TableRow, TableRowWhere,
+};
+pub use task_item::{task_item, TaskItem};
 
 use pulldown_cmark::{Event, Tag};
 use std::borrow::Borrow;
@@ -57,6 +66,9 @@ pub trait Matcher {
     ///
     /// assert!(matcher.is_in(markedit::parse(src)));
     /// ```
+    // consuming the matcher is part of the public API, so newer clippy
+    // versions complain about the name
+    #[allow(clippy::wrong_self_convention)]
     fn is_in<'src, I, E>(mut self, events: I) -> bool
     where
         I: IntoIterator<Item = E> + 'src,
@@ -261,6 +273,24 @@ where
     }
 }
 
+/// Match an inline [`Event::Code`] snippet which *contains* the provided
+/// string.
+///
+/// ```rust
+/// use markedit::Matcher;
+///
+/// let src = "Call `Matcher::is_in()` to check for matches.";
+///
+/// assert!(markedit::code_containing("is_in").is_in(markedit::parse(src)));
+/// assert!(!markedit::code_containing("check").is_in(markedit::parse(src)));
+/// ```
+pub fn code_containing<S: AsRef<str>>(needle: S) -> impl Matcher {
+    move |ev: &Event<'_>| match ev {
+        Event::Code(code) => code.contains(needle.as_ref()),
+        _ => false,
+    }
+}
+
 /// Matches the start of a link who's URL contains a certain string.
 ///
 /// # Examples
@@ -0,0 +1,523 @@
+use crate::{matchers::Matcher, Provenance, Rewriter, Writer};
+use pulldown_cmark::{Event, Tag};
+use std::{cell::Cell, rc::Rc};
+
+/// A table column, identified either by its header text or its index.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Column {
+    /// The column whose header contains exactly this text.
+    Name(String),
+    /// The column at this (zero-based) index.
+    Index(usize),
+}
+
+impl<'a> From<&'a str> for Column {
+    fn from(name: &'a str) -> Column { Column::Name(name.to_string()) }
+}
+
+impl From<String> for Column {
+    fn from(name: String) -> Column { Column::Name(name) }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Column { Column::Index(index) }
+}
+
+/// Keeps track of where we are inside a table.
+///
+/// This is used by the [`TableCell`] and [`TableRow`] matchers and
+/// [`table_row_where()`], and as a [`Matcher`] it matches everything inside a
+/// table (including the start and end tags).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TablePosition {
+    in_table: bool,
+    in_head: bool,
+    /// The current body row, if we are inside one.
+    row: Option<usize>,
+    /// The current cell's column, if we are inside one.
+    column: Option<usize>,
+    rows_seen: usize,
+    cells_seen: usize,
+    headers: Vec<String>,
+    cell_text: String,
+    /// End tags are still considered part of the element they close, so we
+    /// need to wait until the next event before leaving it.
+    leaving_cell: bool,
+    leaving_row: bool,
+    leaving_table: bool,
+}
+
+impl TablePosition {
+    /// Create a new [`TablePosition`].
+    pub fn new() -> Self { TablePosition::default() }
+
+    /// Update the position based on the next [`Event`].
+    pub fn update(&mut self, event: &Event<'_>) {
+        if std::mem::take(&mut self.leaving_cell) {
+            self.column = None;
+        }
+        if std::mem::take(&mut self.leaving_row) {
+            self.row = None;
+        }
+        if std::mem::take(&mut self.leaving_table) {
+            *self = TablePosition::default();
+        }
+
+        match event {
+            Event::Start(Tag::Table(_)) => {
+                *self = TablePosition::default();
+                self.in_table = true;
+            },
+            Event::End(Tag::Table(_)) => self.leaving_table = true,
+            Event::Start(Tag::TableHead) => {
+                self.in_head = true;
+                self.cells_seen = 0;
+            },
+            Event::End(Tag::TableHead) => self.in_head = false,
+            Event::Start(Tag::TableRow) => {
+                self.row = Some(self.rows_seen);
+                self.rows_seen += 1;
+                self.cells_seen = 0;
+            },
+            Event::End(Tag::TableRow) => self.leaving_row = true,
+            Event::Start(Tag::TableCell) => {
+                self.column = Some(self.cells_seen);
+                self.cells_seen += 1;
+                self.cell_text.clear();
+            },
+            Event::End(Tag::TableCell) => {
+                if self.in_head {
+                    self.headers.push(self.cell_text.clone());
+                }
+                self.leaving_cell = true;
+            },
+            Event::Text(text) | Event::Code(text) if self.column.is_some() => {
+                self.cell_text.push_str(text);
+            },
+            _ => {},
+        }
+    }
+
+    /// Are we inside a table?
+    pub fn in_table(&self) -> bool { self.in_table }
+
+    /// Are we inside the table's header?
+    pub fn in_header(&self) -> bool { self.in_table && self.in_head }
+
+    /// The index of the current body row (the header isn't counted).
+    pub fn row(&self) -> Option<usize> { self.row }
+
+    /// The index of the current cell's column.
+    pub fn column(&self) -> Option<usize> { self.column }
+
+    /// The text in each of the header's cells.
+    pub fn headers(&self) -> &[String] { &self.headers }
+
+    /// The header text for the current column.
+    pub fn header(&self) -> Option<&str> {
+        self.column
+            .and_then(|ix| self.headers.get(ix))
+            .map(|header| header.as_str())
+    }
+
+    /// The text in the current cell, so far.
+    pub fn cell_text(&self) -> &str { &self.cell_text }
+
+    /// Is the current cell in this column?
+    pub fn in_column(&self, column: &Column) -> bool {
+        match (column, self.column) {
+            (Column::Index(expected), Some(actual)) => *expected == actual,
+            (Column::Name(name), Some(_)) => {
+                self.header().map(str::trim) == Some(name.trim())
+            },
+            (_, None) => false,
+        }
+    }
+}
+
+impl Matcher for TablePosition {
+    fn matches_event(&mut self, event: &Event<'_>) -> bool {
+        self.update(event);
+        self.in_table
+    }
+}
+
+/// Matches the contents of a table's body cells in a particular column,
+/// including the cells' start and end tags.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{pulldown_cmark::{Event, Options}, Matcher};
+///
+/// let src = "| Name | Status |\n| --- | --- |\n| foo | ok |\n| bar | failed |";
+/// let events: Vec<_> =
+///     markedit::parse_with_options(src, Options::ENABLE_TABLES).collect();
+///
+/// let statuses: Vec<_> = markedit::match_indices(markedit::table_cell("Status"), &events)
+///     .filter_map(|ix| match &events[ix] {
+///         Event::Text(text) => Some(text.to_string()),
+///         _ => None,
+///     })
+///     .collect();
+///
+/// assert_eq!(statuses, vec!["ok", "failed"]);
+/// ```
+pub fn table_cell<C: Into<Column>>(column: C) -> TableCell {
+    TableCell {
+        column: column.into(),
+        position: TablePosition::new(),
+    }
+}
+
+/// The [`Matcher`] returned by [`table_cell()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableCell {
+    column: Column,
+    position: TablePosition,
+}
+
+impl TableCell {
+    /// Where we are in the current table.
+    pub fn position(&self) -> &TablePosition { &self.position }
+}
+
+impl Matcher for TableCell {
+    fn matches_event(&mut self, event: &Event<'_>) -> bool {
+        self.position.update(event);
+
+        self.position.row().is_some() && self.position.in_column(&self.column)
+    }
+}
+
+/// Matches everything in a particular row of a table's body (not counting the
+/// header), including the row's start and end tags.
+///
+/// ```rust
+/// use markedit::{pulldown_cmark::{Event, Options}, Matcher};
+///
+/// let src = "| Name |\n| --- |\n| first |\n| second |";
+/// let events: Vec<_> =
+///     markedit::parse_with_options(src, Options::ENABLE_TABLES).collect();
+///
+/// let ix = markedit::exact_text("second")
+///     .and(markedit::table_row(1))
+///     .first_match(&events);
+///
+/// assert!(ix.is_some());
+/// ```
+pub fn table_row(index: usize) -> TableRow {
+    TableRow {
+        index,
+        position: TablePosition::new(),
+    }
+}
+
+/// The [`Matcher`] returned by [`table_row()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRow {
+    index: usize,
+    position: TablePosition,
+}
+
+impl TableRow {
+    /// Where we are in the current table.
+    pub fn position(&self) -> &TablePosition { &self.position }
+}
+
+impl Matcher for TableRow {
+    fn matches_event(&mut self, event: &Event<'_>) -> bool {
+        self.position.update(event);
+
+        self.position.row() == Some(self.index)
+    }
+}
+
+/// Picks out the table rows where the text in one of their cells satisfies a
+/// predicate.
+///
+/// Each body row is buffered until it ends so the predicate can see every
+/// cell, meaning the column being tested may come before or after the cells
+/// you want to change. Use [`TableRowWhere::rewrite_with()`] to build a
+/// [`Rewriter`] from a [`MatchingRow`] matcher, combining it with
+/// [`table_cell()`] to pick out a single cell.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{pulldown_cmark::Options, Matcher};
+///
+/// let src = "| Status | Name |\n| --- | --- |\n| failed | foo |\n| failed | bar |";
+///
+/// let rewriter = markedit::table_row_where("Name", |name: &str| name == "foo")
+///     .rewrite_with(|row| {
+///         let status = row.and(markedit::table_cell("Status"));
+///         markedit::change_matched_text(status, |_| "ok")
+///     });
+///
+/// let events = markedit::parse_with_options(src, Options::ENABLE_TABLES);
+/// let markdown = markedit::to_markdown(markedit::rewrite(events, rewriter));
+///
+/// assert_eq!(
+///     markdown,
+///     "| Status | Name |\n| --- | --- |\n| ok | foo |\n| failed | bar |\n",
+/// );
+/// ```
+pub fn table_row_where<C, P>(column: C, predicate: P) -> TableRowWhere<P>
+where
+    C: Into<Column>,
+    P: FnMut(&str) -> bool,
+{
+    TableRowWhere {
+        column: column.into(),
+        predicate,
+    }
+}
+
+/// The row selector returned by [`table_row_where()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRowWhere<P> {
+    column: Column,
+    predicate: P,
+}
+
+impl<P> TableRowWhere<P> {
+    /// Create a [`Rewriter`] which passes every [`Event`] to the [`Rewriter`]
+    /// returned by `make_rewriter`, where the [`MatchingRow`] matches
+    /// everything in the rows selected by the predicate.
+    pub fn rewrite_with<'src, F, R>(
+        self,
+        make_rewriter: F,
+    ) -> impl Rewriter<'src> + 'src
+    where
+        P: FnMut(&str) -> bool + 'src,
+        F: FnOnce(MatchingRow) -> R,
+        R: Rewriter<'src> + 'src,
+    {
+        let in_matching_row = Rc::new(Cell::new(false));
+        let inner = make_rewriter(MatchingRow {
+            in_matching_row: Rc::clone(&in_matching_row),
+        });
+
+        RowsWhere {
+            column: self.column,
+            predicate: self.predicate,
+            inner,
+            in_matching_row,
+            position: TablePosition::new(),
+            row: None,
+            row_matches: false,
+        }
+    }
+}
+
+/// The [`Matcher`] given to [`TableRowWhere::rewrite_with()`], which matches
+/// everything in a selected row (including the row's start and end tags).
+#[derive(Debug, Clone)]
+pub struct MatchingRow {
+    in_matching_row: Rc<Cell<bool>>,
+}
+
+impl Matcher for MatchingRow {
+    fn matches_event(&mut self, _event: &Event<'_>) -> bool {
+        self.in_matching_row.get()
+    }
+}
+
+struct RowsWhere<'src, P, R> {
+    column: Column,
+    predicate: P,
+    inner: R,
+    in_matching_row: Rc<Cell<bool>>,
+    position: TablePosition,
+    /// The body row currently being buffered, and where each event came
+    /// from.
+    row: Option<Vec<(Event<'src>, Provenance)>>,
+    row_matches: bool,
+}
+
+impl<'src, P, R> RowsWhere<'src, P, R>
+where
+    R: Rewriter<'src>,
+{
+    fn flush_row(&mut self, writer: &mut Writer<'src>) {
+        if let Some(row) = self.row.take() {
+            self.in_matching_row.set(std::mem::take(&mut self.row_matches));
+
+            for (event, provenance) in row {
+                writer.set_current(Some(provenance));
+                self.inner.rewrite_event(event, writer);
+            }
+
+            self.in_matching_row.set(false);
+        }
+    }
+}
+
+impl<'src, P, R> Rewriter<'src> for RowsWhere<'src, P, R>
+where
+    P: FnMut(&str) -> bool,
+    R: Rewriter<'src>,
+{
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        self.position.update(&event);
+
+        match event {
+            Event::Start(Tag::TableRow) if self.position.row().is_some() => {
+                self.row = Some(vec![(event, writer.provenance())]);
+            },
+            Event::End(Tag::TableRow) if self.row.is_some() => {
+                if let Some(ref mut row) = self.row {
+                    row.push((event, writer.provenance()));
+                }
+                self.flush_row(writer);
+            },
+            _ => {
+                if matches!(event, Event::End(Tag::TableCell))
+                    && self.position.in_column(&self.column)
+                {
+                    self.row_matches =
+                        (self.predicate)(self.position.cell_text().trim());
+                }
+
+                match self.row {
+                    Some(ref mut row) => {
+                        row.push((event, writer.provenance()))
+                    },
+                    None => self.inner.rewrite_event(event, writer),
+                }
+            },
+        }
+    }
+
+    fn finish(&mut self, writer: &mut Writer<'src>) {
+        self.flush_row(writer);
+        writer.set_current(None);
+        self.inner.finish(writer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Options;
+
+    const SRC: &str = "| Name | Status |
+| --- | --- |
+| foo | ok |
+| bar | failed |
+
+| Status |
+| --- |
+| unrelated |
+";
+
+    fn matched_text<M: Matcher>(matcher: M) -> Vec<String> {
+        let events: Vec<_> =
+            crate::parse_with_options(SRC, Options::ENABLE_TABLES).collect();
+
+        crate::match_indices(matcher, &events)
+            .filter_map(|ix| match &events[ix] {
+                Event::Text(text) => Some(text.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn track_positions_in_a_table() {
+        let events: Vec<_> =
+            crate::parse_with_options(SRC, Options::ENABLE_TABLES).collect();
+        let mut position = TablePosition::new();
+        let mut got = Vec::new();
+
+        for event in &events {
+            position.update(event);
+
+            if let Event::Text(text) = event {
+                got.push((
+                    text.to_string(),
+                    position.row(),
+                    position.header().map(String::from),
+                ));
+            }
+        }
+
+        let status = || Some(String::from("Status"));
+        assert_eq!(
+            got,
+            vec![
+                (String::from("Name"), None, None),
+                (String::from("Status"), None, None),
+                (String::from("foo"), Some(0), Some(String::from("Name"))),
+                (String::from("ok"), Some(0), status()),
+                (String::from("bar"), Some(1), Some(String::from("Name"))),
+                (String::from("failed"), Some(1), status()),
+                (String::from("Status"), None, None),
+                (String::from("unrelated"), Some(0), status()),
+            ]
+        );
+    }
+
+    #[test]
+    fn match_cells_rows_and_predicates() {
+        assert_eq!(
+            matched_text(table_cell(0)),
+            vec!["foo", "bar", "unrelated"]
+        );
+        assert_eq!(
+            matched_text(table_cell("Status")),
+            vec!["ok", "failed", "unrelated"]
+        );
+        assert_eq!(matched_text(table_row(1)), vec!["bar", "failed"]);
+    }
+
+    #[test]
+    fn row_predicates_can_look_at_later_columns() {
+        let src = "| Status | Name |
+| --- | --- |
+| failed | foo |
+| failed | bar |
+
+| Name |
+| --- |
+| foo |
+";
+        let rewriter = table_row_where("Name", |name: &str| name == "foo")
+            .rewrite_with(|row| {
+                crate::change_matched_text(row, |text| text.to_uppercase())
+            });
+
+        let events = crate::parse_with_options(src, Options::ENABLE_TABLES);
+        let markdown = crate::to_markdown(crate::rewrite(events, rewriter));
+
+        assert_eq!(
+            markdown,
+            "| Status | Name |
+| --- | --- |
+| FAILED | FOO |
+| failed | bar |
+
+| Name |
+| --- |
+| FOO |
+"
+        );
+    }
+
+    #[test]
+    fn untouched_rows_keep_their_original_text() {
+        let src = "| Name | Status |\n|---|---|\n| foo | ok |\n";
+        let events =
+            pulldown_cmark::Parser::new_ext(src, Options::ENABLE_TABLES)
+                .into_offset_iter();
+
+        let mut rewriter = table_row_where("Status", |s: &str| s == "failed")
+            .rewrite_with(|row| {
+                crate::change_matched_text(row, |text| text.to_uppercase())
+            });
+        let edits = crate::rewrite_to_edits(src, events, &mut rewriter);
+
+        assert_eq!(edits.apply(src), src);
+    }
+}
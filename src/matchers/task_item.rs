@@ -0,0 +1,120 @@
+use crate::matchers::Matcher;
+use pulldown_cmark::{Event, Tag};
+
+/// Matches a GitHub-flavoured Markdown task list item (`- [ ] like this`),
+/// optionally only matching items which are (or aren't) checked.
+///
+/// The checkbox is the first thing inside a [`Tag::Item`], so this matches
+/// from the [`Event::TaskListMarker`] up to and including the item's end tag
+/// (including any nested lists). Because [`Event`]s are processed as a stream,
+/// the item's start tag can't be matched.
+///
+/// Remember that task lists are an extension to CommonMark, so you'll need to
+/// parse with [`Options::ENABLE_TASKLISTS`][pulldown_cmark::Options].
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{pulldown_cmark::{Event, Options}, Matcher};
+///
+/// let src = "- [x] Write the code\n- [ ] Write the docs\n- Not a task";
+/// let events: Vec<_> =
+///     markedit::parse_with_options(src, Options::ENABLE_TASKLISTS).collect();
+///
+/// let todo: Vec<_> = markedit::match_indices(markedit::task_item(Some(false)), &events)
+///     .filter_map(|ix| match &events[ix] {
+///         Event::Text(text) => Some(text.to_string()),
+///         _ => None,
+///     })
+///     .collect();
+///
+/// assert_eq!(todo, vec!["Write the docs"]);
+/// assert!(markedit::task_item(None).is_in(&events));
+/// ```
+pub fn task_item(checked: Option<bool>) -> TaskItem {
+    TaskItem {
+        checked,
+        item_depth: 0,
+        matched_depth: None,
+    }
+}
+
+/// The [`Matcher`] returned by [`task_item()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskItem {
+    checked: Option<bool>,
+    item_depth: usize,
+    /// The depth of the task item we're currently inside.
+    matched_depth: Option<usize>,
+}
+
+impl TaskItem {
+    fn matches_checked(&self, checked: bool) -> bool {
+        match self.checked {
+            Some(expected) => checked == expected,
+            None => true,
+        }
+    }
+}
+
+impl Matcher for TaskItem {
+    fn matches_event(&mut self, event: &Event<'_>) -> bool {
+        match event {
+            Event::Start(Tag::Item) => self.item_depth += 1,
+            Event::TaskListMarker(checked)
+                if self.matched_depth.is_none()
+                    && self.matches_checked(*checked) =>
+            {
+                self.matched_depth = Some(self.item_depth);
+            },
+            Event::End(Tag::Item) => {
+                let depth = self.item_depth;
+                self.item_depth = self.item_depth.saturating_sub(1);
+
+                if self.matched_depth == Some(depth) {
+                    self.matched_depth = None;
+                    // make sure the end tag is also matched
+                    return true;
+                }
+            },
+            _ => {},
+        }
+
+        self.matched_depth.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Options;
+
+    #[test]
+    fn nested_items_are_part_of_their_parent() {
+        let src = "- [x] done
+  - [ ] nested
+- [ ] todo
+";
+        let events: Vec<_> =
+            crate::parse_with_options(src, Options::ENABLE_TASKLISTS).collect();
+
+        let got: Vec<_> = crate::match_indices(task_item(Some(true)), &events)
+            .map(|ix| events[ix].clone())
+            .collect();
+
+        assert_eq!(
+            got,
+            vec![
+                Event::TaskListMarker(true),
+                Event::Text("done".into()),
+                Event::Start(Tag::List(None)),
+                Event::Start(Tag::Item),
+                Event::TaskListMarker(false),
+                Event::Text("nested".into()),
+                Event::End(Tag::Item),
+                Event::End(Tag::List(None)),
+                Event::End(Tag::Item),
+            ]
+        );
+    }
+}
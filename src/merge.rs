@@ -0,0 +1,387 @@
+use crate::{
+    blocks::{self, Block},
+    diff::{self, Op},
+};
+use pulldown_cmark::{CowStr, Event};
+use std::ops::Range;
+
+/// Merge two sets of changes to the same document, one [`Block`] at a time.
+///
+/// Blocks which were only changed on one side (or changed the same way on
+/// both) are merged automatically, even when the other side changed the
+/// block next to them. When `ours` and `theirs` both changed the same blocks
+/// in different ways, or inserted different blocks at the same spot, both
+/// versions are kept and wrapped in git-style conflict markers.
+///
+/// # Examples
+///
+/// ```rust
+/// let base = "# Title\n\nIntro\n\n## Usage\n\nRun it.\n";
+/// let ours = "# Title\n\nA better intro.\n\n## Usage\n\nRun it.\n";
+/// let theirs = "# Title\n\nIntro\n\n## Usage\n\nRun it twice.\n";
+///
+/// let merged = markedit::merge(
+///     markedit::parse(base),
+///     markedit::parse(ours),
+///     markedit::parse(theirs),
+/// );
+///
+/// assert!(merged.conflicts.is_empty());
+/// assert_eq!(
+///     merged.to_markdown(),
+///     "# Title\n\nA better intro.\n\n## Usage\n\nRun it twice.\n",
+/// );
+/// ```
+pub fn merge<'src, B, O, T>(base: B, ours: O, theirs: T) -> Merge<'src>
+where
+    B: IntoIterator<Item = Event<'src>>,
+    O: IntoIterator<Item = Event<'src>>,
+    T: IntoIterator<Item = Event<'src>>,
+{
+    let base = blocks::split_blocks(base);
+    let ours = blocks::split_blocks(ours);
+    let theirs = blocks::split_blocks(theirs);
+
+    let ours_matches = matches(&base, &ours);
+    let mut merged = Merge::default();
+    let mut next_base = 0;
+
+    for cluster in clusters(changes(&base, &ours), changes(&base, &theirs)) {
+        let before = next_base..cluster.base.start;
+        merged.unchanged(&base, before, &ours, &ours_matches);
+        merged.merge_cluster(&base, &ours, &theirs, &cluster);
+        next_base = cluster.base.end;
+    }
+
+    merged.unchanged(&base, next_base..base.len(), &ours, &ours_matches);
+
+    merged
+}
+
+/// A run of changes one side made to the base document.
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    /// The base blocks which were replaced, or an empty range for a pure
+    /// insertion.
+    base: Range<usize>,
+    /// The blocks they were replaced with.
+    replacement: Range<usize>,
+}
+
+/// Overlapping [`Change`]s from both sides.
+#[derive(Debug, Default)]
+struct Cluster {
+    base: Range<usize>,
+    ours: Vec<Change>,
+    theirs: Vec<Change>,
+}
+
+/// For each block in `base`, find the matching block in `other` (if it
+/// wasn't changed).
+fn matches(base: &[Block<'_>], other: &[Block<'_>]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; base.len()];
+
+    for op in diff::diff_lines(&keys(base), &keys(other)) {
+        if let Op::Equal { old, new } = op {
+            matches[old] = Some(new);
+        }
+    }
+
+    matches
+}
+
+/// Find the runs of blocks `other` changed.
+fn changes(base: &[Block<'_>], other: &[Block<'_>]) -> Vec<Change> {
+    let ops = diff::diff_lines(&keys(base), &keys(other));
+
+    ops.split(|op| matches!(op, Op::Equal { .. }))
+        .filter(|run| !run.is_empty())
+        .map(|run| {
+            // deletions are sorted before insertions, so the positions
+            // aren't monotonic
+            let old = run.iter().map(|op| op.position().0).min().unwrap_or(0);
+            let new = run.iter().map(|op| op.position().1).min().unwrap_or(0);
+            let deleted =
+                run.iter().filter(|op| matches!(op, Op::Delete { .. })).count();
+            let inserted = run.len() - deleted;
+
+            Change {
+                base: old..old + deleted,
+                replacement: new..new + inserted,
+            }
+        })
+        .collect()
+}
+
+fn keys<'a>(blocks: &'a [Block<'_>]) -> Vec<&'a str> {
+    blocks.iter().map(Block::key).collect()
+}
+
+/// Group changes which touch the same base blocks, or insert at the same
+/// spot.
+fn clusters(ours: Vec<Change>, theirs: Vec<Change>) -> Vec<Cluster> {
+    let mut changes: Vec<(bool, Change)> = ours
+        .into_iter()
+        .map(|change| (true, change))
+        .chain(theirs.into_iter().map(|change| (false, change)))
+        .collect();
+    changes.sort_by_key(|(_, change)| (change.base.start, change.base.end));
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+
+    for (is_ours, change) in changes {
+        let cluster = match clusters.last_mut() {
+            Some(last) if overlaps(&last.base, &change.base) => last,
+            _ => {
+                clusters.push(Cluster {
+                    base: change.base.clone(),
+                    ..Cluster::default()
+                });
+                clusters.last_mut().unwrap()
+            },
+        };
+
+        cluster.base.end = cluster.base.end.max(change.base.end);
+        if is_ours {
+            cluster.ours.push(change);
+        } else {
+            cluster.theirs.push(change);
+        }
+    }
+
+    clusters
+}
+
+/// Do two changes touch the same base blocks, or insert at the same spot?
+///
+/// Changes to neighbouring blocks, or an insertion next to a changed block,
+/// don't overlap.
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => a.start == b.start,
+        (true, false) => b.start < a.start && a.start < b.end,
+        (false, true) => a.start < b.start && b.start < a.end,
+        (false, false) => a.start < b.end && b.start < a.end,
+    }
+}
+
+/// Apply one side's changes to a range of base blocks.
+fn apply<'src>(
+    base: &[Block<'src>],
+    range: Range<usize>,
+    side: &[Block<'src>],
+    changes: &[Change],
+) -> Vec<Block<'src>> {
+    let mut blocks = Vec::new();
+    let mut changes = changes.iter().peekable();
+    let mut ix = range.start;
+
+    loop {
+        while let Some(change) = changes.next_if(|c| c.base.start == ix) {
+            blocks.extend_from_slice(&side[change.replacement.clone()]);
+            ix = change.base.end;
+        }
+
+        if ix >= range.end {
+            return blocks;
+        }
+
+        blocks.push(base[ix].clone());
+        ix += 1;
+    }
+}
+
+fn same_blocks(left: &[Block<'_>], right: &[Block<'_>]) -> bool {
+    left.len() == right.len()
+        && left.iter().zip(right).all(|(l, r)| l.same_content(r))
+}
+
+/// The result of a [`merge()`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Merge<'src> {
+    /// The merged document, including conflict markers.
+    pub events: Vec<Event<'src>>,
+    /// Places where `ours` and `theirs` made incompatible changes.
+    pub conflicts: Vec<Conflict<'src>>,
+}
+
+impl<'src> Merge<'src> {
+    /// Were both sets of changes merged without any conflicts?
+    pub fn is_clean(&self) -> bool { self.conflicts.is_empty() }
+
+    /// Render the merged document as Markdown.
+    pub fn to_markdown(&self) -> String { crate::to_markdown(&self.events) }
+
+    /// Copy across blocks which neither side changed.
+    fn unchanged(
+        &mut self,
+        base: &[Block<'src>],
+        range: Range<usize>,
+        ours: &[Block<'src>],
+        ours_matches: &[Option<usize>],
+    ) {
+        for ix in range {
+            let block = ours_matches[ix].map_or(&base[ix], |o| &ours[o]);
+            self.events.extend(block.events.iter().cloned());
+        }
+    }
+
+    fn merge_cluster(
+        &mut self,
+        base: &[Block<'src>],
+        ours: &[Block<'src>],
+        theirs: &[Block<'src>],
+        cluster: &Cluster,
+    ) {
+        let range = cluster.base.clone();
+        let ours = apply(base, range.clone(), ours, &cluster.ours);
+        let theirs = apply(base, range.clone(), theirs, &cluster.theirs);
+
+        let resolved = if cluster.ours.is_empty() {
+            theirs
+        } else if cluster.theirs.is_empty() || same_blocks(&ours, &theirs) {
+            ours
+        } else {
+            self.conflict(&base[range], &ours, &theirs);
+            return;
+        };
+
+        for block in resolved {
+            self.events.extend(block.events);
+        }
+    }
+
+    fn conflict(
+        &mut self,
+        base: &[Block<'src>],
+        ours: &[Block<'src>],
+        theirs: &[Block<'src>],
+    ) {
+        let marker =
+            |text: &str| Event::Html(CowStr::from(format!("{}\n", text)));
+
+        self.events.push(marker("<<<<<<< ours"));
+        for block in ours {
+            self.events.extend(block.events.iter().cloned());
+        }
+        self.events.push(marker("======="));
+        for block in theirs {
+            self.events.extend(block.events.iter().cloned());
+        }
+        self.events.push(marker(">>>>>>> theirs"));
+
+        let heading_path = base
+            .iter()
+            .chain(ours)
+            .chain(theirs)
+            .next()
+            .map(|block| block.heading_path.clone())
+            .unwrap_or_default();
+
+        self.conflicts.push(Conflict {
+            heading_path,
+            base: base.to_vec(),
+            ours: ours.to_vec(),
+            theirs: theirs.to_vec(),
+        });
+    }
+}
+
+/// Blocks which were changed differently by both sides of a [`merge()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict<'src> {
+    /// The headings the conflict is nested under, outermost first.
+    pub heading_path: Vec<String>,
+    /// The blocks in the common ancestor.
+    pub base: Vec<Block<'src>>,
+    /// Our version of the blocks.
+    pub ours: Vec<Block<'src>>,
+    /// Their version of the blocks.
+    pub theirs: Vec<Block<'src>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge_str(base: &str, ours: &str, theirs: &str) -> Merge<'static> {
+        let parse = |src: &str| {
+            crate::parse(src)
+                .map(crate::rewriters::owned_event)
+                .collect::<Vec<_>>()
+        };
+
+        merge(parse(base), parse(ours), parse(theirs))
+    }
+
+    #[test]
+    fn only_conflicting_blocks_get_markers() {
+        let base = "# Title\n\nIntro\n\n## Usage\n\nRun it.\n";
+        let ours =
+            "# Title\n\nOur intro\n\n## Usage\n\nRun it.\n\nMore usage.\n";
+        let theirs = "# Title\n\nTheir intro\n\n## Usage\n\nRun it.\n";
+
+        let merged = merge_str(base, ours, theirs);
+
+        assert_eq!(
+            merged.to_markdown(),
+            "# Title\n\n<<<<<<< ours\n\nOur intro\n\n=======\n\nTheir intro\n\n>>>>>>> theirs\n\n## Usage\n\nRun it.\n\nMore usage.\n"
+        );
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!(merged.conflicts[0].heading_path, &["Title"]);
+        assert_eq!(merged.conflicts[0].base[0].markdown(), "Intro");
+    }
+
+    #[test]
+    fn adjacent_edits_merge_cleanly() {
+        let base = "# Title\n\nFirst\n\nSecond\n\nThird\n";
+        let ours = "# Title\n\nOur first\n\nSecond\n\nThird\n\nAppended\n";
+        let theirs = "# Title\n\nFirst\n\nTheir second\n\nThird\n";
+
+        let merged = merge_str(base, ours, theirs);
+
+        assert!(merged.is_clean());
+        assert_eq!(
+            merged.to_markdown(),
+            "# Title\n\nOur first\n\nTheir second\n\nThird\n\nAppended\n"
+        );
+    }
+
+    #[test]
+    fn insertions_at_the_same_spot_conflict() {
+        let base = "A\n\nB\n";
+        let ours = "A\n\nOurs\n\nB\n";
+        let theirs = "A\n\nTheirs\n\nB\n";
+
+        let merged = merge_str(base, ours, theirs);
+
+        assert_eq!(merged.conflicts.len(), 1);
+        assert!(merged.conflicts[0].base.is_empty());
+        assert_eq!(merged.conflicts[0].ours[0].markdown(), "Ours");
+        assert_eq!(merged.conflicts[0].theirs[0].markdown(), "Theirs");
+    }
+
+    #[test]
+    fn same_change_on_both_sides() {
+        let base = "# Title\n\nText\n";
+        let ours = "# Title\n\nNew\ntext\n";
+        let theirs = "# Title\n\nNew text\n";
+
+        let merged = merge_str(base, ours, theirs);
+
+        assert!(merged.is_clean());
+        assert_eq!(merged.to_markdown(), "# Title\n\nNew\ntext\n");
+    }
+
+    #[test]
+    fn deletions_and_insertions_at_the_end() {
+        let base = "A\n\nB\n\nC\n";
+        let ours = "A\n\nC\n";
+        let theirs = "A\n\nB\n\nC\n\nD\n";
+
+        let merged = merge_str(base, ours, theirs);
+
+        assert!(merged.is_clean());
+        assert_eq!(merged.to_markdown(), "A\n\nC\n\nD\n");
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{InfoString, Matcher, Provenance, Rewriter, Writer};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag};
+
+/// A [`Rewriter`] which lets you transform the contents of every code block
+/// whose [`Event::Start`] tag is matched by the [`Matcher`].
+///
+/// The block's text (which `pulldown_cmark` may split across several
+/// [`Event::Text`] nodes) is gathered into a single string and passed to the
+/// closure along with the parsed [`InfoString`]. The closure returns the
+/// block's new text, and may modify the [`InfoString`] in place.
+///
+/// Indented code blocks are given an empty [`InfoString`], and will be turned
+/// into fenced code blocks if the closure adds a language or attributes. The
+/// original info string is kept as-is unless the closure modifies it, and
+/// blocks the closure doesn't change at all are passed through untouched.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{CodeBlock, InfoString};
+///
+/// let src = "```json\n{\"name\": \"markedit\", \"version\": \"0.3.0\"}\n```";
+///
+/// let rewriter = markedit::rewrite_code_blocks(
+///     CodeBlock::fenced_with_lang("json"),
+///     |info: &mut InfoString, code: String| {
+///         info.set("title", "package.json");
+///         code.replace(", ", ",\n ")
+///     },
+/// );
+/// let events = markedit::rewrite(markedit::parse(src), rewriter);
+///
+/// assert_eq!(
+///     markedit::to_markdown(events),
+///     "```json title=package.json\n{\"name\": \"markedit\",\n \"version\": \"0.3.0\"}\n```\n",
+/// );
+/// ```
+pub fn rewrite_code_blocks<'src, M, F>(
+    mut matcher: M,
+    mut mutator: F,
+) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+    F: FnMut(&mut InfoString, String) -> String + 'src,
+{
+    // (original info string if fenced, parsed info string, code)
+    let mut current_block: Option<(Option<CowStr<'src>>, InfoString, String)> =
+        None;
+    // the block's original events, in case nothing changes
+    let mut buffered: Vec<(Event<'src>, Provenance)> = Vec::new();
+
+    move |ev: Event<'src>, writer: &mut Writer<'src>| {
+        let is_match = matcher.matches_event(&ev);
+
+        match ev {
+            Event::Start(Tag::CodeBlock(ref kind)) if is_match => {
+                current_block = Some(match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        let parsed = InfoString::parse(info);
+                        (Some(info.clone()), parsed, String::new())
+                    },
+                    CodeBlockKind::Indented => {
+                        (None, InfoString::default(), String::new())
+                    },
+                });
+                buffered = vec![(ev, writer.provenance())];
+            },
+            Event::Text(ref text) if current_block.is_some() => {
+                if let Some((_, _, ref mut code)) = current_block {
+                    code.push_str(text);
+                }
+                buffered.push((ev, writer.provenance()));
+            },
+            Event::End(Tag::CodeBlock(_)) if current_block.is_some() => {
+                buffered.push((ev, writer.provenance()));
+
+                if let Some((original, mut info, code)) = current_block.take() {
+                    let parsed = info.clone();
+                    let new_code = mutator(&mut info, code.clone());
+
+                    let events = std::mem::take(&mut buffered);
+
+                    if info == parsed && new_code == code {
+                        for (event, provenance) in events {
+                            writer.push_spanned(event, provenance);
+                        }
+                    } else {
+                        let info = match original {
+                            Some(original) if info == parsed => Some(original),
+                            None if info == parsed => None,
+                            _ => Some(info.to_string().into()),
+                        };
+                        write_code_block(info, new_code, writer);
+                    }
+                }
+            },
+            other => writer.pass_through(other),
+        }
+    }
+}
+
+/// Write a code block, where `info` is [`None`] for indented code blocks.
+fn write_code_block<'src>(
+    info: Option<CowStr<'src>>,
+    mut code: String,
+    writer: &mut Writer<'src>,
+) {
+    let tag = match info {
+        Some(info) => Tag::CodeBlock(CodeBlockKind::Fenced(info)),
+        None => Tag::CodeBlock(CodeBlockKind::Indented),
+    };
+
+    if !code.is_empty() && !code.ends_with('\n') {
+        code.push('\n');
+    }
+
+    writer.push(Event::Start(tag.clone()));
+    if !code.is_empty() {
+        writer.push(Event::Text(code.into()));
+    }
+    writer.push(Event::End(tag));
+}
+
+/// Remove the lines rustdoc hides from rendered documentation, for use with
+/// [`rewrite_code_blocks()`].
+///
+/// A line is hidden if it is just `#` or starts with `# `, and a leading `##`
+/// is an escaped `#`.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::CodeBlock;
+///
+/// let src = "```rust\n# use std::fmt;\n## not hidden\nfn main() {}\n```";
+///
+/// let rewriter = markedit::rewrite_code_blocks(
+///     CodeBlock::fenced_with_lang("rust"),
+///     markedit::strip_hidden_lines(),
+/// );
+/// let events = markedit::rewrite(markedit::parse(src), rewriter);
+///
+/// assert_eq!(
+///     markedit::to_markdown(events),
+///     "```rust\n# not hidden\nfn main() {}\n```\n",
+/// );
+/// ```
+pub fn strip_hidden_lines() -> impl FnMut(&mut InfoString, String) -> String {
+    |_: &mut InfoString, code: String| {
+        let mut stripped = String::with_capacity(code.len());
+
+        for line in code.lines() {
+            let trimmed = line.trim_start();
+
+            if trimmed == "#" || trimmed.starts_with("# ") {
+                continue;
+            }
+
+            match trimmed.strip_prefix("##") {
+                Some(rest) => {
+                    let indent = &line[..line.len() - trimmed.len()];
+                    stripped.push_str(indent);
+                    stripped.push('#');
+                    stripped.push_str(rest);
+                },
+                None => stripped.push_str(line),
+            }
+            stripped.push('\n');
+        }
+
+        stripped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CodeBlock;
+
+    #[test]
+    fn fragmented_text_is_merged_into_one_event() {
+        let fenced = Tag::CodeBlock(CodeBlockKind::Fenced("text".into()));
+        let events = vec![
+            Event::Start(fenced.clone()),
+            Event::Text("first\n".into()),
+            Event::Text("second\n".into()),
+            Event::End(fenced.clone()),
+        ];
+
+        let rewriter = rewrite_code_blocks(CodeBlock::any(), |_, code| {
+            code.to_uppercase()
+        });
+        let got: Vec<_> = crate::rewrite(events, rewriter).collect();
+
+        assert_eq!(
+            got,
+            vec![
+                Event::Start(fenced.clone()),
+                Event::Text("FIRST\nSECOND\n".into()),
+                Event::End(fenced),
+            ]
+        );
+    }
+
+    #[test]
+    fn fenced_blocks_without_a_language_stay_fenced() {
+        let src = "```\nplain\n```\n";
+
+        let rewriter = rewrite_code_blocks(CodeBlock::any(), |_, code| code);
+        let markdown =
+            crate::to_markdown(crate::rewrite(crate::parse(src), rewriter));
+
+        assert_eq!(markdown, src);
+    }
+
+    #[test]
+    fn adding_a_language_makes_indented_blocks_fenced() {
+        let src = "Some text.\n\n    let x = 42;\n\n```\nuntouched\n```\n";
+
+        let rewriter =
+            rewrite_code_blocks(CodeBlock::indented(), |info, code| {
+                info.language = Some(String::from("rust"));
+                code
+            });
+        let markdown =
+            crate::to_markdown(crate::rewrite(crate::parse(src), rewriter));
+
+        assert_eq!(
+            markdown,
+            "Some text.\n\n```rust\nlet x = 42;\n```\n\n```\nuntouched\n```\n"
+        );
+    }
+
+    #[test]
+    fn untouched_info_strings_are_kept_verbatim() {
+        let src = "```rust,ignore title=x\nfn main() {}\n```\n";
+
+        let rewriter = rewrite_code_blocks(CodeBlock::any(), |_, code| {
+            code.replace("main", "start")
+        });
+        let markdown =
+            crate::to_markdown(crate::rewrite(crate::parse(src), rewriter));
+
+        assert_eq!(markdown, "```rust,ignore title=x\nfn start() {}\n```\n");
+    }
+
+    #[test]
+    fn unchanged_blocks_keep_their_original_text() {
+        let src = "~~~  rust\nfn main() {}\n~~~\n\n    indented\n";
+        let events = pulldown_cmark::Parser::new(src).into_offset_iter();
+
+        let mut rewriter =
+            rewrite_code_blocks(CodeBlock::any(), |_, code| code);
+        let edits = crate::rewrite_to_edits(src, events, &mut rewriter);
+
+        assert_eq!(edits.apply(src), src);
+    }
+}
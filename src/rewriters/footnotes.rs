@@ -0,0 +1,377 @@
+use crate::{Provenance, Rewriter, Writer};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag};
+use std::collections::HashMap;
+
+/// Create a [`RenumberFootnotes`] rewriter.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Rewriter;
+/// use markedit::pulldown_cmark::Options;
+///
+/// let src = "[^b]: Defined first.
+///
+/// Some text[^a] and more text[^b].
+///
+/// [^a]: The first footnote.
+///
+/// [^unused]: Nobody refers to me.
+/// ";
+///
+/// let events = markedit::parse_with_options(src, Options::ENABLE_FOOTNOTES);
+/// let mut rewritten = markedit::renumber_footnotes().rewrite(events);
+/// let markdown = markedit::to_markdown(rewritten.by_ref());
+///
+/// assert_eq!(
+///     markdown,
+///     "Some text[^1] and more text[^2].
+///
+/// [^1]: The first footnote.
+///
+/// [^2]: Defined first.
+/// ",
+/// );
+/// assert_eq!(rewritten.rewriter().unused_definitions(), &["unused"]);
+/// ```
+pub fn renumber_footnotes<'src>() -> RenumberFootnotes<'src> {
+    RenumberFootnotes::default()
+}
+
+/// A [`Rewriter`] which renames footnotes sequentially (`1`, `2`, ...) in the
+/// order they are first referenced, and moves every footnote definition to
+/// the end of the document.
+///
+/// Definitions which are never referenced are removed, and can be inspected
+/// using [`RenumberFootnotes::unused_definitions()`]. Likewise, references to
+/// a footnote which is never defined are available via
+/// [`RenumberFootnotes::dangling_references()`].
+///
+/// Because definitions may appear anywhere in a document, all footnote
+/// definitions are buffered until the end of the [`Event`] stream. Their
+/// contents keep the [`Provenance`] they had when they were buffered.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RenumberFootnotes<'src> {
+    inline: bool,
+    /// Maps the original label to its new label.
+    new_labels: HashMap<String, String>,
+    /// References, in the order they were first seen.
+    references: Vec<String>,
+    definitions: Vec<(String, Vec<(Event<'src>, Provenance)>)>,
+    current_definition: Option<(String, Vec<(Event<'src>, Provenance)>)>,
+    /// Used in "inline" mode, where references may appear before their
+    /// definitions.
+    buffer: Vec<(Event<'src>, Provenance)>,
+    unused_definitions: Vec<String>,
+    dangling_references: Vec<String>,
+}
+
+impl<'src> RenumberFootnotes<'src> {
+    /// Instead of renumbering footnotes, replace each reference with the
+    /// footnote's text in parentheses and remove the definitions.
+    ///
+    /// This is useful for formats which don't support footnotes. Any
+    /// block-level elements in the footnote (e.g. paragraphs or lists) are
+    /// flattened into a single line.
+    ///
+    /// Note that this requires buffering the entire document.
+    ///
+    /// ```rust
+    /// use markedit::pulldown_cmark::Options;
+    ///
+    /// let src = "Some text[^note].\n\n[^note]: A *short* note.";
+    ///
+    /// let events = markedit::parse_with_options(src, Options::ENABLE_FOOTNOTES);
+    /// let rewriter = markedit::renumber_footnotes().inline();
+    /// let markdown = markedit::to_markdown(markedit::rewrite(events, rewriter));
+    ///
+    /// assert_eq!(markdown, "Some text (A *short* note.).\n");
+    /// ```
+    pub fn inline(self) -> Self {
+        RenumberFootnotes {
+            inline: true,
+            ..self
+        }
+    }
+
+    /// The labels for any footnote definitions which were never referenced.
+    ///
+    /// This is only populated after the entire [`Event`] stream has been
+    /// processed.
+    pub fn unused_definitions(&self) -> &[String] { &self.unused_definitions }
+
+    /// The labels for any footnote references which didn't have a
+    /// corresponding definition.
+    ///
+    /// This is only populated after the entire [`Event`] stream has been
+    /// processed.
+    pub fn dangling_references(&self) -> &[String] { &self.dangling_references }
+
+    fn reference(&mut self, label: CowStr<'src>) -> Event<'src> {
+        let next_number = self.new_labels.len() + 1;
+        let new_label = self
+            .new_labels
+            .entry(label.to_string())
+            .or_insert_with(|| next_number.to_string());
+
+        if !self.references.iter().any(|r| r.as_str() == label.as_ref()) {
+            self.references.push(label.to_string());
+        }
+
+        Event::FootnoteReference(new_label.clone().into())
+    }
+
+    fn emit_definitions(&mut self, writer: &mut Writer<'src>) {
+        let mut definitions = std::mem::take(&mut self.definitions);
+
+        for original_label in &self.references {
+            let ix = definitions
+                .iter()
+                .position(|(label, _)| label == original_label);
+
+            match ix {
+                Some(ix) => {
+                    let (_, events) = definitions.remove(ix);
+                    let new_label =
+                        CowStr::from(self.new_labels[original_label].clone());
+                    let tag = Tag::FootnoteDefinition(new_label);
+
+                    writer.push(Event::Start(tag.clone()));
+                    for (event, provenance) in events {
+                        writer.push_spanned(event, provenance);
+                    }
+                    writer.push(Event::End(tag));
+                },
+                None => self.dangling_references.push(original_label.clone()),
+            }
+        }
+
+        self.unused_definitions
+            .extend(definitions.into_iter().map(|(label, _)| label));
+    }
+
+    fn inline_footnotes(&mut self, writer: &mut Writer<'src>) {
+        let definitions = std::mem::take(&mut self.definitions);
+
+        for (event, provenance) in std::mem::take(&mut self.buffer) {
+            let label = match event {
+                Event::FootnoteReference(label) => label,
+                other => {
+                    writer.push_spanned(other, provenance);
+                    continue;
+                },
+            };
+
+            let definition = definitions
+                .iter()
+                .find(|(l, _)| l.as_str() == label.as_ref());
+
+            match definition {
+                Some((_, events)) => {
+                    writer.push(Event::Text(" (".into()));
+                    writer.extend(flatten(events));
+                    writer.push(Event::Text(")".into()));
+
+                    if !self.references.iter().any(|r| r.as_str() == &*label) {
+                        self.references.push(label.to_string());
+                    }
+                },
+                None => {
+                    if !self.dangling_references.iter().any(|r| r == &*label) {
+                        self.dangling_references.push(label.to_string());
+                    }
+                    writer.push_spanned(
+                        Event::FootnoteReference(label),
+                        provenance,
+                    );
+                },
+            }
+        }
+
+        for (label, _) in definitions {
+            if !self.references.contains(&label) {
+                self.unused_definitions.push(label);
+            }
+        }
+    }
+}
+
+impl<'src> Rewriter<'src> for RenumberFootnotes<'src> {
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        // in "inline" mode we deal with references at the end
+        let (event, renamed) = match event {
+            Event::FootnoteReference(label) if !self.inline => {
+                (self.reference(label), true)
+            },
+            other => (other, false),
+        };
+        let provenance = match writer.provenance() {
+            provenance if renamed => Provenance::Synthetic {
+                cause: provenance.cause().cloned(),
+            },
+            provenance => provenance,
+        };
+
+        match event {
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                self.current_definition = Some((label.to_string(), Vec::new()));
+            },
+            Event::End(Tag::FootnoteDefinition(_)) => {
+                if let Some((label, events)) = self.current_definition.take() {
+                    if self.definitions.iter().any(|(l, _)| *l == label) {
+                        // duplicate definitions are ignored
+                        self.unused_definitions.push(label);
+                    } else {
+                        self.definitions.push((label, events));
+                    }
+                }
+            },
+            other => match self.current_definition {
+                Some((_, ref mut events)) => events.push((other, provenance)),
+                None if self.inline => self.buffer.push((other, provenance)),
+                None if renamed => writer.push(other),
+                None => writer.pass_through(other),
+            },
+        }
+    }
+
+    fn finish(&mut self, writer: &mut Writer<'src>) {
+        if self.inline {
+            self.inline_footnotes(writer);
+        } else {
+            self.emit_definitions(writer);
+        }
+    }
+}
+
+/// Flatten the block-level elements in a footnote definition into a sequence
+/// of inline events.
+fn flatten<'src>(events: &[(Event<'src>, Provenance)]) -> Vec<Event<'src>> {
+    let mut flattened = Vec::new();
+
+    for (event, _) in events {
+        match event {
+            Event::Start(tag) | Event::End(tag) if is_block(tag) => {
+                let previous_was_separator = match flattened.last() {
+                    Some(Event::Text(text)) => text.ends_with(' '),
+                    Some(_) => false,
+                    None => true,
+                };
+
+                if !previous_was_separator {
+                    flattened.push(Event::Text(" ".into()));
+                }
+            },
+            Event::SoftBreak | Event::HardBreak => {
+                flattened.push(Event::Text(" ".into()))
+            },
+            Event::Rule | Event::TaskListMarker(_) => {},
+            other => flattened.push(other.clone()),
+        }
+    }
+
+    // get rid of any trailing whitespace
+    if let Some(Event::Text(text)) = flattened.last() {
+        if text.trim().is_empty() {
+            flattened.pop();
+        }
+    }
+
+    flattened
+}
+
+fn is_block(tag: &Tag<'_>) -> bool {
+    match tag {
+        Tag::Paragraph
+        | Tag::Heading(_)
+        | Tag::BlockQuote
+        | Tag::CodeBlock(CodeBlockKind::Indented)
+        | Tag::CodeBlock(CodeBlockKind::Fenced(_))
+        | Tag::List(_)
+        | Tag::Item
+        | Tag::FootnoteDefinition(_)
+        | Tag::Table(_)
+        | Tag::TableHead
+        | Tag::TableRow
+        | Tag::TableCell => true,
+        Tag::Emphasis
+        | Tag::Strong
+        | Tag::Strikethrough
+        | Tag::Link(..)
+        | Tag::Image(..) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Options;
+
+    fn renumber<'src>(
+        src: &'src str,
+        rewriter: RenumberFootnotes<'src>,
+    ) -> (String, RenumberFootnotes<'src>) {
+        let events = crate::parse_with_options(src, Options::ENABLE_FOOTNOTES);
+        let mut rewritten = rewriter.rewrite(events);
+        let markdown = crate::to_markdown(rewritten.by_ref());
+
+        (markdown, rewritten.into_rewriter())
+    }
+
+    #[test]
+    fn references_inside_definitions_are_renumbered() {
+        let src = "A[^x] B[^y] A again[^x]
+
+[^y]: Y refers to [^z].
+
+[^x]: X.
+
+[^z]: Z.
+";
+        let (markdown, rewriter) = renumber(src, renumber_footnotes());
+
+        assert_eq!(
+            markdown,
+            "A[^1] B[^2] A again[^1]
+
+[^1]: X.
+
+[^2]: Y refers to [^3].
+
+[^3]: Z.
+"
+        );
+        assert!(rewriter.unused_definitions().is_empty());
+        assert!(rewriter.dangling_references().is_empty());
+    }
+
+    #[test]
+    fn report_dangling_references() {
+        let src = "A[^missing] B[^x]\n\n[^x]: X.";
+
+        let (markdown, rewriter) = renumber(src, renumber_footnotes());
+
+        assert_eq!(markdown, "A[^1] B[^2]\n\n[^2]: X.\n");
+        assert_eq!(rewriter.dangling_references(), &["missing"]);
+    }
+
+    #[test]
+    fn inline_multi_line_footnotes() {
+        let src = "Text[^long] and[^missing].
+
+[^long]: First line
+continues *here*.
+
+[^unused]: Unused.
+";
+
+        let (markdown, rewriter) = renumber(src, renumber_footnotes().inline());
+
+        assert_eq!(
+            markdown,
+            "Text (First line continues *here*.) and[^missing].\n"
+        );
+        assert_eq!(rewriter.unused_definitions(), &["unused"]);
+        assert_eq!(rewriter.dangling_references(), &["missing"]);
+    }
+}
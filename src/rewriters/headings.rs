@@ -0,0 +1,115 @@
+use crate::{Rewriter, Writer};
+use pulldown_cmark::{Event, Tag};
+
+/// A [`Rewriter`] which moves every heading up or down by `offset` levels.
+///
+/// Levels are clamped so they stay between 1 and 6, which is handy when
+/// embedding one document inside another.
+///
+/// # Examples
+///
+/// ```rust
+/// let src = "# Title\n\n## Section\n\n###### Deepest\n";
+///
+/// let rewritten = markedit::rewrite(markedit::parse(src), markedit::shift_headings(1));
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "## Title\n\n### Section\n\n###### Deepest\n",
+/// );
+/// ```
+pub fn shift_headings<'src>(offset: i32) -> impl Rewriter<'src> + 'src {
+    let shift = move |level: u32| (level as i32 + offset).clamp(1, 6) as u32;
+
+    move |ev: Event<'src>, writer: &mut Writer<'src>| match ev {
+        Event::Start(Tag::Heading(level)) if shift(level) != level => {
+            writer.push(Event::Start(Tag::Heading(shift(level))))
+        },
+        Event::End(Tag::Heading(level)) if shift(level) != level => {
+            writer.push(Event::End(Tag::Heading(shift(level))))
+        },
+        other => writer.pass_through(other),
+    }
+}
+
+/// Create an [`InsertAfterHeading`] rewriter which splices some events in
+/// after every heading with the desired text.
+///
+/// Leading and trailing whitespace is ignored when comparing the heading's
+/// text.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Rewriter;
+///
+/// let src = "# Title\n\n## Usage\n\nSome text.\n";
+/// let snippet = markedit::parse("```console\n$ markedit\n```").collect();
+///
+/// let mut rewritten = markedit::insert_after_heading("Usage", snippet)
+///     .rewrite(markedit::parse(src));
+/// let markdown = markedit::to_markdown(rewritten.by_ref());
+///
+/// assert_eq!(
+///     markdown,
+///     "# Title\n\n## Usage\n\n```console\n$ markedit\n```\n\nSome text.\n",
+/// );
+/// assert_eq!(rewritten.rewriter().inserted(), 1);
+/// ```
+pub fn insert_after_heading<'src, S>(
+    heading: S,
+    to_insert: Vec<Event<'src>>,
+) -> InsertAfterHeading<'src>
+where
+    S: Into<String>,
+{
+    InsertAfterHeading {
+        heading: heading.into(),
+        to_insert,
+        current_heading: None,
+        inserted: 0,
+    }
+}
+
+/// The [`Rewriter`] returned by [`insert_after_heading()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertAfterHeading<'src> {
+    heading: String,
+    to_insert: Vec<Event<'src>>,
+    /// The text of the heading we're currently inside.
+    current_heading: Option<String>,
+    inserted: usize,
+}
+
+impl<'src> InsertAfterHeading<'src> {
+    /// How many times the events were inserted.
+    pub fn inserted(&self) -> usize { self.inserted }
+}
+
+impl<'src> Rewriter<'src> for InsertAfterHeading<'src> {
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        match &event {
+            Event::Start(Tag::Heading(_)) => {
+                self.current_heading = Some(String::new())
+            },
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(ref mut heading) = self.current_heading {
+                    heading.push_str(text);
+                }
+            },
+            Event::End(Tag::Heading(_)) => {
+                let text = self.current_heading.take().unwrap_or_default();
+                writer.pass_through(event);
+
+                if text.trim() == self.heading.trim() {
+                    writer.extend(self.to_insert.iter().cloned());
+                    self.inserted += 1;
+                }
+                return;
+            },
+            _ => {},
+        }
+
+        writer.pass_through(event);
+    }
+}
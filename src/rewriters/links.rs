@@ -0,0 +1,417 @@
+use crate::{urls, Rewriter, Writer};
+use pulldown_cmark::{CowStr, Event, LinkType, Tag};
+
+/// The destination of a link or image, as passed to [`rewrite_links()`] and
+/// [`rewrite_images()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link<'src> {
+    /// How the link was written in the original document.
+    pub link_type: LinkType,
+    /// Where the link points to.
+    pub url: CowStr<'src>,
+    /// The link's title, or an empty string if there is none.
+    pub title: CowStr<'src>,
+}
+
+/// A [`Rewriter`] which lets you update the [`LinkType`], URL and title of
+/// every [`Tag::Link`].
+///
+/// The corresponding [`Event::End`] tag is updated automatically so it always
+/// matches its [`Event::Start`].
+///
+/// # Examples
+///
+/// ```rust
+/// use pulldown_cmark::{Event, LinkType, Tag};
+///
+/// let src = "Check out [the docs](http://example.com/docs).";
+///
+/// let rewriter = markedit::rewrite_links(|link: &mut markedit::Link<'_>| {
+///     link.url = link.url.replace("http://", "https://").into();
+/// });
+/// let events: Vec<_> =
+///     markedit::rewrite(markedit::parse(src), rewriter).collect();
+///
+/// let expected = Tag::Link(
+///     LinkType::Inline,
+///     "https://example.com/docs".into(),
+///     "".into(),
+/// );
+/// assert_eq!(events[2], Event::Start(expected.clone()));
+/// assert_eq!(events[4], Event::End(expected));
+/// ```
+pub fn rewrite_links<'src, F>(mut mutator: F) -> impl Rewriter<'src> + 'src
+where
+    F: FnMut(&mut Link<'src>) + 'src,
+{
+    rewrite_tags(
+        |tag| match tag {
+            Tag::Link(link_type, url, title) => Ok(Link {
+                link_type,
+                url,
+                title,
+            }),
+            other => Err(other),
+        },
+        move |mut link| {
+            mutator(&mut link);
+            Tag::Link(link.link_type, link.url, link.title)
+        },
+    )
+}
+
+/// A [`Rewriter`] which lets you update the [`LinkType`], URL and title of
+/// every [`Tag::Image`].
+///
+/// This is the image equivalent of [`rewrite_links()`].
+///
+/// # Examples
+///
+/// ```rust
+/// use pulldown_cmark::{Event, LinkType, Tag};
+///
+/// let src = "![Logo](images/logo.png)";
+///
+/// let rewriter = markedit::rewrite_images(markedit::rebase_relative("assets"));
+/// let events: Vec<_> =
+///     markedit::rewrite(markedit::parse(src), rewriter).collect();
+///
+/// assert_eq!(
+///     events[1],
+///     Event::Start(Tag::Image(
+///         LinkType::Inline,
+///         "assets/images/logo.png".into(),
+///         "".into()
+///     )),
+/// );
+/// ```
+pub fn rewrite_images<'src, F>(mut mutator: F) -> impl Rewriter<'src> + 'src
+where
+    F: FnMut(&mut Link<'src>) + 'src,
+{
+    rewrite_tags(
+        |tag| match tag {
+            Tag::Image(link_type, url, title) => Ok(Link {
+                link_type,
+                url,
+                title,
+            }),
+            other => Err(other),
+        },
+        move |mut link| {
+            mutator(&mut link);
+            Tag::Image(link.link_type, link.url, link.title)
+        },
+    )
+}
+
+/// A [`Rewriter`] which turns inline links and images (`[text](url)`) into
+/// reference-style links (`[text][text]`).
+///
+/// The link reference definitions are emitted by [`to_markdown()`] at the
+/// end of the document, with links to the same URL sharing a definition.
+///
+/// [`to_markdown()`]: crate::to_markdown
+///
+/// # Examples
+///
+/// ```rust
+/// let src = "Use [markedit](https://crates.io/crates/markedit), a [crate](https://crates.io/crates/markedit) for [Markdown](https://commonmark.org/) ![logo](logo.png).";
+///
+/// let events = markedit::parse(src);
+/// let rewritten = markedit::rewrite(events, markedit::to_reference_links());
+/// let markdown = markedit::to_markdown(rewritten);
+///
+/// assert_eq!(
+///     markdown,
+///     "Use [markedit][markedit], a [crate][markedit] for [Markdown][Markdown] ![logo][logo].
+///
+/// [markedit]: https://crates.io/crates/markedit
+/// [Markdown]: https://commonmark.org/
+/// [logo]: logo.png
+/// ",
+/// );
+/// ```
+pub fn to_reference_links<'src>() -> impl Rewriter<'src> + 'src {
+    change_link_types(|link_type| match link_type {
+        LinkType::Inline => LinkType::Reference,
+        other => other,
+    })
+}
+
+/// A [`Rewriter`] which turns reference-style links and images
+/// (`[text][label]`, `[text][]` and `[text]`) into inline ones
+/// (`[text](url)`).
+///
+/// # Examples
+///
+/// ```rust
+/// let src = "Use [markedit] for [Markdown][cm] ![logo][].
+///
+/// [markedit]: https://crates.io/crates/markedit
+/// [cm]: https://commonmark.org/ \"CommonMark\"
+/// [logo]: logo.png
+/// ";
+///
+/// let events = markedit::parse(src);
+/// let rewritten = markedit::rewrite(events, markedit::to_inline_links());
+/// let markdown = markedit::to_markdown(rewritten);
+///
+/// assert_eq!(
+///     markdown,
+///     "Use [markedit](https://crates.io/crates/markedit) for [Markdown](https://commonmark.org/ \"CommonMark\") ![logo](logo.png).\n",
+/// );
+/// ```
+pub fn to_inline_links<'src>() -> impl Rewriter<'src> + 'src {
+    change_link_types(|link_type| match link_type {
+        LinkType::Reference
+        | LinkType::ReferenceUnknown
+        | LinkType::Collapsed
+        | LinkType::CollapsedUnknown
+        | LinkType::Shortcut
+        | LinkType::ShortcutUnknown => LinkType::Inline,
+        LinkType::Inline | LinkType::Autolink | LinkType::Email => link_type,
+    })
+}
+
+/// Change the [`LinkType`] of every [`Tag::Link`] and [`Tag::Image`].
+fn change_link_types<'src, F>(mut change: F) -> impl Rewriter<'src> + 'src
+where
+    F: FnMut(LinkType) -> LinkType + 'src,
+{
+    rewrite_tags(
+        |tag| match tag {
+            Tag::Link(..) | Tag::Image(..) => Ok(tag),
+            other => Err(other),
+        },
+        move |tag| match tag {
+            Tag::Link(link_type, url, title) => {
+                Tag::Link(change(link_type), url, title)
+            },
+            Tag::Image(link_type, url, title) => {
+                Tag::Image(change(link_type), url, title)
+            },
+            other => other,
+        },
+    )
+}
+
+fn rewrite_tags<'src, T, D, F>(
+    mut destructure: D,
+    mut mutator: F,
+) -> impl Rewriter<'src> + 'src
+where
+    D: FnMut(Tag<'src>) -> Result<T, Tag<'src>> + 'src,
+    F: FnMut(T) -> Tag<'src> + 'src,
+{
+    // images can be nested inside links (and vice versa), so we need to keep
+    // track of every start tag we've rewritten
+    let mut rewritten_tags: Vec<Tag<'src>> = Vec::new();
+
+    move |ev: Event<'src>, writer: &mut Writer<'src>| match ev {
+        Event::Start(tag) => match destructure(tag) {
+            Ok(link) => {
+                let tag = mutator(link);
+                rewritten_tags.push(tag.clone());
+                writer.push(Event::Start(tag));
+            },
+            Err(tag) => writer.pass_through(Event::Start(tag)),
+        },
+        Event::End(tag) => match destructure(tag) {
+            Ok(link) => {
+                let tag = rewritten_tags.pop().unwrap_or_else(|| mutator(link));
+                writer.push(Event::End(tag));
+            },
+            Err(tag) => writer.pass_through(Event::End(tag)),
+        },
+        _ => writer.pass_through(ev),
+    }
+}
+
+/// Prefix every relative URL with a base path, leaving absolute URLs,
+/// fragment-only links (`#section`) and URLs with a scheme untouched.
+///
+/// This is intended to be used with [`rewrite_links()`] or
+/// [`rewrite_images()`].
+///
+/// ```rust
+/// let mut link = markedit::Link {
+///     link_type: pulldown_cmark::LinkType::Inline,
+///     url: "./getting-started.md#installing".into(),
+///     title: "".into(),
+/// };
+///
+/// let mut rebase = markedit::rebase_relative("/docs/");
+/// rebase(&mut link);
+///
+/// assert_eq!(link.url.as_ref(), "/docs/getting-started.md#installing");
+/// ```
+pub fn rebase_relative<'src, S>(base: S) -> impl FnMut(&mut Link<'src>)
+where
+    S: AsRef<str>,
+{
+    move |link: &mut Link<'src>| {
+        if !urls::is_relative(&link.url) {
+            return;
+        }
+
+        let base = base.as_ref().trim_end_matches('/');
+        let url = link.url.trim_start_matches("./");
+        link.url = format!("{}/{}", base, url).into();
+    }
+}
+
+/// Point any absolute URL with a particular domain at another domain instead.
+///
+/// The scheme, port, path, query and fragment are left as-is.
+///
+/// ```rust
+/// let mut link = markedit::Link {
+///     link_type: pulldown_cmark::LinkType::Inline,
+///     url: "https://old.example.com/blog/post?id=42".into(),
+///     title: "".into(),
+/// };
+///
+/// let mut swap = markedit::swap_domain("old.example.com", "example.com");
+/// swap(&mut link);
+///
+/// assert_eq!(link.url.as_ref(), "https://example.com/blog/post?id=42");
+/// ```
+pub fn swap_domain<'src, F, T>(from: F, to: T) -> impl FnMut(&mut Link<'src>)
+where
+    F: AsRef<str>,
+    T: AsRef<str>,
+{
+    move |link: &mut Link<'src>| {
+        let authority_start = match link.url.find("://") {
+            Some(ix) if urls::has_scheme(&link.url) => ix + 3,
+            _ => return,
+        };
+        let after_scheme = &link.url[authority_start..];
+        let authority_len = after_scheme
+            .find(['/', '?', '#'])
+            .unwrap_or(after_scheme.len());
+        let authority = &after_scheme[..authority_len];
+
+        // ignore any user info and port number
+        let host_start = authority.rfind('@').map_or(0, |ix| ix + 1);
+        let host_len = authority[host_start..]
+            .find(':')
+            .unwrap_or_else(|| authority.len() - host_start);
+        let host = &authority[host_start..host_start + host_len];
+
+        if host.eq_ignore_ascii_case(from.as_ref()) {
+            let host_start = authority_start + host_start;
+            let url = format!(
+                "{}{}{}",
+                &link.url[..host_start],
+                to.as_ref(),
+                &link.url[host_start + host_len..]
+            );
+            link.url = url.into();
+        }
+    }
+}
+
+/// Turn relative links to `*.md` files into links to the corresponding
+/// `*.html` file, keeping any query or fragment.
+///
+/// ```rust
+/// let mut link = markedit::Link {
+///     link_type: pulldown_cmark::LinkType::Inline,
+///     url: "../guide/intro.md#overview".into(),
+///     title: "".into(),
+/// };
+///
+/// let mut md_to_html = markedit::md_to_html();
+/// md_to_html(&mut link);
+///
+/// assert_eq!(link.url.as_ref(), "../guide/intro.html#overview");
+/// ```
+pub fn md_to_html<'src>() -> impl FnMut(&mut Link<'src>) {
+    move |link: &mut Link<'src>| {
+        if !urls::is_relative(&link.url) {
+            return;
+        }
+
+        let (path, rest) = urls::split_path(&link.url);
+
+        if let Some(stem) = path.strip_suffix(".md") {
+            let url = format!("{}.html{}", stem, rest);
+            link.url = url.into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str) -> Link<'static> {
+        Link {
+            link_type: LinkType::Inline,
+            url: CowStr::from(url.to_string()),
+            title: "".into(),
+        }
+    }
+
+    #[test]
+    fn end_tags_match_for_images_nested_in_links() {
+        let src = "[![badge](badge.svg)](https://example.com/)";
+        let rewriter = rewrite_images(|img: &mut Link<'_>| {
+            img.url = "new.svg".into();
+            img.title = "Badge".into();
+        });
+        let events: Vec<_> =
+            crate::rewrite(crate::parse(src), rewriter).collect();
+
+        let image =
+            Tag::Image(LinkType::Inline, "new.svg".into(), "Badge".into());
+        let outer_link = Tag::Link(
+            LinkType::Inline,
+            "https://example.com/".into(),
+            "".into(),
+        );
+        assert_eq!(events[1], Event::Start(outer_link.clone()));
+        assert_eq!(events[2], Event::Start(image.clone()));
+        assert_eq!(events[4], Event::End(image));
+        assert_eq!(events[5], Event::End(outer_link));
+    }
+
+    #[test]
+    fn swap_domains_with_ports_and_user_info() {
+        let inputs = vec![
+            ("https://old.com", "https://new.org"),
+            ("http://user@old.com:8080/x", "http://user@new.org:8080/x"),
+            ("https://OLD.com/#frag", "https://new.org/#frag"),
+            ("https://notold.com/", "https://notold.com/"),
+            ("old.com/relative", "old.com/relative"),
+        ];
+
+        for (url, should_be) in inputs {
+            let mut l = link(url);
+            swap_domain("old.com", "new.org")(&mut l);
+            assert_eq!(l.url.as_ref(), should_be);
+        }
+    }
+
+    #[test]
+    fn only_relative_markdown_links_are_converted() {
+        let inputs = vec![
+            ("README.md", "README.html"),
+            ("docs/intro.md?x=1", "docs/intro.html?x=1"),
+            (
+                "https://example.com/README.md",
+                "https://example.com/README.md",
+            ),
+            ("notes.mdx", "notes.mdx"),
+            ("#section.md", "#section.md"),
+        ];
+
+        for (url, should_be) in inputs {
+            let mut l = link(url);
+            md_to_html()(&mut l);
+            assert_eq!(l.url.as_ref(), should_be);
+        }
+    }
+}
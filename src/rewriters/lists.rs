@@ -0,0 +1,409 @@
+use crate::{Matcher, Rewriter, Writer};
+use pulldown_cmark::{Event, Tag};
+use std::{cmp::Ordering, collections::HashSet};
+
+/// The events for a single list item, including its start and end tags and
+/// any nested lists.
+pub(super) type Item<'src> = Vec<Event<'src>>;
+
+/// A [`Rewriter`] which sorts the items in every list whose [`Event::Start`]
+/// tag is matched by the [`Matcher`], using each item's plain text.
+///
+/// Only the item's own text is compared, and any nested lists move with
+/// their parent item.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Always;
+///
+/// let src = "- Windows\n- Linux\n  - Ubuntu\n  - Arch\n- *macOS*\n";
+///
+/// let events = markedit::parse(src);
+/// let rewritten = markedit::rewrite(events, markedit::sort_lists(Always));
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "- Linux\n  - Arch\n  - Ubuntu\n- Windows\n- *macOS*\n",
+/// );
+/// ```
+pub fn sort_lists<'src, M>(matcher: M) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+{
+    sort_lists_by(matcher, |left: &str, right: &str| left.cmp(right))
+}
+
+/// A [`Rewriter`] which sorts the items in every matched list using a custom
+/// comparison function.
+///
+/// This is the same as [`sort_lists()`], except you get to decide how two
+/// items' text should be compared.
+///
+/// ```rust
+/// use markedit::Always;
+///
+/// let src = "- banana\n- Cherry\n- apple\n";
+///
+/// let rewriter = markedit::sort_lists_by(Always, |left: &str, right: &str| {
+///     left.to_lowercase().cmp(&right.to_lowercase())
+/// });
+/// let rewritten = markedit::rewrite(markedit::parse(src), rewriter);
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "- apple\n- banana\n- Cherry\n",
+/// );
+/// ```
+pub fn sort_lists_by<'src, M, F>(
+    matcher: M,
+    mut compare: F,
+) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+    F: FnMut(&str, &str) -> Ordering + 'src,
+{
+    rewrite_lists(matcher, move |_, items| {
+        let mut keyed: Vec<_> = items
+            .drain(..)
+            .map(|item| (item_text(&item), item))
+            .collect();
+        keyed.sort_by(|(left, _), (right, _)| compare(left, right));
+        items.extend(keyed.into_iter().map(|(_, item)| item));
+    })
+}
+
+/// A [`Rewriter`] which removes any items from a matched list if an earlier
+/// item has the same plain text.
+///
+/// Removed items are dropped along with any nested lists they contain.
+///
+/// ```rust
+/// use markedit::Always;
+///
+/// let src = "- Alice\n- Bob\n- **Alice**\n- Carol\n";
+///
+/// let events = markedit::parse(src);
+/// let rewritten = markedit::rewrite(events, markedit::dedup_lists(Always));
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "- Alice\n- Bob\n- Carol\n",
+/// );
+/// ```
+pub fn dedup_lists<'src, M>(matcher: M) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+{
+    rewrite_lists(matcher, |_, items| {
+        let mut seen = HashSet::new();
+        items.retain(|item| seen.insert(item_text(item)));
+    })
+}
+
+/// A [`Rewriter`] which renumbers every matched ordered list so it starts
+/// from `start`.
+///
+/// Unordered lists are left alone.
+///
+/// ```rust
+/// use markedit::Always;
+///
+/// let src = "3. Three\n7. Seven\n\n- unordered\n";
+///
+/// let events = markedit::parse(src);
+/// let rewritten = markedit::rewrite(events, markedit::renumber_lists(Always, 1));
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "1. Three\n2. Seven\n\n- unordered\n",
+/// );
+/// ```
+pub fn renumber_lists<'src, M>(
+    matcher: M,
+    start: u64,
+) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+{
+    rewrite_lists(matcher, move |kind, _| {
+        if kind.is_some() {
+            *kind = Some(start);
+        }
+    })
+}
+
+/// A [`Rewriter`] which turns every matched list into an ordered list
+/// starting from `start`.
+///
+/// ```rust
+/// use markedit::Always;
+///
+/// let src = "- First\n- Second\n";
+///
+/// let events = markedit::parse(src);
+/// let rewritten =
+///     markedit::rewrite(events, markedit::to_ordered_lists(Always, 1));
+///
+/// assert_eq!(markedit::to_markdown(rewritten), "1. First\n2. Second\n");
+/// ```
+pub fn to_ordered_lists<'src, M>(
+    matcher: M,
+    start: u64,
+) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+{
+    rewrite_lists(matcher, move |kind, _| {
+        if kind.is_none() {
+            *kind = Some(start);
+        }
+    })
+}
+
+/// A [`Rewriter`] which turns every matched list into an unordered list.
+///
+/// ```rust
+/// use markedit::Always;
+///
+/// let src = "1. First\n2. Second\n";
+///
+/// let events = markedit::parse(src);
+/// let rewritten =
+///     markedit::rewrite(events, markedit::to_unordered_lists(Always));
+///
+/// assert_eq!(markedit::to_markdown(rewritten), "- First\n- Second\n");
+/// ```
+pub fn to_unordered_lists<'src, M>(matcher: M) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+{
+    rewrite_lists(matcher, |kind, _| *kind = None)
+}
+
+/// Buffer each top-level list, then call `f` with the kind and items of
+/// every list (including nested ones) whose start tag was matched.
+///
+/// Nested lists are processed before their parents, and a list is removed
+/// entirely if `f` leaves it without any items.
+pub(super) fn rewrite_lists<'src, M, F>(
+    matcher: M,
+    mut f: F,
+) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+    F: FnMut(&mut Option<u64>, &mut Vec<Item<'src>>) + 'src,
+{
+    buffer_lists(matcher, move |events, matched| {
+        let mut events = events.into_iter().enumerate();
+        let mut rewritten = Vec::new();
+
+        while let Some((ix, event)) = events.next() {
+            match event {
+                Event::Start(Tag::List(kind)) => rewritten.extend(map_list(
+                    ix,
+                    kind,
+                    &mut events,
+                    matched,
+                    &mut f,
+                )),
+                other => rewritten.push(other),
+            }
+        }
+
+        rewritten
+    })
+}
+
+/// Buffer each top-level list and let `process` rewrite it, given the indices
+/// of any [`Event`]s which were matched.
+///
+/// Lists which `process` leaves alone keep their original
+/// [`Provenance`][crate::Provenance].
+pub(super) fn buffer_lists<'src, M, F>(
+    mut matcher: M,
+    mut process: F,
+) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+    F: FnMut(Vec<Event<'src>>, &HashSet<usize>) -> Vec<Event<'src>> + 'src,
+{
+    let mut buffer = Vec::new();
+    let mut matched = HashSet::new();
+    let mut depth = 0_usize;
+
+    move |ev: Event<'src>, writer: &mut Writer<'src>| {
+        let is_match = matcher.matches_event(&ev);
+
+        match ev {
+            Event::Start(Tag::List(_)) => depth += 1,
+            Event::End(Tag::List(_)) => depth = depth.saturating_sub(1),
+            _ if depth == 0 => {
+                writer.pass_through(ev);
+                return;
+            },
+            _ => {},
+        }
+
+        if is_match {
+            matched.insert(buffer.len());
+        }
+        buffer.push((ev, writer.provenance()));
+
+        if depth == 0 {
+            let original = std::mem::take(&mut buffer);
+            let events = original.iter().map(|(ev, _)| ev.clone()).collect();
+            let processed = process(events, &matched);
+            matched.clear();
+
+            if original.len() == processed.len()
+                && original.iter().zip(&processed).all(|((a, _), b)| a == b)
+            {
+                // nothing changed, so keep the original spans
+                for (event, provenance) in original {
+                    writer.push_spanned(event, provenance);
+                }
+            } else {
+                writer.extend(processed);
+            }
+        }
+    }
+}
+
+/// Read the rest of a list (after its start tag) and apply `f` to it if it
+/// was matched.
+fn map_list<'src, I>(
+    start_index: usize,
+    mut kind: Option<u64>,
+    events: &mut I,
+    matched: &HashSet<usize>,
+    f: &mut dyn FnMut(&mut Option<u64>, &mut Vec<Item<'src>>),
+) -> Vec<Event<'src>>
+where
+    I: Iterator<Item = (usize, Event<'src>)>,
+{
+    let mut items = Vec::new();
+    let mut current_item = Vec::new();
+
+    while let Some((ix, event)) = events.next() {
+        match event {
+            Event::Start(Tag::List(nested_kind)) => current_item
+                .extend(map_list(ix, nested_kind, events, matched, &mut *f)),
+            Event::End(Tag::Item) => {
+                current_item.push(event);
+                items.push(std::mem::take(&mut current_item));
+            },
+            Event::End(Tag::List(_)) => break,
+            other => current_item.push(other),
+        }
+    }
+
+    if matched.contains(&start_index) {
+        f(&mut kind, &mut items);
+    }
+
+    if items.is_empty() {
+        return Vec::new();
+    }
+
+    let mut list = vec![Event::Start(Tag::List(kind))];
+    list.extend(items.into_iter().flatten());
+    list.push(Event::End(Tag::List(kind)));
+    list
+}
+
+/// Get an item's plain text, ignoring any nested lists.
+pub(super) fn item_text(item: &[Event<'_>]) -> String {
+    let mut text = String::new();
+    let mut depth = 0_usize;
+
+    for event in item {
+        match event {
+            Event::Start(Tag::List(_)) => depth += 1,
+            Event::End(Tag::List(_)) => depth = depth.saturating_sub(1),
+            Event::Text(t) | Event::Code(t) if depth == 0 => text.push_str(t),
+            Event::SoftBreak | Event::HardBreak if depth == 0 => text.push(' '),
+            _ => {},
+        }
+    }
+
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_lists_after_the_matched_heading_are_sorted() {
+        let src = "# Contributors
+
+- Zoe
+- Adam
+  - b
+  - a
+
+# Changes
+
+- second
+- first
+";
+        let mut contributors = false;
+        let matcher = move |ev: &Event<'_>| {
+            match ev {
+                Event::Text(text) if text.as_ref() == "Contributors" => {
+                    contributors = true
+                },
+                Event::Text(text) if text.as_ref() == "Changes" => {
+                    contributors = false
+                },
+                _ => {},
+            }
+            contributors
+        };
+
+        let events = crate::parse(src);
+        let markdown =
+            crate::to_markdown(crate::rewrite(events, sort_lists(matcher)));
+
+        assert_eq!(
+            markdown,
+            "# Contributors
+
+- Adam
+  - a
+  - b
+- Zoe
+
+# Changes
+
+- second
+- first
+"
+        );
+    }
+
+    #[test]
+    fn dedup_compares_the_items_own_text() {
+        let src = "- a\n  - nested\n- a\n- b\n  - nested\n";
+
+        let events = crate::parse(src);
+        let markdown = crate::to_markdown(crate::rewrite(
+            events,
+            dedup_lists(crate::Always),
+        ));
+
+        assert_eq!(markdown, "- a\n  - nested\n- b\n  - nested\n");
+    }
+
+    #[test]
+    fn unmatched_lists_keep_their_original_text() {
+        let src = "* one\n* two\n";
+        let events = pulldown_cmark::Parser::new(src).into_offset_iter();
+
+        let mut rewriter = sort_lists(crate::exact_text("nope"));
+        let edits = crate::rewrite_to_edits(src, events, &mut rewriter);
+
+        assert_eq!(edits.apply(src), src);
+    }
+}
@@ -1,7 +1,46 @@
+mod code_blocks;
+mod footnotes;
+mod headings;
+mod links;
+mod lists;
+mod pipeline;
+mod provenance;
+mod regions;
 mod rewritten;
+mod substitute;
+mod sync_code_blocks;
+mod tables;
+mod tasks;
+mod to_edits;
 mod writer;
 
-pub use rewritten::{rewrite, Rewritten};
+pub use code_blocks::{rewrite_code_blocks, strip_hidden_lines};
+pub use footnotes::{renumber_footnotes, RenumberFootnotes};
+pub use headings::{
+    insert_after_heading, shift_headings, InsertAfterHeading,
+};
+pub use links::{
+    md_to_html, rebase_relative, rewrite_images, rewrite_links, swap_domain,
+    to_inline_links, to_reference_links, Link,
+};
+pub use lists::{
+    dedup_lists, renumber_lists, sort_lists, sort_lists_by, to_ordered_lists,
+    to_unordered_lists,
+};
+pub use pipeline::Pipeline;
+pub use provenance::{Provenance, SpannedEvent};
+pub use regions::{replace_region, ReplaceRegion};
+pub use rewritten::{rewrite, rewrite_with_offsets, Rewritten};
+pub use substitute::{substitute, Substitute, UnknownVariable};
+pub use sync_code_blocks::{
+    sync_code_blocks, DriftedBlock, SyncCodeBlocks, SyncError,
+};
+pub use tables::rewrite_tables;
+pub use tasks::{
+    check_tasks, move_completed_tasks_to_bottom, remove_completed_tasks,
+    uncheck_tasks,
+};
+pub use to_edits::rewrite_to_edits;
 pub use writer::Writer;
 
 use crate::Matcher;
@@ -18,11 +57,19 @@ pub trait Rewriter<'src> {
     /// to the user via [`rewrite()`].
     fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>);
 
-    /// Use this [`Rewriter`] to rewrite a stream of [`Event`]s.
+    /// Called once after the last [`Event`] has been processed, giving the
+    /// [`Rewriter`] a chance to flush anything it may have buffered.
+    ///
+    /// The default implementation does nothing.
+    fn finish(&mut self, _writer: &mut Writer<'src>) {}
+
+    /// Use this [`Rewriter`] to rewrite a stream of [`Event`]s, optionally
+    /// with their spans (see [`SpannedEvent`]).
     fn rewrite<E>(self, events: E) -> Rewritten<'src, E, Self>
     where
         Self: Sized,
-        E: IntoIterator<Item = Event<'src>>,
+        E: IntoIterator,
+        E::Item: SpannedEvent<'src>,
     {
         Rewritten::new(events, self)
     }
@@ -84,7 +131,7 @@ where
         if matcher.matches_event(&ev) {
             writer.extend(to_insert.iter().cloned());
         }
-        writer.push(ev);
+        writer.pass_through(ev);
     }
 }
 
@@ -100,19 +147,55 @@ where
     S: Into<CowStr<'src>>,
 {
     move |ev: Event<'src>, writer: &mut Writer<'src>| match ev {
-        Event::Text(text) => {
-            let text = if predicate(text.as_ref()) {
-                mutator(text).into()
-            } else {
-                text
-            };
-            writer.push(Event::Text(text));
+        Event::Text(text) if predicate(text.as_ref()) => {
+            writer.push(Event::Text(mutator(text).into()))
         },
-        _ => writer.push(ev),
+        _ => writer.pass_through(ev),
+    }
+}
+
+/// A [`Rewriter`] which updates any [`Event::Text`] nodes matched by a
+/// [`Matcher`].
+///
+/// Unlike [`change_text()`], the [`Matcher`] sees every [`Event`], so it can
+/// use context like "inside a heading" or "in the *Status* column of a
+/// table".
+///
+/// ```rust
+/// use markedit::Heading;
+///
+/// let src = "# Title\n\nTitle";
+///
+/// let rewriter = markedit::change_matched_text(
+///     Heading::any_level(),
+///     |text| text.to_uppercase(),
+/// );
+/// let events = markedit::rewrite(markedit::parse(src), rewriter);
+///
+/// assert_eq!(markedit::to_markdown(events), "# TITLE\n\nTitle\n");
+/// ```
+pub fn change_matched_text<'src, M, F, S>(
+    mut matcher: M,
+    mut mutator: F,
+) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+    F: FnMut(CowStr<'src>) -> S + 'src,
+    S: Into<CowStr<'src>>,
+{
+    move |ev: Event<'src>, writer: &mut Writer<'src>| {
+        let is_match = matcher.matches_event(&ev);
+
+        match ev {
+            Event::Text(text) if is_match => {
+                writer.push(Event::Text(mutator(text).into()))
+            },
+            _ => writer.pass_through(ev),
+        }
     }
 }
 
-fn owned_event(ev: Event<'_>) -> Event<'static> {
+pub(crate) fn owned_event(ev: Event<'_>) -> Event<'static> {
     match ev {
         Event::Start(tag) => Event::Start(owned_tag(tag)),
         Event::End(tag) => Event::End(owned_tag(tag)),
@@ -0,0 +1,142 @@
+use crate::{Provenance, Rewriter, Writer};
+use pulldown_cmark::Event;
+use std::fmt::{self, Debug, Formatter};
+
+/// A [`Rewriter`] which feeds events through several other [`Rewriter`]s, one
+/// after another.
+///
+/// Each stage sees the output of the previous stage, as if you had called
+/// [`rewrite()`][crate::rewrite] several times.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Pipeline;
+///
+/// let src = "# Title\n\nSome text.\n";
+///
+/// let pipeline = Pipeline::new()
+///     .with(markedit::shift_headings(1))
+///     .with(markedit::change_text(|_| true, |text| text.to_uppercase()));
+/// let rewritten = markedit::rewrite(markedit::parse(src), pipeline);
+///
+/// assert_eq!(markedit::to_markdown(rewritten), "## TITLE\n\nSOME TEXT.\n");
+/// ```
+#[derive(Default)]
+pub struct Pipeline<'src> {
+    stages: Vec<Box<dyn Rewriter<'src> + 'src>>,
+}
+
+impl<'src> Pipeline<'src> {
+    /// Create an empty [`Pipeline`], which passes events through unchanged.
+    pub fn new() -> Self { Pipeline { stages: Vec::new() } }
+
+    /// Add a [`Rewriter`] to the end of the pipeline.
+    pub fn push<R>(&mut self, rewriter: R)
+    where
+        R: Rewriter<'src> + 'src,
+    {
+        self.stages.push(Box::new(rewriter));
+    }
+
+    /// Add a [`Rewriter`] to the end of the pipeline, builder-style.
+    pub fn with<R>(mut self, rewriter: R) -> Self
+    where
+        R: Rewriter<'src> + 'src,
+    {
+        self.push(rewriter);
+        self
+    }
+
+    /// The number of stages in the pipeline.
+    pub fn len(&self) -> usize { self.stages.len() }
+
+    /// Does this pipeline have any stages?
+    pub fn is_empty(&self) -> bool { self.stages.is_empty() }
+
+    /// Send some events through every stage from `stage` onwards.
+    fn process<I>(&mut self, stage: usize, events: I, writer: &mut Writer<'src>)
+    where
+        I: IntoIterator<Item = (Event<'src>, Provenance)>,
+    {
+        let rewriter = match self.stages.get_mut(stage) {
+            Some(rewriter) => rewriter,
+            None => {
+                writer.buffer.extend(events);
+                return;
+            },
+        };
+
+        let mut output = Writer::new();
+        for (event, provenance) in events {
+            output.set_current(Some(provenance));
+            rewriter.rewrite_event(event, &mut output);
+        }
+
+        self.process(stage + 1, output.buffer, writer);
+    }
+}
+
+impl<'src> Rewriter<'src> for Pipeline<'src> {
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        let provenance = writer.provenance();
+        self.process(0, Some((event, provenance)), writer);
+    }
+
+    fn finish(&mut self, writer: &mut Writer<'src>) {
+        // flush each stage in turn, making sure anything it had buffered
+        // still goes through the later stages
+        for stage in 0..self.stages.len() {
+            let mut output = Writer::new();
+            self.stages[stage].finish(&mut output);
+            self.process(stage + 1, output.buffer, writer);
+        }
+    }
+}
+
+impl<'src> Debug for Pipeline<'src> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("stages", &self.stages.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffered_events_go_through_later_stages() {
+        let src = "<!-- begin x -->\n\nunterminated\n";
+
+        // replace_region() holds onto everything after the begin marker
+        // until finish() is called
+        let pipeline = Pipeline::new()
+            .with(crate::replace_region("x", "ignored"))
+            .with(crate::change_text(|_| true, |text| text.to_uppercase()));
+        let got =
+            crate::to_markdown(crate::rewrite(crate::parse(src), pipeline));
+
+        assert_eq!(got, "<!-- begin x -->\n\nUNTERMINATED\n");
+    }
+
+    #[test]
+    fn spans_survive_every_stage() {
+        let src = "# Title\n\nText\n";
+        let pipeline = Pipeline::new()
+            .with(crate::shift_headings(0))
+            .with(crate::change_text(|_| true, |text| text.to_uppercase()));
+
+        let got: Vec<_> = crate::rewrite_with_offsets(
+            pulldown_cmark::Parser::new(src).into_offset_iter(),
+            pipeline,
+        )
+        .map(|(_, provenance)| provenance)
+        .collect();
+
+        assert_eq!(got[0], Provenance::Original(0..8));
+        assert_eq!(got[1], Provenance::Synthetic { cause: Some(2..7) });
+        assert_eq!(got[3], Provenance::Original(9..14));
+    }
+}
@@ -0,0 +1,82 @@
+use pulldown_cmark::Event;
+use std::ops::Range;
+
+/// Where an [`Event`] emitted by a [`Rewriter`][crate::Rewriter] came from.
+///
+/// Events the rewriter passes through untouched (using
+/// [`Writer::pass_through()`][crate::Writer::pass_through]) keep the span
+/// they had in the input, while anything it creates or modifies is
+/// synthetic. A synthetic event remembers the input event being rewritten
+/// when it was pushed to the [`Writer`][crate::Writer] (e.g. the event
+/// matched by [`insert_markdown_before()`][crate::insert_markdown_before]).
+///
+/// Rewriters which hold onto events and emit them later (e.g.
+/// [`renumber_footnotes()`][crate::renumber_footnotes]) record each event's
+/// provenance as it is buffered. Events created while a rewriter is
+/// finishing have no cause.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Provenance {
+    /// The event was passed through from this range of the input.
+    Original(Range<usize>),
+    /// The event was created by a [`Rewriter`][crate::Rewriter].
+    Synthetic {
+        /// The span of the input event which caused it, if known.
+        cause: Option<Range<usize>>,
+    },
+    /// The event was passed through, but the input didn't say where it came
+    /// from.
+    Unknown,
+}
+
+impl Provenance {
+    /// The range of the input this event was copied from, if it was passed
+    /// through unchanged.
+    pub fn span(&self) -> Option<&Range<usize>> {
+        match self {
+            Provenance::Original(span) => Some(span),
+            _ => None,
+        }
+    }
+
+    /// Was this event created by a [`Rewriter`][crate::Rewriter]?
+    pub fn is_synthetic(&self) -> bool {
+        matches!(self, Provenance::Synthetic { .. })
+    }
+
+    /// The range of the input responsible for this event, either because it
+    /// was copied from there or because that's what caused it to be created.
+    pub fn cause(&self) -> Option<&Range<usize>> {
+        match self {
+            Provenance::Original(span) => Some(span),
+            Provenance::Synthetic { cause } => cause.as_ref(),
+            Provenance::Unknown => None,
+        }
+    }
+}
+
+/// Something which can be fed to a [`Rewriter`][crate::Rewriter], either a
+/// bare [`Event`], an [`Event`] and the range of source text it came from
+/// (e.g. from
+/// [`Parser::into_offset_iter()`][pulldown_cmark::Parser::into_offset_iter]),
+/// or an [`Event`] and its [`Provenance`] (e.g. from
+/// [`rewrite_with_offsets()`][crate::rewrite_with_offsets]).
+pub trait SpannedEvent<'src> {
+    /// Split this into the [`Event`] and where it came from.
+    fn into_parts(self) -> (Event<'src>, Provenance);
+}
+
+impl<'src> SpannedEvent<'src> for Event<'src> {
+    fn into_parts(self) -> (Event<'src>, Provenance) {
+        (self, Provenance::Unknown)
+    }
+}
+
+impl<'src> SpannedEvent<'src> for (Event<'src>, Range<usize>) {
+    fn into_parts(self) -> (Event<'src>, Provenance) {
+        (self.0, Provenance::Original(self.1))
+    }
+}
+
+impl<'src> SpannedEvent<'src> for (Event<'src>, Provenance) {
+    fn into_parts(self) -> (Event<'src>, Provenance) { self }
+}
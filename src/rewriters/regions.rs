@@ -0,0 +1,109 @@
+use crate::{Provenance, Rewriter, Writer};
+use pulldown_cmark::Event;
+use std::mem;
+
+/// Replace everything between a `<!-- begin name -->` and `<!-- end name -->`
+/// comment with some Markdown, leaving the comments in place so the region
+/// can be updated again later.
+///
+/// If a region is never closed, its contents are left untouched.
+///
+/// # Examples
+///
+/// ```rust
+/// let src = "Intro\n\n<!-- begin usage -->\nOld text.\n<!-- end usage -->\n";
+///
+/// let rewriter = markedit::replace_region("usage", "New *text*.");
+/// let rewritten = markedit::rewrite(markedit::parse(src), rewriter);
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "Intro\n\n<!-- begin usage -->\n\nNew *text*.\n\n<!-- end usage -->\n",
+/// );
+/// ```
+pub fn replace_region<'src, S>(name: S, markdown: &str) -> ReplaceRegion<'src>
+where
+    S: Into<String>,
+{
+    ReplaceRegion {
+        name: name.into(),
+        replacement: crate::parse(markdown).map(super::owned_event).collect(),
+        skipped: None,
+        replaced: 0,
+    }
+}
+
+/// The [`Rewriter`] returned by [`replace_region()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaceRegion<'src> {
+    name: String,
+    replacement: Vec<Event<'src>>,
+    /// The original contents of the region we're currently inside, and
+    /// where they came from.
+    skipped: Option<Vec<(Event<'src>, Provenance)>>,
+    replaced: usize,
+}
+
+impl<'src> ReplaceRegion<'src> {
+    /// How many regions were replaced.
+    pub fn replaced(&self) -> usize { self.replaced }
+
+    fn is_marker(&self, event: &Event<'_>, keyword: &str) -> bool {
+        let html = match event {
+            Event::Html(html) => html.trim(),
+            _ => return false,
+        };
+
+        let words = html
+            .strip_prefix("<!--")
+            .and_then(|rest| rest.strip_suffix("-->"))
+            .map(|comment| comment.split_whitespace().collect::<Vec<_>>());
+
+        matches!(words.as_deref(), Some([kw, name]) if *kw == keyword && *name == self.name)
+    }
+}
+
+impl<'src> Rewriter<'src> for ReplaceRegion<'src> {
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        if self.skipped.is_some() {
+            if self.is_marker(&event, "end") {
+                self.skipped = None;
+                self.replaced += 1;
+                writer.extend(self.replacement.iter().cloned());
+                writer.pass_through(event);
+            } else if let Some(ref mut skipped) = self.skipped {
+                skipped.push((event, writer.provenance()));
+            }
+        } else {
+            if self.is_marker(&event, "begin") {
+                self.skipped = Some(Vec::new());
+            }
+            writer.pass_through(event);
+        }
+    }
+
+    fn finish(&mut self, writer: &mut Writer<'src>) {
+        if let Some(skipped) = mem::take(&mut self.skipped) {
+            for (event, provenance) in skipped {
+                writer.push_spanned(event, provenance);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unterminated_regions_are_left_alone() {
+        let src = "<!-- begin toc -->\n\n- old\n\n<!-- end other -->\n\nText\n";
+
+        let mut rewritten =
+            replace_region("toc", "- new").rewrite(crate::parse(src));
+        let events: Vec<_> = rewritten.by_ref().collect();
+
+        assert_eq!(events, crate::parse(src).collect::<Vec<_>>());
+        assert_eq!(rewritten.rewriter().replaced(), 0);
+    }
+}
@@ -1,5 +1,6 @@
-use crate::{Rewriter, Writer};
+use crate::{Provenance, Rewriter, SpannedEvent, Writer};
 use pulldown_cmark::Event;
+use std::ops::Range;
 
 /// The whole point.
 ///
@@ -17,12 +18,56 @@ where
     Rewritten::new(events.into_iter(), rewriter)
 }
 
+/// Rewrite a stream of [`Event`]s and their locations in the source text,
+/// keeping track of where each output [`Event`] came from.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{Matcher, Provenance, pulldown_cmark::{Event, Parser}};
+///
+/// let src = "# Heading\n\nSome text.\n";
+/// let rewriter = markedit::insert_markdown_before(
+///     "Inserted",
+///     markedit::exact_text("Some text."),
+/// );
+///
+/// let events = Parser::new(src).into_offset_iter();
+/// let rewritten: Vec<_> = markedit::rewrite_with_offsets(events, rewriter).collect();
+///
+/// // the heading was passed through
+/// assert_eq!(rewritten[0].1, Provenance::Original(0..10));
+/// assert_eq!(rewritten[1].1, Provenance::Original(2..9));
+/// // but "Inserted" was caused by the "Some text." event
+/// assert_eq!(rewritten[5].0, Event::Text("Inserted".into()));
+/// assert_eq!(rewritten[5].1, Provenance::Synthetic { cause: Some(11..21) });
+/// assert_eq!(&src[11..21], "Some text.");
+/// ```
+pub fn rewrite_with_offsets<'src, E, R>(
+    events: E,
+    rewriter: R,
+) -> impl Iterator<Item = (Event<'src>, Provenance)> + 'src
+where
+    E: IntoIterator<Item = (Event<'src>, Range<usize>)>,
+    E::IntoIter: 'src,
+    R: Rewriter<'src> + 'src,
+{
+    let mut rewritten = Rewritten::new(events.into_iter(), rewriter);
+    std::iter::from_fn(move || rewritten.next_with_provenance())
+}
+
 /// A stream of [`Event`]s that have been modified by a [`Rewriter`].
+///
+/// The input may either be plain [`Event`]s, or [`Event`]s and their spans
+/// (see [`SpannedEvent`]). In the latter case,
+/// [`Rewritten::with_provenance()`] can tell you where each output
+/// [`Event`] came from.
 #[derive(Debug)]
 pub struct Rewritten<'src, E, R> {
     events: E,
     rewriter: R,
     writer: Writer<'src>,
+    finished: bool,
 }
 
 impl<'src, E, R> Rewritten<'src, E, R> {
@@ -32,36 +77,80 @@ impl<'src, E, R> Rewritten<'src, E, R> {
             rewriter,
             events,
             writer: Writer::new(),
+            finished: false,
         }
     }
+
+    /// Get a reference to the [`Rewriter`] being used.
+    ///
+    /// This is useful when the [`Rewriter`] keeps track of extra information
+    /// (e.g. errors) while it processes events.
+    pub fn rewriter(&self) -> &R { &self.rewriter }
+
+    /// Consume the [`Rewritten`] stream, returning the [`Rewriter`].
+    pub fn into_rewriter(self) -> R { self.rewriter }
 }
 
-impl<'src, E, R> Iterator for Rewritten<'src, E, R>
+impl<'src, E, R> Rewritten<'src, E, R>
 where
-    E: Iterator<Item = Event<'src>>,
+    E: Iterator,
+    E::Item: SpannedEvent<'src>,
     R: Rewriter<'src>,
 {
-    type Item = Event<'src>;
+    /// Iterate over the rewritten [`Event`]s and their [`Provenance`].
+    pub fn with_provenance(
+        &mut self,
+    ) -> impl Iterator<Item = (Event<'src>, Provenance)> + '_ {
+        std::iter::from_fn(move || self.next_with_provenance())
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_with_provenance(&mut self) -> Option<(Event<'src>, Provenance)> {
         loop {
             // we're still working through items buffered by the rewriter
-            if let Some(ev) = self.writer.buffer.pop_front() {
-                return Some(ev);
+            if let Some(item) = self.writer.buffer.pop_front() {
+                return Some(item);
+            }
+
+            if self.finished {
+                return None;
             }
 
             // we need to pop another event and process it
-            let event = self.events.next()?;
-            self.rewriter.rewrite_event(event, &mut self.writer);
+            match self.events.next() {
+                Some(item) => {
+                    let (event, provenance) = item.into_parts();
+                    self.writer.set_current(Some(provenance));
+                    self.rewriter.rewrite_event(event, &mut self.writer);
+                },
+                None => {
+                    // give the rewriter a chance to flush its buffers
+                    self.finished = true;
+                    self.writer.set_current(None);
+                    self.rewriter.finish(&mut self.writer);
+                },
+            }
         }
     }
 }
 
+impl<'src, E, R> Iterator for Rewritten<'src, E, R>
+where
+    E: Iterator,
+    E::Item: SpannedEvent<'src>,
+    R: Rewriter<'src>,
+{
+    type Item = Event<'src>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_provenance().map(|(event, _)| event)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use pulldown_cmark::Tag;
+    use pulldown_cmark::{Parser, Tag};
 
     #[test]
     fn ignore_some_events() {
@@ -90,4 +179,111 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn modified_events_are_synthetic() {
+        let src = "# Heading\n";
+        let mut rewritten = crate::shift_headings(1)
+            .rewrite(Parser::new(src).into_offset_iter());
+
+        let got: Vec<_> = rewritten.with_provenance().collect();
+
+        assert_eq!(
+            got,
+            vec![
+                (
+                    Event::Start(Tag::Heading(2)),
+                    Provenance::Synthetic { cause: Some(0..10) }
+                ),
+                (Event::Text("Heading".into()), Provenance::Original(2..9)),
+                (
+                    Event::End(Tag::Heading(2)),
+                    Provenance::Synthetic { cause: Some(0..10) }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_are_unknown_without_offsets() {
+        let mut rewritten =
+            crate::shift_headings(0).rewrite(crate::parse("Text"));
+
+        assert!(rewritten
+            .with_provenance()
+            .all(|(_, provenance)| provenance == Provenance::Unknown));
+    }
+
+    #[test]
+    fn chained_rewriters_keep_synthetic_events_synthetic() {
+        let src = "# Heading\n";
+        let first = rewrite_with_offsets(
+            Parser::new(src).into_offset_iter(),
+            crate::shift_headings(1),
+        );
+
+        let mut rewritten = Rewritten::new(
+            first,
+            |event: Event<'static>, writer: &mut Writer<'static>| {
+                writer.pass_through(event)
+            },
+        );
+        let got: Vec<_> = rewritten.with_provenance().collect();
+
+        assert_eq!(got[0].1, Provenance::Synthetic { cause: Some(0..10) });
+        assert_eq!(got[1].1, Provenance::Original(2..9));
+    }
+
+    #[test]
+    fn only_the_first_copy_is_passed_through() {
+        let src = "Text";
+        let got: Vec<_> = rewrite_with_offsets(
+            Parser::new(src).into_offset_iter(),
+            |event: Event<'static>, writer: &mut Writer<'static>| {
+                writer.pass_through(event.clone());
+                writer.pass_through(event);
+            },
+        )
+        .map(|(_, provenance)| provenance)
+        .collect();
+
+        assert_eq!(got[2], Provenance::Original(0..4));
+        assert_eq!(got[3], Provenance::Synthetic { cause: Some(0..4) });
+    }
+
+    #[test]
+    fn buffered_events_keep_their_provenance() {
+        // replace_region() holds onto an unterminated region until finish()
+        let src = "<!-- begin x -->\n\nText\n";
+        let mut rewritten = crate::replace_region("x", "ignored")
+            .rewrite(Parser::new(src).into_offset_iter());
+
+        let got: Vec<_> = rewritten.with_provenance().collect();
+
+        assert_eq!(got[0].1, Provenance::Original(0..17));
+        assert_eq!(
+            got[2],
+            (Event::Text("Text".into()), Provenance::Original(18..22))
+        );
+    }
+
+    #[test]
+    fn events_created_while_finishing_have_no_cause() {
+        let src = "Some text[^a].\n\n[^a]: Note.\n";
+        let events = Parser::new_ext(src, pulldown_cmark::Options::all())
+            .into_offset_iter();
+        let mut rewritten = crate::renumber_footnotes().rewrite(events);
+
+        let got: Vec<_> = rewritten.with_provenance().collect();
+        let definition = got
+            .iter()
+            .position(|(event, _)| {
+                matches!(event, Event::Start(Tag::FootnoteDefinition(_)))
+            })
+            .unwrap();
+
+        assert_eq!(got[definition].1, Provenance::Synthetic { cause: None });
+        // but the definition's contents came from the original document
+        assert!(matches!(got[definition + 1].1, Provenance::Original(_)));
+    }
 }
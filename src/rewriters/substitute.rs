@@ -0,0 +1,377 @@
+use crate::{Provenance, Rewriter, Writer};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Tag};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+/// Create a [`Substitute`] rewriter which will expand `{{name}}` placeholders
+/// using the provided variables.
+///
+/// See [`Substitute`] for more details on the placeholder syntax and which
+/// parts of the document are affected.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Rewriter;
+/// use pulldown_cmark::{Event, LinkType, Tag};
+///
+/// let src = "Version {{version}} is [out]({{repo_url}}/releases)!";
+/// let variables = vec![
+///     ("version", "1.2.3"),
+///     ("repo_url", "https://github.com/Michael-F-Bryan/markedit"),
+/// ];
+///
+/// let mut rewritten = markedit::substitute(variables)
+///     .rewrite(markedit::parse(src));
+/// let events: Vec<_> = rewritten.by_ref().collect();
+///
+/// assert_eq!(events[1], Event::Text("Version 1.2.3 is ".into()));
+/// assert_eq!(
+///     events[2],
+///     Event::Start(Tag::Link(
+///         LinkType::Inline,
+///         "https://github.com/Michael-F-Bryan/markedit/releases".into(),
+///         "".into(),
+///     )),
+/// );
+/// // all the variables were known
+/// assert!(rewritten.rewriter().errors().is_empty());
+/// ```
+pub fn substitute<'src, I, K, V>(variables: I) -> Substitute<'src>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+{
+    Substitute::new(
+        variables
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect(),
+    )
+}
+
+/// A [`Rewriter`] which expands `{{name}}` placeholders.
+///
+/// Placeholders are expanded in [`Event::Text`], [`Event::Code`],
+/// [`Event::Html`], the contents of code blocks, link and image URLs and
+/// titles, and the info string of fenced code blocks. Whitespace around the
+/// variable name is ignored, so `{{ version }}` and `{{version}}` are
+/// equivalent.
+///
+/// Use `{{{{` and `}}}}` to emit a literal `{{` or `}}`. Any other stray
+/// braces are kept as literal text, so `{{{version}}}` becomes `{1.2.3}`.
+/// Backslash escapes can't be used because the Markdown parser will have
+/// already removed them.
+///
+/// Placeholders referring to an unknown variable are left as-is and recorded
+/// as an [`UnknownVariable`] error. You can inspect these errors after
+/// rewriting by using [`Rewritten::rewriter()`][crate::Rewritten::rewriter].
+///
+/// ```rust
+/// use markedit::Rewriter;
+/// use pulldown_cmark::Event;
+///
+/// let src = "Released on {{date}}, use {{{{version}}}} in templates";
+///
+/// let mut rewritten = markedit::substitute(vec![("version", "1.2.3")])
+///     .rewrite(markedit::parse(src));
+/// let events: Vec<_> = rewritten.by_ref().collect();
+///
+/// assert_eq!(
+///     events[1],
+///     Event::Text("Released on {{date}}, use {{version}} in templates".into()),
+/// );
+/// let errors = rewritten.rewriter().errors();
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].name, "date");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitute<'src> {
+    variables: HashMap<String, String>,
+    /// Consecutive [`Event::Text`] nodes, merged so placeholders which were
+    /// split by the parser can still be found, and where they came from.
+    pending_text: Option<(CowStr<'src>, Provenance)>,
+    errors: Vec<UnknownVariable>,
+}
+
+impl<'src> Substitute<'src> {
+    /// Create a new [`Substitute`] rewriter.
+    pub fn new(variables: HashMap<String, String>) -> Self {
+        Substitute {
+            variables,
+            pending_text: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Any placeholders which referred to unknown variables.
+    pub fn errors(&self) -> &[UnknownVariable] { &self.errors }
+
+    fn flush_text(&mut self, writer: &mut Writer<'src>) {
+        if let Some((text, provenance)) = self.pending_text.take() {
+            let provenance = if needs_expanding(&text) {
+                Provenance::Synthetic {
+                    cause: provenance.cause().cloned(),
+                }
+            } else {
+                provenance
+            };
+
+            let text = self.expand(text, true);
+            writer.push_spanned(Event::Text(text), provenance);
+        }
+    }
+
+    fn expand_tag(&mut self, tag: Tag<'src>, is_start: bool) -> Tag<'src> {
+        // the same tag appears in both Start and End events, so we only want
+        // to report errors once
+        match tag {
+            Tag::Link(link_type, url, title) => Tag::Link(
+                link_type,
+                self.expand(url, is_start),
+                self.expand(title, is_start),
+            ),
+            Tag::Image(link_type, url, title) => Tag::Image(
+                link_type,
+                self.expand(url, is_start),
+                self.expand(title, is_start),
+            ),
+            Tag::CodeBlock(CodeBlockKind::Fenced(info)) => Tag::CodeBlock(
+                CodeBlockKind::Fenced(self.expand(info, is_start)),
+            ),
+            other => other,
+        }
+    }
+
+    fn expand(
+        &mut self,
+        text: CowStr<'src>,
+        report_errors: bool,
+    ) -> CowStr<'src> {
+        if !needs_expanding(&text) {
+            // fast path, there's nothing to expand
+            return text;
+        }
+
+        let mut expanded = String::with_capacity(text.len());
+        let mut rest: &str = &text;
+
+        while let Some(c) = rest.chars().next() {
+            if rest.starts_with("{{{{") {
+                expanded.push_str("{{");
+                rest = &rest[4..];
+            } else if rest.starts_with("}}}}") {
+                expanded.push_str("}}");
+                rest = &rest[4..];
+            } else if let Some(placeholder_len) = placeholder_length(rest) {
+                let placeholder = &rest[..placeholder_len];
+                let name = placeholder[2..placeholder_len - 2].trim();
+
+                match self.variables.get(name) {
+                    Some(value) => expanded.push_str(value),
+                    None => {
+                        if report_errors {
+                            self.errors.push(UnknownVariable {
+                                name: name.to_string(),
+                            });
+                        }
+                        expanded.push_str(placeholder);
+                    },
+                }
+
+                rest = &rest[placeholder_len..];
+            } else {
+                expanded.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+
+        expanded.into()
+    }
+}
+
+fn needs_expanding(text: &str) -> bool {
+    text.contains("{{") || text.contains("}}}}")
+}
+
+fn tag_needs_expanding(tag: &Tag<'_>) -> bool {
+    match tag {
+        Tag::Link(_, url, title) | Tag::Image(_, url, title) => {
+            needs_expanding(url) || needs_expanding(title)
+        },
+        Tag::CodeBlock(CodeBlockKind::Fenced(info)) => needs_expanding(info),
+        _ => false,
+    }
+}
+
+/// Merged text events are synthetic, caused by everything they were merged
+/// from.
+fn merge_provenance(first: Provenance, second: Provenance) -> Provenance {
+    let cause = match (first.cause(), second.cause()) {
+        (Some(first), Some(second)) => Some(first.start..second.end),
+        (first, second) => first.or(second).cloned(),
+    };
+
+    Provenance::Synthetic { cause }
+}
+
+/// If `text` starts with a `{{name}}` placeholder, get its length.
+///
+/// Names can't contain braces, so unbalanced braces like the outer pair in
+/// `{{{name}}}` are treated as literal text.
+fn placeholder_length(text: &str) -> Option<usize> {
+    if !text.starts_with("{{") {
+        return None;
+    }
+
+    let end = text[2..].find("}}")?;
+    let name = text[2..2 + end].trim();
+
+    if name.is_empty() || name.contains(['{', '}']) {
+        None
+    } else {
+        Some(end + 4)
+    }
+}
+
+impl<'src> Rewriter<'src> for Substitute<'src> {
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        if let Event::Text(text) = event {
+            let provenance = writer.provenance();
+
+            self.pending_text = Some(match self.pending_text.take() {
+                Some((previous, previous_provenance)) => (
+                    CowStr::from(format!("{}{}", previous, text)),
+                    merge_provenance(previous_provenance, provenance),
+                ),
+                None => (text, provenance),
+            });
+            return;
+        }
+
+        self.flush_text(writer);
+
+        let unchanged = match &event {
+            Event::Code(text) | Event::Html(text) => !needs_expanding(text),
+            Event::Start(tag) | Event::End(tag) => !tag_needs_expanding(tag),
+            _ => true,
+        };
+        if unchanged {
+            writer.pass_through(event);
+            return;
+        }
+
+        let event = match event {
+            Event::Code(code) => Event::Code(self.expand(code, true)),
+            Event::Html(html) => Event::Html(self.expand(html, true)),
+            Event::Start(tag) => Event::Start(self.expand_tag(tag, true)),
+            Event::End(tag) => Event::End(self.expand_tag(tag, false)),
+            other => other,
+        };
+        writer.push(event);
+    }
+
+    fn finish(&mut self, writer: &mut Writer<'src>) { self.flush_text(writer); }
+}
+
+/// A placeholder referred to a variable which wasn't defined.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownVariable {
+    /// The variable's name.
+    pub name: String,
+}
+
+impl Display for UnknownVariable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown variable, \"{}\"", self.name)
+    }
+}
+
+impl Error for UnknownVariable {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::LinkType;
+
+    fn substituted(src: &str) -> (Vec<Event<'_>>, Vec<UnknownVariable>) {
+        let variables = vec![
+            ("version", "1.2.3"),
+            ("repo_url", "https://example.com/repo"),
+            ("lang", "rust"),
+        ];
+        let mut rewritten = substitute(variables).rewrite(crate::parse(src));
+        let events = rewritten.by_ref().collect();
+
+        (events, rewritten.into_rewriter().errors)
+    }
+
+    #[test]
+    fn placeholders_split_across_text_events_are_expanded() {
+        // the backslash makes pulldown-cmark emit two Text events
+        let (events, errors) = substituted("v\\{{version}}");
+
+        assert_eq!(events[1], Event::Text("v1.2.3".into()));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn expand_inline_code_images_and_code_blocks() {
+        let src = "`{{ version }}` ![logo]({{repo_url}}/logo.png \"{{version}}\")\n\n```{{lang}}\nlet v = \"{{version}}\";\n```";
+        let (events, errors) = substituted(src);
+
+        assert_eq!(events[1], Event::Code("1.2.3".into()));
+        let image = Tag::Image(
+            LinkType::Inline,
+            "https://example.com/repo/logo.png".into(),
+            "1.2.3".into(),
+        );
+        assert_eq!(events[3], Event::Start(image.clone()));
+        assert_eq!(events[5], Event::End(image));
+        let code_block = Tag::CodeBlock(CodeBlockKind::Fenced("rust".into()));
+        assert_eq!(events[7], Event::Start(code_block.clone()));
+        assert_eq!(events[8], Event::Text("let v = \"1.2.3\";\n".into()));
+        assert_eq!(events[9], Event::End(code_block));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn unknown_variables_in_links_are_only_reported_once() {
+        let (events, errors) = substituted("[link]({{missing}})");
+
+        assert_eq!(
+            events[1],
+            Event::Start(Tag::Link(
+                LinkType::Inline,
+                "{{missing}}".into(),
+                "".into()
+            ))
+        );
+        assert_eq!(
+            errors,
+            vec![UnknownVariable {
+                name: String::from("missing")
+            }]
+        );
+    }
+
+    #[test]
+    fn unterminated_and_empty_placeholders_are_left_alone() {
+        let (events, errors) = substituted("{{}} and {{version");
+
+        assert_eq!(events[1], Event::Text("{{}} and {{version".into()));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn unbalanced_braces_are_literal_text() {
+        let (events, errors) = substituted("{{{version}}} and {{{ lang }}");
+
+        assert_eq!(events[1], Event::Text("{1.2.3} and {rust".into()));
+        assert!(errors.is_empty());
+    }
+}
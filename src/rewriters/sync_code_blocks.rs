@@ -0,0 +1,454 @@
+use crate::{InfoString, Provenance, Rewriter, Writer};
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+/// Create a [`SyncCodeBlocks`] rewriter which resolves `source=...` paths
+/// relative to `root`.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Rewriter;
+///
+/// let src = "```toml source=Cargo.toml lines=1-2\nold contents\n```";
+///
+/// let mut rewritten = markedit::sync_code_blocks(".")
+///     .rewrite(markedit::parse(src));
+/// let markdown = markedit::to_markdown(rewritten.by_ref());
+///
+/// assert_eq!(
+///     markdown,
+///     "```toml source=Cargo.toml lines=1-2\n[package]\nname = \"markedit\"\n```\n",
+/// );
+/// assert_eq!(rewritten.rewriter().drifted().len(), 1);
+/// assert!(rewritten.rewriter().errors().is_empty());
+/// ```
+pub fn sync_code_blocks<'src, P: Into<PathBuf>>(
+    root: P,
+) -> SyncCodeBlocks<'src> {
+    SyncCodeBlocks::new(root.into())
+}
+
+/// A [`Rewriter`] which replaces the contents of fenced code blocks with the
+/// file they were copied from.
+///
+/// A code block is synchronised when its info string has a `source=...`
+/// attribute, and you can embed just part of a file with either a
+/// `lines=start-end` attribute (1-based and inclusive, with either end being
+/// optional) or an `anchor=name` attribute. Anchors use the same
+/// `ANCHOR: name` and `ANCHOR_END: name` comments as mdBook, and any anchor
+/// comments inside the embedded region are removed.
+///
+/// Blocks whose contents don't match the file are recorded in
+/// [`SyncCodeBlocks::drifted()`], and problems like missing files are
+/// recorded in [`SyncCodeBlocks::errors()`]. Use
+/// [`SyncCodeBlocks::check()`] to find out of date blocks without changing
+/// anything (e.g. in CI).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncCodeBlocks<'src> {
+    root: PathBuf,
+    check: bool,
+    files: HashMap<PathBuf, Option<String>>,
+    /// The fenced code block being buffered, its code, and its original
+    /// events.
+    current_block: Option<(Tag<'src>, String, Buffered<'src>)>,
+    drifted: Vec<DriftedBlock>,
+    errors: Vec<SyncError>,
+}
+
+/// A code block's original [`Event`]s and where they came from.
+type Buffered<'src> = Vec<(Event<'src>, Provenance)>;
+
+impl<'src> SyncCodeBlocks<'src> {
+    /// Create a new [`SyncCodeBlocks`] rewriter.
+    pub fn new(root: PathBuf) -> Self {
+        SyncCodeBlocks {
+            root,
+            check: false,
+            files: HashMap::new(),
+            current_block: None,
+            drifted: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Only report which code blocks are out of date, leaving the document
+    /// unchanged.
+    ///
+    /// ```rust
+    /// use markedit::Rewriter;
+    ///
+    /// let src = "```toml source=Cargo.toml lines=1\n[package]\n```\n\n```rust source=src/lib.rs lines=1\nold\n```";
+    ///
+    /// let mut rewritten = markedit::sync_code_blocks(".")
+    ///     .check()
+    ///     .rewrite(markedit::parse(src));
+    /// let markdown = markedit::to_markdown(rewritten.by_ref());
+    ///
+    /// assert!(markdown.contains("old"));
+    /// let checker = rewritten.rewriter();
+    /// assert!(!checker.is_in_sync());
+    /// assert_eq!(checker.drifted().len(), 1);
+    /// assert_eq!(checker.drifted()[0].current, "old\n");
+    /// ```
+    pub fn check(self) -> Self {
+        SyncCodeBlocks {
+            check: true,
+            ..self
+        }
+    }
+
+    /// Code blocks which didn't match the file they were copied from.
+    pub fn drifted(&self) -> &[DriftedBlock] { &self.drifted }
+
+    /// Any code blocks which couldn't be synchronised.
+    pub fn errors(&self) -> &[SyncError] { &self.errors }
+
+    /// Was every code block up to date?
+    pub fn is_in_sync(&self) -> bool {
+        self.drifted.is_empty() && self.errors.is_empty()
+    }
+
+    /// Figure out what the code block should contain.
+    fn expected_contents(&mut self, info: &InfoString) -> Option<String> {
+        let source = PathBuf::from(info.get("source")?);
+        let contents = match self.read(&source) {
+            Some(contents) => contents,
+            None => {
+                self.errors.push(SyncError::UnreadableFile(source));
+                return None;
+            },
+        };
+
+        let extracted = if let Some(lines) = info.get("lines") {
+            extract_lines(contents, lines).ok_or_else(|| {
+                SyncError::InvalidLineRange {
+                    path: source.clone(),
+                    lines: lines.to_string(),
+                }
+            })
+        } else if let Some(anchor) = info.get("anchor") {
+            extract_anchor(contents, anchor).ok_or_else(|| {
+                SyncError::UnknownAnchor {
+                    path: source.clone(),
+                    anchor: anchor.to_string(),
+                }
+            })
+        } else {
+            Ok(contents.to_string())
+        };
+
+        match extracted {
+            Ok(mut code) => {
+                if !code.is_empty() && !code.ends_with('\n') {
+                    code.push('\n');
+                }
+                Some(code)
+            },
+            Err(e) => {
+                self.errors.push(e);
+                None
+            },
+        }
+    }
+
+    fn read(&mut self, source: &Path) -> Option<&str> {
+        let path = self.root.join(source);
+
+        self.files
+            .entry(path)
+            .or_insert_with_key(|path| std::fs::read_to_string(path).ok())
+            .as_deref()
+    }
+
+    fn finish_block(
+        &mut self,
+        tag: Tag<'src>,
+        current: String,
+        original: Buffered<'src>,
+        writer: &mut Writer<'src>,
+    ) {
+        let info = match tag {
+            Tag::CodeBlock(CodeBlockKind::Fenced(ref info)) => {
+                InfoString::parse(info)
+            },
+            _ => unreachable!("We only buffer fenced code blocks"),
+        };
+
+        let text = match self.expected_contents(&info) {
+            Some(expected) if expected != current => {
+                let source = PathBuf::from(info.get("source").unwrap_or(""));
+                self.drifted.push(DriftedBlock {
+                    source,
+                    current: current.clone(),
+                    expected: expected.clone(),
+                });
+
+                if self.check {
+                    None
+                } else {
+                    Some(expected)
+                }
+            },
+            _ => None,
+        };
+
+        let text = match text {
+            Some(text) => text,
+            // leave the block exactly as it was written
+            None => {
+                for (event, provenance) in original {
+                    writer.push_spanned(event, provenance);
+                }
+                return;
+            },
+        };
+
+        writer.push(Event::Start(tag.clone()));
+        if !text.is_empty() {
+            writer.push(Event::Text(text.into()));
+        }
+        writer.push(Event::End(tag));
+    }
+}
+
+impl<'src> Rewriter<'src> for SyncCodeBlocks<'src> {
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref info)))
+                if InfoString::parse(info).get("source").is_some() =>
+            {
+                let tag = Tag::CodeBlock(CodeBlockKind::Fenced(info.clone()));
+                let original = vec![(event, writer.provenance())];
+                self.current_block = Some((tag, String::new(), original));
+            },
+            Event::Text(ref text) if self.current_block.is_some() => {
+                if let Some((_, ref mut code, ref mut original)) =
+                    self.current_block
+                {
+                    code.push_str(text);
+                    original.push((event, writer.provenance()));
+                }
+            },
+            Event::End(Tag::CodeBlock(_)) if self.current_block.is_some() => {
+                if let Some((tag, code, mut original)) =
+                    self.current_block.take()
+                {
+                    original.push((event, writer.provenance()));
+                    self.finish_block(tag, code, original, writer);
+                }
+            },
+            other => writer.pass_through(other),
+        }
+    }
+}
+
+/// Parse a `start-end` line range and get those lines.
+fn extract_lines(contents: &str, range: &str) -> Option<String> {
+    let parse_bound = |bound: &str| -> Option<Option<usize>> {
+        let bound = bound.trim();
+
+        if bound.is_empty() {
+            Some(None)
+        } else {
+            bound.parse().ok().filter(|&n| n > 0).map(Some)
+        }
+    };
+
+    let (start, end) = match range.find('-') {
+        Some(ix) => {
+            (parse_bound(&range[..ix])?, parse_bound(&range[ix + 1..])?)
+        },
+        None => {
+            let line = parse_bound(range)?;
+            (line, line)
+        },
+    };
+
+    let lines: Vec<&str> = contents.lines().collect();
+    let start = start.unwrap_or(1);
+    let end = end.unwrap_or(lines.len());
+
+    if start > end || end > lines.len() {
+        return None;
+    }
+
+    Some(lines[start - 1..end].join("\n"))
+}
+
+/// Get the lines between the `ANCHOR: name` and `ANCHOR_END: name` comments,
+/// skipping any other anchor comments.
+fn extract_anchor(contents: &str, anchor: &str) -> Option<String> {
+    let mut lines = contents.lines();
+
+    lines
+        .by_ref()
+        .find(|line| anchor_name(line, "ANCHOR:") == Some(anchor))?;
+
+    let mut extracted = Vec::new();
+
+    for line in lines {
+        if anchor_name(line, "ANCHOR_END:") == Some(anchor) {
+            return Some(extracted.join("\n"));
+        }
+
+        if anchor_name(line, "ANCHOR:").is_none()
+            && anchor_name(line, "ANCHOR_END:").is_none()
+        {
+            extracted.push(line);
+        }
+    }
+
+    // we never found the end
+    None
+}
+
+fn anchor_name<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    let ix = line.find(marker)?;
+
+    line[ix + marker.len()..].split_whitespace().next()
+}
+
+/// A code block which didn't match the file it was copied from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DriftedBlock {
+    /// The `source=...` path, as written in the info string.
+    pub source: PathBuf,
+    /// The code block's original contents.
+    pub current: String,
+    /// What the code block should contain.
+    pub expected: String,
+}
+
+/// The reasons a code block may not be able to be synchronised.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyncError {
+    /// The source file couldn't be read.
+    UnreadableFile(PathBuf),
+    /// The `lines=...` attribute wasn't a valid range for this file.
+    InvalidLineRange {
+        /// The source file.
+        path: PathBuf,
+        /// The line range, as written in the info string.
+        lines: String,
+    },
+    /// The source file doesn't contain the desired anchor.
+    UnknownAnchor {
+        /// The source file.
+        path: PathBuf,
+        /// The anchor that couldn't be found.
+        anchor: String,
+    },
+}
+
+impl Display for SyncError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::UnreadableFile(path) => {
+                write!(f, "Unable to read \"{}\"", path.display())
+            },
+            SyncError::InvalidLineRange { path, lines } => write!(
+                f,
+                "\"{}\" isn't a valid line range for \"{}\"",
+                lines,
+                path.display()
+            ),
+            SyncError::UnknownAnchor { path, anchor } => write!(
+                f,
+                "\"{}\" has no anchor called \"{}\"",
+                path.display(),
+                anchor
+            ),
+        }
+    }
+}
+
+impl Error for SyncError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "use std::fmt;
+
+// ANCHOR: main
+fn main() {
+    // ANCHOR: greeting
+    println!(\"Hello, World!\");
+    // ANCHOR_END: greeting
+}
+// ANCHOR_END: main
+";
+
+    #[test]
+    fn extract_line_ranges() {
+        let inputs = vec![
+            ("1", Some("use std::fmt;")),
+            ("4-4", Some("fn main() {")),
+            ("8-", Some("}\n// ANCHOR_END: main")),
+            ("-2", Some("use std::fmt;\n")),
+            ("0-3", None),
+            ("5-4", None),
+            ("9-10", None),
+            ("not-a-range", None),
+        ];
+
+        for (range, should_be) in inputs {
+            let got = extract_lines(SOURCE, range);
+            assert_eq!(got.as_deref(), should_be, "{}", range);
+        }
+    }
+
+    #[test]
+    fn extract_anchors() {
+        assert_eq!(
+            extract_anchor(SOURCE, "main").unwrap(),
+            "fn main() {\n    println!(\"Hello, World!\");\n}"
+        );
+        assert_eq!(
+            extract_anchor(SOURCE, "greeting").unwrap(),
+            "    println!(\"Hello, World!\");"
+        );
+        assert_eq!(extract_anchor(SOURCE, "gree"), None);
+    }
+
+    #[test]
+    fn report_errors_and_leave_the_block_alone() {
+        let src = "```rust source=does-not-exist.rs\nold\n```\n\n```toml source=Cargo.toml anchor=nope\nold\n```\n";
+
+        let mut rewritten = sync_code_blocks(env!("CARGO_MANIFEST_DIR"))
+            .rewrite(crate::parse(src));
+        let markdown = crate::to_markdown(rewritten.by_ref());
+        let syncer = rewritten.into_rewriter();
+
+        assert_eq!(markdown, src);
+        assert!(syncer.drifted().is_empty());
+        assert_eq!(
+            syncer.errors(),
+            &[
+                SyncError::UnreadableFile(PathBuf::from("does-not-exist.rs")),
+                SyncError::UnknownAnchor {
+                    path: PathBuf::from("Cargo.toml"),
+                    anchor: String::from("nope"),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn check_mode_leaves_the_source_alone() {
+        let src = "~~~toml source=Cargo.toml lines=1\nold\n~~~\n";
+        let events = pulldown_cmark::Parser::new(src).into_offset_iter();
+
+        let mut syncer = sync_code_blocks(env!("CARGO_MANIFEST_DIR")).check();
+        let edits = crate::rewrite_to_edits(src, events, &mut syncer);
+
+        assert_eq!(edits.apply(src), src);
+        assert_eq!(syncer.drifted().len(), 1);
+    }
+}
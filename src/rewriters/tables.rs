@@ -0,0 +1,142 @@
+use crate::{Matcher, Provenance, Rewriter, Table, Writer};
+use pulldown_cmark::{Event, Tag};
+
+/// A [`Rewriter`] which lets you edit every table whose [`Event::Start`] tag
+/// is matched by the [`Matcher`].
+///
+/// The table's [`Event`]s are buffered until the end of the table, then
+/// passed to the closure as a [`Table`] so you can sort rows, add or remove
+/// columns, or rewrite cells. The new table's events are synthetic, caused by
+/// the whole table.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{pulldown_cmark::Options, Table};
+///
+/// let src = "| Crate | Downloads |
+/// | --- | --: |
+/// | serde | 100 |
+/// | anyhow | 20 |
+/// ";
+///
+/// let rewriter = markedit::rewrite_tables(
+///     |_: &markedit::pulldown_cmark::Event<'_>| true,
+///     |table: &mut Table<'_>| {
+///         table.sort_by_column(0);
+///         table.remove_column(1);
+///     },
+/// );
+/// let events = markedit::parse_with_options(src, Options::ENABLE_TABLES);
+/// let markdown = markedit::to_markdown(markedit::rewrite(events, rewriter));
+///
+/// assert_eq!(markdown, "| Crate |\n| --- |\n| anyhow |\n| serde |\n");
+/// ```
+pub fn rewrite_tables<'src, M, F>(
+    mut matcher: M,
+    mut mutator: F,
+) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+    F: FnMut(&mut Table<'src>) + 'src,
+{
+    let mut buffer: Option<Vec<(Event<'src>, Provenance)>> = None;
+
+    move |ev: Event<'src>, writer: &mut Writer<'src>| {
+        let is_match = matcher.matches_event(&ev);
+
+        match ev {
+            Event::Start(Tag::Table(_)) if is_match => {
+                buffer = Some(vec![(ev, writer.provenance())]);
+            },
+            Event::End(Tag::Table(_)) if buffer.is_some() => {
+                let mut events = buffer.take().unwrap_or_default();
+                events.push((ev, writer.provenance()));
+
+                let table = Table::from_events(
+                    events.iter().map(|(event, _)| event.clone()),
+                );
+
+                match table {
+                    Some(mut table) => {
+                        mutator(&mut table);
+                        writer.extend(table.into_events());
+                    },
+                    // we couldn't make sense of the table, so pass it through
+                    // untouched
+                    None => {
+                        for (event, provenance) in events {
+                            writer.push_spanned(event, provenance);
+                        }
+                    },
+                }
+            },
+            other => match buffer {
+                Some(ref mut events) => {
+                    events.push((other, writer.provenance()))
+                },
+                None => writer.pass_through(other),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Options;
+
+    #[test]
+    fn only_matched_tables_are_rewritten() {
+        let src = "# Dependencies
+
+| name |
+| --- |
+| b |
+| a |
+
+# Other
+
+| name |
+| --- |
+| d |
+| c |
+";
+        let mut seen_heading = false;
+        let after_dependencies = move |ev: &Event<'_>| {
+            match ev {
+                Event::Text(text) if text.as_ref() == "Dependencies" => {
+                    seen_heading = true
+                },
+                Event::Text(text) if text.as_ref() == "Other" => {
+                    seen_heading = false
+                },
+                _ => {},
+            }
+            seen_heading
+        };
+        let rewriter =
+            rewrite_tables(after_dependencies, |table| table.sort_by_column(0));
+
+        let events = crate::parse_with_options(src, Options::ENABLE_TABLES);
+        let markdown = crate::to_markdown(crate::rewrite(events, rewriter));
+
+        assert_eq!(
+            markdown,
+            "# Dependencies
+
+| name |
+| --- |
+| a |
+| b |
+
+# Other
+
+| name |
+| --- |
+| d |
+| c |
+"
+        );
+    }
+}
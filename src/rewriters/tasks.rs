@@ -0,0 +1,207 @@
+use crate::{
+    rewriters::lists::{buffer_lists, rewrite_lists},
+    Always, Matcher, Rewriter,
+};
+use pulldown_cmark::{Event, Tag};
+
+/// A [`Rewriter`] which checks any task list items whose text is matched by
+/// the [`Matcher`].
+///
+/// Only the item's own text is considered, so a match inside a nested list
+/// won't check its parent.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::pulldown_cmark::Options;
+///
+/// let src = "- [ ] Write the code\n- [ ] Write the docs\n";
+///
+/// let rewriter = markedit::check_tasks(markedit::text_containing("docs"));
+/// let events = markedit::parse_with_options(src, Options::ENABLE_TASKLISTS);
+/// let markdown = markedit::to_markdown(markedit::rewrite(events, rewriter));
+///
+/// assert_eq!(markdown, "- [ ] Write the code\n- [x] Write the docs\n");
+/// ```
+pub fn check_tasks<'src, M>(matcher: M) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+{
+    set_tasks(matcher, true)
+}
+
+/// A [`Rewriter`] which unchecks any task list items whose text is matched by
+/// the [`Matcher`].
+///
+/// This is the inverse of [`check_tasks()`].
+pub fn uncheck_tasks<'src, M>(matcher: M) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+{
+    set_tasks(matcher, false)
+}
+
+/// A [`Rewriter`] which removes every checked task list item, along with
+/// anything nested inside it.
+///
+/// Lists which end up empty are removed entirely.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::pulldown_cmark::Options;
+///
+/// let src = "- [x] Done\n- [ ] Todo\n  - [x] Nested and done\n- Not a task\n";
+///
+/// let events = markedit::parse_with_options(src, Options::ENABLE_TASKLISTS);
+/// let rewritten =
+///     markedit::rewrite(events, markedit::remove_completed_tasks());
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "- [ ] Todo\n- Not a task\n",
+/// );
+/// ```
+pub fn remove_completed_tasks<'src>() -> impl Rewriter<'src> + 'src {
+    rewrite_lists(Always, |_, items| {
+        items.retain(|item| task_status(item) != Some(true))
+    })
+}
+
+/// A [`Rewriter`] which moves checked task list items to the bottom of their
+/// list, keeping everything else in the same order.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::pulldown_cmark::Options;
+///
+/// let src = "- [x] First\n- [ ] Second\n- [x] Third\n- [ ] Fourth\n";
+///
+/// let events = markedit::parse_with_options(src, Options::ENABLE_TASKLISTS);
+/// let rewritten =
+///     markedit::rewrite(events, markedit::move_completed_tasks_to_bottom());
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "- [ ] Second\n- [ ] Fourth\n- [x] First\n- [x] Third\n",
+/// );
+/// ```
+pub fn move_completed_tasks_to_bottom<'src>() -> impl Rewriter<'src> + 'src {
+    rewrite_lists(Always, |_, items| {
+        let (mut todo, done): (Vec<_>, Vec<_>) = items
+            .drain(..)
+            .partition(|item| task_status(item) != Some(true));
+        todo.extend(done);
+        *items = todo;
+    })
+}
+
+fn set_tasks<'src, M>(matcher: M, checked: bool) -> impl Rewriter<'src> + 'src
+where
+    M: Matcher + 'src,
+{
+    buffer_lists(matcher, move |mut events, matched| {
+        let markers: Vec<_> = events
+            .iter()
+            .enumerate()
+            .filter(|(_, ev)| matches!(ev, Event::TaskListMarker(_)))
+            .map(|(ix, _)| ix)
+            .collect();
+
+        for marker in markers {
+            // the item's own text stops at a nested list or the end of the
+            // item
+            let end = events[marker..]
+                .iter()
+                .position(|ev| {
+                    matches!(
+                        ev,
+                        Event::Start(Tag::List(_)) | Event::End(Tag::Item)
+                    )
+                })
+                .map_or(events.len(), |offset| marker + offset);
+
+            if (marker..end).any(|ix| matched.contains(&ix)) {
+                events[marker] = Event::TaskListMarker(checked);
+            }
+        }
+
+        events
+    })
+}
+
+/// Is this list item a task, and if so, is it checked?
+fn task_status(item: &[Event<'_>]) -> Option<bool> {
+    match item.get(1) {
+        Some(Event::TaskListMarker(checked)) => Some(*checked),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Options;
+
+    fn rewrite_tasks<'src, R>(src: &'src str, rewriter: R) -> String
+    where
+        R: Rewriter<'src> + 'src,
+    {
+        let events = crate::parse_with_options(src, Options::ENABLE_TASKLISTS);
+        crate::to_markdown(crate::rewrite(events, rewriter))
+    }
+
+    #[test]
+    fn matches_in_nested_items_dont_affect_the_parent() {
+        let src = "- [ ] parent
+  - [ ] nested release
+- [ ] release
+";
+
+        let got =
+            rewrite_tasks(src, check_tasks(crate::text_containing("release")));
+
+        assert_eq!(
+            got,
+            "- [ ] parent\n  - [x] nested release\n- [x] release\n"
+        );
+    }
+
+    #[test]
+    fn nested_lists_are_sorted_independently() {
+        let src = "1. [x] done
+2. [ ] parent
+   - [x] nested done
+   - [ ] nested todo
+
+Some paragraph.
+
+- [x] only done
+";
+
+        let got = rewrite_tasks(src, move_completed_tasks_to_bottom());
+
+        assert_eq!(
+            got,
+            "1. [ ] parent
+   - [ ] nested todo
+   - [x] nested done
+2. [x] done
+
+Some paragraph.
+
+- [x] only done
+"
+        );
+    }
+
+    #[test]
+    fn lists_with_only_completed_tasks_are_removed() {
+        let src = "Before\n\n- [x] done\n- [x] also done\n\nAfter\n";
+
+        let got = rewrite_tasks(src, remove_completed_tasks());
+
+        assert_eq!(got, "Before\n\nAfter\n");
+    }
+}
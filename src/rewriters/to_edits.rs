@@ -0,0 +1,408 @@
+use crate::{
+    markdown::{self, LinkDefinitions},
+    Edit, EditSet, Provenance, Rewriter, Rewritten, Writer,
+};
+use pulldown_cmark::Event;
+use std::ops::Range;
+
+/// Rewrite a document, turning the result into [`Edit`]s to the original
+/// source text instead of serializing the whole thing.
+///
+/// Each top-level block the [`Rewriter`] passed through untouched (see
+/// [`Provenance`]) is left exactly as it was written, so only blocks which
+/// were created, modified, or removed get reformatted. The `rewriter` is
+/// borrowed so you can inspect it afterwards.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::pulldown_cmark::Parser;
+///
+/// let src = "Title\n=====\n\n* Unusual\n* List markers\n\n# Section\n";
+/// let events = Parser::new(src).into_offset_iter();
+///
+/// let mut rewriter = markedit::shift_headings(1);
+/// let edits = markedit::rewrite_to_edits(src, events, &mut rewriter);
+///
+/// assert_eq!(
+///     edits.apply(src),
+///     "## Title\n\n* Unusual\n* List markers\n\n## Section\n",
+/// );
+/// ```
+pub fn rewrite_to_edits<'src, E, R>(
+    src: &str,
+    events: E,
+    rewriter: &mut R,
+) -> EditSet
+where
+    E: IntoIterator<Item = (Event<'src>, Range<usize>)>,
+    R: Rewriter<'src>,
+{
+    let original: Vec<_> = events.into_iter().collect();
+    let output: Vec<_> =
+        Rewritten::new(original.iter().cloned(), ByRef(rewriter))
+            .with_provenance()
+            .collect();
+
+    let original_blocks = top_level_blocks(original.iter().map(|(ev, _)| ev));
+    let output_blocks = top_level_blocks(output.iter().map(|(ev, _)| ev));
+
+    let mut splicer = Splicer {
+        src,
+        spans: original_blocks
+            .iter()
+            .map(|block| trimmed(src, &original[block.start].1))
+            .collect(),
+        definitions: markdown::existing_definitions(src, &original),
+        edits: EditSet::new(),
+    };
+    let mut previous_anchor = None;
+    let mut new_events = Vec::new();
+
+    for block in output_blocks {
+        let events = &output[block];
+
+        match unchanged_block(&original, &original_blocks, events) {
+            // blocks which moved backwards have to be reinserted
+            Some(ix) if previous_anchor < Some(ix) => {
+                splicer.splice(previous_anchor, Some(ix), &new_events);
+                new_events.clear();
+                previous_anchor = Some(ix);
+            },
+            _ => new_events.extend(events.iter().cloned()),
+        }
+    }
+
+    splicer.splice(previous_anchor, None, &new_events);
+
+    splicer.edits
+}
+
+/// Lets [`rewrite_to_edits()`] use a borrowed [`Rewriter`].
+struct ByRef<'a, R>(&'a mut R);
+
+impl<'a, 'src, R: Rewriter<'src>> Rewriter<'src> for ByRef<'a, R> {
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        self.0.rewrite_event(event, writer);
+    }
+
+    fn finish(&mut self, writer: &mut Writer<'src>) { self.0.finish(writer); }
+}
+
+/// Split a stream of events into the index ranges of each top-level block.
+fn top_level_blocks<'a, 'src: 'a, I>(events: I) -> Vec<Range<usize>>
+where
+    I: IntoIterator<Item = &'a Event<'src>>,
+{
+    let mut blocks = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+    let mut len = 0;
+
+    for (ix, event) in events.into_iter().enumerate() {
+        len = ix + 1;
+
+        match event {
+            Event::Start(_) => {
+                if depth == 0 {
+                    start = ix;
+                }
+                depth += 1;
+            },
+            Event::End(_) => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    blocks.push(start..ix + 1);
+                }
+            },
+            _ if depth == 0 => blocks.push(ix..ix + 1),
+            _ => {},
+        }
+    }
+
+    if depth > 0 {
+        // the rewriter left a block open
+        blocks.push(start..len);
+    }
+
+    blocks
+}
+
+/// If this output block is an untouched copy of one of the original blocks,
+/// get that block's index.
+fn unchanged_block(
+    original: &[(Event<'_>, Range<usize>)],
+    original_blocks: &[Range<usize>],
+    events: &[(Event<'_>, Provenance)],
+) -> Option<usize> {
+    let start = events.first()?.1.span()?.start;
+    let ix = original_blocks
+        .binary_search_by_key(&start, |block| original[block.start].1.start)
+        .ok()?;
+
+    let original_spans = original[original_blocks[ix].clone()]
+        .iter()
+        .map(|(_, span)| Some(span));
+    let spans = events.iter().map(|(_, provenance)| provenance.span());
+
+    if spans.eq(original_spans) {
+        Some(ix)
+    } else {
+        None
+    }
+}
+
+/// A block's span, without any trailing whitespace.
+fn trimmed(src: &str, span: &Range<usize>) -> Range<usize> {
+    span.start..span.start + src[span.clone()].trim_end().len()
+}
+
+/// Turns the blocks between two unchanged blocks into [`Edit`]s.
+struct Splicer<'a> {
+    src: &'a str,
+    /// The span of each original top-level block.
+    spans: Vec<Range<usize>>,
+    /// The link reference definitions in the document so far.
+    definitions: LinkDefinitions,
+    edits: EditSet,
+}
+
+impl<'a> Splicer<'a> {
+    /// Replace the original blocks between `previous` and `next` with
+    /// `new_events`.
+    fn splice(
+        &mut self,
+        previous: Option<usize>,
+        next: Option<usize>,
+        new_events: &[(Event<'_>, Provenance)],
+    ) {
+        let first_removed = previous.map_or(0, |ix| ix + 1);
+        let removed = first_removed..next.unwrap_or(self.spans.len());
+        let markdown = markdown::to_markdown_fragment(
+            self.src,
+            &mut self.definitions,
+            new_events.iter().cloned(),
+        );
+        let markdown = markdown.trim_end();
+
+        if removed.is_empty() {
+            if !markdown.is_empty() {
+                self.insert(previous, next, markdown);
+            }
+            return;
+        }
+
+        let mut deleted = removed.clone();
+
+        if !markdown.is_empty() {
+            // reuse the first block's location
+            self.add(Edit::new(self.spans[removed.start].clone(), markdown));
+            deleted.start += 1;
+        }
+
+        self.delete(deleted);
+    }
+
+    fn insert(
+        &mut self,
+        previous: Option<usize>,
+        next: Option<usize>,
+        md: &str,
+    ) {
+        let src = self.src;
+
+        let edit = match (previous, next) {
+            (previous, Some(next)) => {
+                let start = self.spans[next].start;
+                let gap = previous.map_or("\n\n", |ix| {
+                    &src[self.spans[ix].end..start]
+                });
+                // make sure we aren't glued to the previous block
+                let prefix = if gap.contains("\n\n") { "" } else { "\n" };
+
+                Edit::insert(start, format!("{}{}\n\n", prefix, md))
+            },
+            (Some(previous), None) => {
+                Edit::insert(self.spans[previous].end, format!("\n\n{}", md))
+            },
+            (None, None) => Edit::new(0..src.len(), format!("{}\n", md)),
+        };
+
+        self.add(edit);
+    }
+
+    /// Delete some blocks, along with the blank lines after them.
+    fn delete(&mut self, blocks: Range<usize>) {
+        let src = self.src;
+        let mut pending: Option<Range<usize>> = None;
+
+        for ix in blocks {
+            let Range { start, end } = self.spans[ix];
+            let span = start..next_non_blank_line(src, end);
+
+            pending = match pending {
+                Some(previous) if previous.end == span.start => {
+                    Some(previous.start..span.end)
+                },
+                Some(previous) => {
+                    self.add(self.deletion(previous));
+                    Some(span)
+                },
+                None => Some(span),
+            };
+        }
+
+        if let Some(span) = pending {
+            self.add(self.deletion(span));
+        }
+    }
+
+    fn deletion(&self, span: Range<usize>) -> Edit {
+        let src = self.src;
+
+        if span.end < src.len() {
+            return Edit::delete(span);
+        }
+
+        // we're removing the end of the document, so take the blank lines
+        // before us too and keep the trailing newline
+        let start = src[..span.start].trim_end().len();
+        let trailing_newline = start > 0 && src.ends_with('\n');
+
+        Edit::new(start..span.end, if trailing_newline { "\n" } else { "" })
+    }
+
+    fn add(&mut self, edit: Edit) {
+        self.edits
+            .insert(edit)
+            .expect("Edits to different blocks never overlap");
+    }
+}
+
+/// The start of the first non-blank line after the one containing `offset`,
+/// or the end of the text.
+fn next_non_blank_line(src: &str, offset: usize) -> usize {
+    let mut cursor = match src[offset..].find('\n') {
+        Some(ix) => offset + ix + 1,
+        None => return src.len(),
+    };
+
+    while cursor < src.len() {
+        let line_end = src[cursor..]
+            .find('\n')
+            .map_or(src.len(), |ix| cursor + ix + 1);
+
+        if !src[cursor..line_end].trim().is_empty() {
+            break;
+        }
+        cursor = line_end;
+    }
+
+    cursor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{Options, Parser, Tag};
+
+    fn edited<'src, R>(src: &'src str, mut rewriter: R) -> String
+    where
+        R: Rewriter<'src>,
+    {
+        let events = Parser::new_ext(src, Options::all()).into_offset_iter();
+        rewrite_to_edits(src, events, &mut rewriter).apply(src)
+    }
+
+    #[test]
+    fn untouched_documents_are_left_alone() {
+        let src = "Title\n=====\n\n*  odd   spacing*\n\n+ list\n+ markers\n";
+
+        let got = edited(src, |ev, writer: &mut Writer<'_>| {
+            writer.pass_through(ev)
+        });
+
+        assert_eq!(got, src);
+    }
+
+    #[test]
+    fn only_replaced_region_contents_are_reformatted() {
+        let src = "* keep\n* me\n\n<!-- begin x -->\n\nold  *text*\n\n<!-- end x -->\n\n__Bold__\n";
+
+        let got = edited(src, crate::replace_region("x", "New __text__"));
+
+        assert_eq!(
+            got,
+            "* keep\n* me\n\n<!-- begin x -->\n\nNew **text**\n\n<!-- end x -->\n\n__Bold__\n"
+        );
+    }
+
+    #[test]
+    fn insert_between_blocks() {
+        let src = "# Title\nText\n";
+
+        let rewriter = |ev: Event<'static>, writer: &mut Writer<'static>| {
+            if ev == Event::Start(Tag::Paragraph) {
+                writer.extend(crate::parse("Inserted"));
+            }
+            writer.pass_through(ev);
+        };
+        let got = edited(src, rewriter);
+
+        assert_eq!(got, "# Title\n\nInserted\n\nText\n");
+    }
+
+    #[test]
+    fn insert_at_the_end() {
+        let src = "Text\n";
+
+        let rewriter = |ev: Event<'static>, writer: &mut Writer<'static>| {
+            let is_end = matches!(ev, Event::End(Tag::Paragraph));
+            writer.pass_through(ev);
+            if is_end {
+                writer.push(Event::Rule);
+            }
+        };
+        let got = edited(src, rewriter);
+
+        assert_eq!(got, "Text\n\n***\n");
+    }
+
+    #[test]
+    fn removed_blocks_take_their_blank_lines_with_them() {
+        let src = "First\n\n# Remove\n\nLast\n\n# Remove\n";
+        let mut in_heading = false;
+
+        let rewriter =
+            move |ev: Event<'static>, writer: &mut Writer<'static>| match ev {
+                Event::Start(Tag::Heading(_)) => in_heading = true,
+                Event::End(Tag::Heading(_)) => in_heading = false,
+                other if !in_heading => writer.pass_through(other),
+                _ => {},
+            };
+        let got = edited(src, rewriter);
+
+        assert_eq!(got, "First\n\nLast\n");
+    }
+
+    #[test]
+    fn existing_link_definitions_are_reused() {
+        let src = "# See [the docs][docs]\n\n[docs]: https://docs.rs/\n";
+
+        let got = edited(src, crate::shift_headings(1));
+
+        assert_eq!(
+            got,
+            "## See [the docs][docs]\n\n[docs]: https://docs.rs/\n"
+        );
+    }
+
+    #[test]
+    fn moved_blocks_are_reinserted() {
+        let src = "[^a]: Note.\n\nText[^a].\n";
+
+        let got = edited(src, crate::renumber_footnotes());
+
+        assert_eq!(got, "Text[^1].\n\n[^1]: Note.\n");
+    }
+}
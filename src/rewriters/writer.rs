@@ -1,28 +1,97 @@
+use crate::Provenance;
 use pulldown_cmark::Event;
-use std::collections::VecDeque;
+use std::{collections::VecDeque, ops::Range};
 
-#[allow(unused_imports)] // for rustdoc
-use crate::Rewriter;
-
-/// The output buffer given to [`Rewriter::rewrite_event()`].
+/// The output buffer given to
+/// [`Rewriter::rewrite_event()`][crate::Rewriter::rewrite_event].
+///
+/// The [`Writer`] also keeps track of each event's [`Provenance`]. Use
+/// [`Writer::pass_through()`] when emitting the event being rewritten
+/// unchanged so it keeps its original span, while anything sent to
+/// [`Writer::push()`] is treated as synthetic.
 #[derive(Debug)]
 pub struct Writer<'a> {
-    pub(crate) buffer: VecDeque<Event<'a>>,
+    pub(crate) buffer: VecDeque<(Event<'a>, Provenance)>,
+    /// Where the event being rewritten came from, or [`None`] when the
+    /// [`Rewriter`][crate::Rewriter] is finishing.
+    current: Option<Provenance>,
+    /// Has the event being rewritten already been passed through?
+    passed_through: bool,
 }
 
 impl<'a> Writer<'a> {
     pub(crate) fn new() -> Writer<'a> {
         Writer {
             buffer: VecDeque::new(),
+            current: None,
+            passed_through: false,
         }
     }
 
-    /// Queue an [`Event`] to be emitted.
-    pub fn push(&mut self, event: Event<'a>) { self.buffer.push_back(event); }
+    /// Queue an [`Event`] created by the [`Rewriter`][crate::Rewriter].
+    ///
+    /// The event is marked as [`Provenance::Synthetic`], caused by the event
+    /// currently being rewritten.
+    pub fn push(&mut self, event: Event<'a>) {
+        let provenance = Provenance::Synthetic { cause: self.cause() };
+        self.buffer.push_back((event, provenance));
+    }
+
+    /// Queue the [`Event`] currently being rewritten, unchanged, so it keeps
+    /// its original span.
+    ///
+    /// Passing the same event through more than once (or calling this from
+    /// [`Rewriter::finish()`][crate::Rewriter::finish]) is treated like a
+    /// [`Writer::push()`].
+    pub fn pass_through(&mut self, event: Event<'a>) {
+        let provenance = match self.current {
+            Some(ref provenance) if !self.passed_through => provenance.clone(),
+            _ => Provenance::Synthetic { cause: self.cause() },
+        };
+        self.passed_through = true;
+
+        self.buffer.push_back((event, provenance));
+    }
+
+    /// Queue an [`Event`] with an explicit [`Provenance`].
+    ///
+    /// This is intended for rewriters which hold onto events and emit them
+    /// later, using the [`Writer::provenance()`] recorded when each event was
+    /// buffered.
+    pub fn push_spanned(&mut self, event: Event<'a>, provenance: Provenance) {
+        self.buffer.push_back((event, provenance));
+    }
+
+    /// Where the event currently being rewritten came from.
+    ///
+    /// This is synthetic, with no cause, while the
+    /// [`Rewriter`][crate::Rewriter] is finishing.
+    pub fn provenance(&self) -> Provenance {
+        match self.current {
+            Some(ref provenance) => provenance.clone(),
+            None => Provenance::Synthetic { cause: None },
+        }
+    }
+
+    /// Set where the event which is about to be rewritten came from, or
+    /// [`None`] when the [`Rewriter`][crate::Rewriter] is finishing.
+    pub(crate) fn set_current(&mut self, current: Option<Provenance>) {
+        self.current = current;
+        self.passed_through = false;
+    }
+
+    fn cause(&self) -> Option<Range<usize>> {
+        self.current
+            .as_ref()
+            .and_then(Provenance::cause)
+            .cloned()
+    }
 }
 
 impl<'a> Extend<Event<'a>> for Writer<'a> {
     fn extend<I: IntoIterator<Item = Event<'a>>>(&mut self, iter: I) {
-        self.buffer.extend(iter);
+        for event in iter {
+            self.push(event);
+        }
     }
 }
@@ -0,0 +1,800 @@
+//! Rewrite rules loaded from a `markedit.toml` file.
+
+use crate::{Always, CodeBlock, Heading, Matcher, Pipeline};
+use pulldown_cmark::Event;
+use std::{
+    collections::{BTreeMap, HashSet},
+    convert::TryFrom,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+const RULE_TYPES: &[&str] = &[
+    "insert_markdown_before",
+    "change_text",
+    "replace_region",
+    "shift_headings",
+    "substitute",
+    "sort_lists",
+    "dedup_lists",
+    "renumber_lists",
+    "check_tasks",
+    "uncheck_tasks",
+    "remove_completed_tasks",
+    "move_completed_tasks_to_bottom",
+    "renumber_footnotes",
+    "to_reference_links",
+    "to_inline_links",
+    "swap_domain",
+    "rebase_relative",
+    "md_to_html",
+];
+
+const MATCHER_TYPES: &[&str] = &[
+    "always",
+    "heading",
+    "exact_text",
+    "text_containing",
+    "code_containing",
+    "link_with_url_containing",
+    "task_item",
+    "code_block",
+    "and",
+    "falling_edge",
+    "fuse",
+    "then_start_of_next_line",
+];
+
+/// A set of rewrite rules, normally loaded from a `markedit.toml` file.
+///
+/// Each `[[rule]]` table names one of the existing [`Rewriter`]s with its
+/// `type` field, and rules which need a [`Matcher`] take it as an inline
+/// table with its own `type` (`heading`, `exact_text`, `text_containing`,
+/// `link_with_url_containing`, etc.). The combinators `and`, `falling_edge`,
+/// `fuse` and `then_start_of_next_line` wrap other matchers.
+///
+/// The rules are applied in order, with each rule seeing the output of the
+/// one before it.
+///
+/// [`Rewriter`]: crate::Rewriter
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::Rules;
+///
+/// let rules = Rules::from_toml(r#"
+///     [[rule]]
+///     type = "insert_markdown_before"
+///     markdown = "*Generated, do not edit.*"
+///     matcher = { type = "falling_edge", matcher = { type = "heading", level = 1 } }
+///
+///     [[rule]]
+///     type = "change_text"
+///     matcher = { type = "text_containing", text = "TODO" }
+///     find = "TODO"
+///     replace = "FIXME"
+/// "#).unwrap();
+///
+/// let src = "# Title\n\nTODO: write docs\n";
+/// let rewritten = markedit::rewrite(markedit::parse(src), rules.to_rewriter());
+///
+/// assert_eq!(
+///     markedit::to_markdown(rewritten),
+///     "# Title\n\n*Generated, do not edit.*\n\nFIXME: write docs\n",
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Parse rules from a TOML document.
+    pub fn from_toml(src: &str) -> Result<Self, RuleError> {
+        let table: Table = src.parse().map_err(RuleError::Toml)?;
+        let mut fields = Fields::new(String::new(), &table);
+
+        let rules = match fields.optional("rule")? {
+            Some(Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let path = format!("rule[{}]", i);
+                    match item {
+                        Value::Table(table) => Rule::parse(path, table),
+                        _ => Err(RuleError::invalid(path, "a table")),
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => {
+                return Err(RuleError::invalid("rule", "an array of tables"))
+            },
+            None => Vec::new(),
+        };
+
+        fields.finish()?;
+
+        Ok(Rules { rules })
+    }
+
+    /// Read rules from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RuleError> {
+        let path = path.as_ref();
+        let src =
+            std::fs::read_to_string(path).map_err(|error| RuleError::Io {
+                path: path.to_path_buf(),
+                error,
+            })?;
+
+        Rules::from_toml(&src)
+    }
+
+    /// The number of rules.
+    pub fn len(&self) -> usize { self.rules.len() }
+
+    /// Are there any rules?
+    pub fn is_empty(&self) -> bool { self.rules.is_empty() }
+
+    /// Compile the rules into a [`Pipeline`].
+    ///
+    /// A lot of [`Matcher`]s are stateful, so you'll need a fresh
+    /// [`Pipeline`] for each document.
+    pub fn to_rewriter<'src>(&self) -> Pipeline<'src> {
+        let mut pipeline = Pipeline::new();
+
+        for rule in &self.rules {
+            rule.add_to(&mut pipeline);
+        }
+
+        pipeline
+    }
+}
+
+/// The reasons [`Rules`] may fail to load.
+#[derive(Debug)]
+pub enum RuleError {
+    /// The rule file couldn't be read.
+    Io {
+        /// The file being read.
+        path: PathBuf,
+        /// The underlying error.
+        error: io::Error,
+    },
+    /// The rule file isn't valid TOML.
+    Toml(toml::de::Error),
+    /// A rule's `type` isn't a known [`Rewriter`][crate::Rewriter].
+    UnknownRule {
+        /// Where the rule is (e.g. `rule[2].type`).
+        path: String,
+        /// The rule type that was requested.
+        name: String,
+    },
+    /// A matcher's `type` isn't a known [`Matcher`].
+    UnknownMatcher {
+        /// Where the matcher is (e.g. `rule[0].matcher.type`).
+        path: String,
+        /// The matcher type that was requested.
+        name: String,
+    },
+    /// A required field wasn't provided.
+    MissingField {
+        /// The missing field (e.g. `rule[1].markdown`).
+        path: String,
+    },
+    /// A field has the wrong type or an invalid value.
+    InvalidField {
+        /// The field (e.g. `rule[3].level`).
+        path: String,
+        /// What we were expecting.
+        expected: &'static str,
+    },
+    /// A field isn't used by this rule or matcher, which is normally a typo.
+    UnknownField {
+        /// The field (e.g. `rule[0].matchr`).
+        path: String,
+    },
+}
+
+impl RuleError {
+    fn invalid<P: Into<String>>(path: P, expected: &'static str) -> Self {
+        RuleError::InvalidField {
+            path: path.into(),
+            expected,
+        }
+    }
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Io { path, .. } => {
+                write!(f, "Unable to read \"{}\"", path.display())
+            },
+            RuleError::Toml(_) => write!(f, "Invalid TOML"),
+            RuleError::UnknownRule { path, name } => write!(
+                f,
+                "{}: unknown rule type \"{}\" (expected one of {})",
+                path,
+                name,
+                RULE_TYPES.join(", ")
+            ),
+            RuleError::UnknownMatcher { path, name } => write!(
+                f,
+                "{}: unknown matcher type \"{}\" (expected one of {})",
+                path,
+                name,
+                MATCHER_TYPES.join(", ")
+            ),
+            RuleError::MissingField { path } => {
+                write!(f, "{}: missing field", path)
+            },
+            RuleError::InvalidField { path, expected } => {
+                write!(f, "{}: expected {}", path, expected)
+            },
+            RuleError::UnknownField { path } => {
+                write!(f, "{}: unknown field", path)
+            },
+        }
+    }
+}
+
+impl Error for RuleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RuleError::Io { error, .. } => Some(error),
+            RuleError::Toml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A single validated `[[rule]]`.
+#[derive(Debug, Clone, PartialEq)]
+enum Rule {
+    InsertMarkdownBefore {
+        markdown: String,
+        matcher: MatcherSpec,
+    },
+    ChangeText {
+        matcher: MatcherSpec,
+        find: String,
+        replace: String,
+    },
+    ReplaceRegion {
+        name: String,
+        markdown: String,
+    },
+    ShiftHeadings {
+        by: i32,
+    },
+    Substitute {
+        variables: BTreeMap<String, String>,
+    },
+    SortLists(MatcherSpec),
+    DedupLists(MatcherSpec),
+    RenumberLists {
+        matcher: MatcherSpec,
+        start: u64,
+    },
+    CheckTasks(MatcherSpec),
+    UncheckTasks(MatcherSpec),
+    RemoveCompletedTasks,
+    MoveCompletedTasksToBottom,
+    RenumberFootnotes,
+    ToReferenceLinks,
+    ToInlineLinks,
+    SwapDomain {
+        from: String,
+        to: String,
+    },
+    RebaseRelative {
+        base: String,
+    },
+    MdToHtml,
+}
+
+impl Rule {
+    fn parse(path: String, table: &Table) -> Result<Self, RuleError> {
+        let mut fields = Fields::new(path, table);
+        let kind = fields.string("type")?;
+
+        let rule = match kind.as_str() {
+            "insert_markdown_before" => Rule::InsertMarkdownBefore {
+                markdown: fields.string("markdown")?,
+                matcher: fields.matcher("matcher")?,
+            },
+            "change_text" => Rule::ChangeText {
+                matcher: fields.optional_matcher("matcher")?,
+                find: fields.string("find")?,
+                replace: fields.string("replace")?,
+            },
+            "replace_region" => Rule::ReplaceRegion {
+                name: fields.string("name")?,
+                markdown: fields.string("markdown")?,
+            },
+            "shift_headings" => Rule::ShiftHeadings {
+                by: fields.integer("by")?,
+            },
+            "substitute" => Rule::Substitute {
+                variables: fields.string_table("variables")?,
+            },
+            "sort_lists" => {
+                Rule::SortLists(fields.optional_matcher("matcher")?)
+            },
+            "dedup_lists" => {
+                Rule::DedupLists(fields.optional_matcher("matcher")?)
+            },
+            "renumber_lists" => Rule::RenumberLists {
+                matcher: fields.optional_matcher("matcher")?,
+                start: fields.optional_integer("start")?.unwrap_or(1),
+            },
+            "check_tasks" => Rule::CheckTasks(fields.matcher("matcher")?),
+            "uncheck_tasks" => Rule::UncheckTasks(fields.matcher("matcher")?),
+            "remove_completed_tasks" => Rule::RemoveCompletedTasks,
+            "move_completed_tasks_to_bottom" => {
+                Rule::MoveCompletedTasksToBottom
+            },
+            "renumber_footnotes" => Rule::RenumberFootnotes,
+            "to_reference_links" => Rule::ToReferenceLinks,
+            "to_inline_links" => Rule::ToInlineLinks,
+            "swap_domain" => Rule::SwapDomain {
+                from: fields.string("from")?,
+                to: fields.string("to")?,
+            },
+            "rebase_relative" => Rule::RebaseRelative {
+                base: fields.string("base")?,
+            },
+            "md_to_html" => Rule::MdToHtml,
+            _ => {
+                return Err(RuleError::UnknownRule {
+                    path: fields.path("type"),
+                    name: kind,
+                })
+            },
+        };
+
+        fields.finish()?;
+        Ok(rule)
+    }
+
+    fn add_to<'src>(&self, pipeline: &mut Pipeline<'src>) {
+        match self {
+            Rule::InsertMarkdownBefore { markdown, matcher } => {
+                pipeline.push(crate::insert_markdown_before(
+                    markdown.clone(),
+                    matcher.build(),
+                ))
+            },
+            Rule::ChangeText {
+                matcher,
+                find,
+                replace,
+            } => {
+                let (find, replace) = (find.clone(), replace.clone());
+                pipeline.push(crate::change_matched_text(
+                    matcher.build(),
+                    move |text| text.replace(&find, &replace),
+                ));
+            },
+            Rule::ReplaceRegion { name, markdown } => {
+                pipeline.push(crate::replace_region(name.clone(), markdown))
+            },
+            Rule::ShiftHeadings { by } => {
+                pipeline.push(crate::shift_headings(*by))
+            },
+            Rule::Substitute { variables } => {
+                pipeline.push(crate::substitute(variables.clone()))
+            },
+            Rule::SortLists(matcher) => {
+                pipeline.push(crate::sort_lists(matcher.build()))
+            },
+            Rule::DedupLists(matcher) => {
+                pipeline.push(crate::dedup_lists(matcher.build()))
+            },
+            Rule::RenumberLists { matcher, start } => {
+                pipeline.push(crate::renumber_lists(matcher.build(), *start))
+            },
+            Rule::CheckTasks(matcher) => {
+                pipeline.push(crate::check_tasks(matcher.build()))
+            },
+            Rule::UncheckTasks(matcher) => {
+                pipeline.push(crate::uncheck_tasks(matcher.build()))
+            },
+            Rule::RemoveCompletedTasks => {
+                pipeline.push(crate::remove_completed_tasks())
+            },
+            Rule::MoveCompletedTasksToBottom => {
+                pipeline.push(crate::move_completed_tasks_to_bottom())
+            },
+            Rule::RenumberFootnotes => {
+                pipeline.push(crate::renumber_footnotes())
+            },
+            Rule::ToReferenceLinks => {
+                pipeline.push(crate::to_reference_links())
+            },
+            Rule::ToInlineLinks => pipeline.push(crate::to_inline_links()),
+            Rule::SwapDomain { from, to } => {
+                pipeline.push(crate::rewrite_links(crate::swap_domain(
+                    from.clone(),
+                    to.clone(),
+                )))
+            },
+            Rule::RebaseRelative { base } => pipeline.push(
+                crate::rewrite_links(crate::rebase_relative(base.clone())),
+            ),
+            Rule::MdToHtml => {
+                pipeline.push(crate::rewrite_links(crate::md_to_html()))
+            },
+        }
+    }
+}
+
+/// A validated description of a [`Matcher`].
+#[derive(Debug, Clone, PartialEq)]
+enum MatcherSpec {
+    Always,
+    Heading(Option<u32>),
+    ExactText(String),
+    TextContaining(String),
+    CodeContaining(String),
+    LinkWithUrlContaining(String),
+    TaskItem(Option<bool>),
+    CodeBlock(Option<String>),
+    And(Vec<MatcherSpec>),
+    FallingEdge(Box<MatcherSpec>),
+    Fuse(Box<MatcherSpec>),
+    ThenStartOfNextLine(Box<MatcherSpec>),
+}
+
+impl MatcherSpec {
+    fn parse(path: String, value: &Value) -> Result<Self, RuleError> {
+        let table = match value {
+            Value::Table(table) => table,
+            _ => return Err(RuleError::invalid(path, "a table")),
+        };
+        let mut fields = Fields::new(path, table);
+        let kind = fields.string("type")?;
+
+        let spec = match kind.as_str() {
+            "always" => MatcherSpec::Always,
+            "heading" => {
+                let level = fields.optional_integer("level")?;
+                if level.is_some_and(|l| !(1..=6).contains(&l)) {
+                    return Err(RuleError::invalid(
+                        fields.path("level"),
+                        "a heading level between 1 and 6",
+                    ));
+                }
+                MatcherSpec::Heading(level)
+            },
+            "exact_text" => MatcherSpec::ExactText(fields.string("text")?),
+            "text_containing" => {
+                MatcherSpec::TextContaining(fields.string("text")?)
+            },
+            "code_containing" => {
+                MatcherSpec::CodeContaining(fields.string("text")?)
+            },
+            "link_with_url_containing" => {
+                MatcherSpec::LinkWithUrlContaining(fields.string("url")?)
+            },
+            "task_item" => {
+                MatcherSpec::TaskItem(fields.optional_bool("checked")?)
+            },
+            "code_block" => {
+                MatcherSpec::CodeBlock(fields.optional_string("lang")?)
+            },
+            "and" => {
+                let path = fields.path("matchers");
+                let matchers = match fields.required("matchers")? {
+                    Value::Array(items) if !items.is_empty() => items
+                        .iter()
+                        .enumerate()
+                        .map(|(i, item)| {
+                            MatcherSpec::parse(format!("{}[{}]", path, i), item)
+                        })
+                        .collect::<Result<_, _>>()?,
+                    _ => {
+                        return Err(RuleError::invalid(
+                            path,
+                            "a non-empty array of matchers",
+                        ))
+                    },
+                };
+                MatcherSpec::And(matchers)
+            },
+            "falling_edge" => {
+                MatcherSpec::FallingEdge(Box::new(fields.matcher("matcher")?))
+            },
+            "fuse" => MatcherSpec::Fuse(Box::new(fields.matcher("matcher")?)),
+            "then_start_of_next_line" => MatcherSpec::ThenStartOfNextLine(
+                Box::new(fields.matcher("matcher")?),
+            ),
+            _ => {
+                return Err(RuleError::UnknownMatcher {
+                    path: fields.path("type"),
+                    name: kind,
+                })
+            },
+        };
+
+        fields.finish()?;
+        Ok(spec)
+    }
+
+    fn build(&self) -> DynMatcher {
+        let matcher: Box<dyn Matcher> = match self {
+            MatcherSpec::Always => Box::new(Always),
+            MatcherSpec::Heading(Some(level)) => {
+                Box::new(Heading::with_level(*level))
+            },
+            MatcherSpec::Heading(None) => Box::new(Heading::any_level()),
+            MatcherSpec::ExactText(text) => {
+                Box::new(crate::exact_text(text.clone()))
+            },
+            MatcherSpec::TextContaining(text) => {
+                Box::new(crate::text_containing(text.clone()))
+            },
+            MatcherSpec::CodeContaining(text) => {
+                Box::new(crate::code_containing(text.clone()))
+            },
+            MatcherSpec::LinkWithUrlContaining(url) => {
+                Box::new(crate::link_with_url_containing(url.clone()))
+            },
+            MatcherSpec::TaskItem(checked) => {
+                Box::new(crate::task_item(*checked))
+            },
+            MatcherSpec::CodeBlock(Some(lang)) => {
+                Box::new(CodeBlock::fenced_with_lang(lang.clone()))
+            },
+            MatcherSpec::CodeBlock(None) => Box::new(CodeBlock::any()),
+            MatcherSpec::And(matchers) => {
+                let mut built = matchers.iter().map(MatcherSpec::build);
+                let first = built.next().expect("Checked when parsing");
+                return built.fold(first, |left, right| {
+                    DynMatcher(Box::new(left.and(right)))
+                });
+            },
+            MatcherSpec::FallingEdge(inner) => {
+                Box::new(inner.build().falling_edge())
+            },
+            MatcherSpec::Fuse(inner) => Box::new(inner.build().fuse()),
+            MatcherSpec::ThenStartOfNextLine(inner) => {
+                Box::new(inner.build().then_start_of_next_line())
+            },
+        };
+
+        DynMatcher(matcher)
+    }
+}
+
+/// A boxed [`Matcher`].
+struct DynMatcher(Box<dyn Matcher>);
+
+impl Matcher for DynMatcher {
+    fn matches_event(&mut self, event: &Event<'_>) -> bool {
+        self.0.matches_event(event)
+    }
+}
+
+/// A helper for reading fields from a table, keeping track of where we are
+/// so errors can point at the offending field.
+struct Fields<'a> {
+    path: String,
+    table: &'a Table,
+    used: HashSet<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    fn new(path: String, table: &'a Table) -> Self {
+        Fields {
+            path,
+            table,
+            used: HashSet::new(),
+        }
+    }
+
+    fn path(&self, field: &str) -> String {
+        if self.path.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", self.path, field)
+        }
+    }
+
+    fn optional(
+        &mut self,
+        field: &'a str,
+    ) -> Result<Option<&'a Value>, RuleError> {
+        self.used.insert(field);
+        Ok(self.table.get(field))
+    }
+
+    fn required(&mut self, field: &'a str) -> Result<&'a Value, RuleError> {
+        self.optional(field)?
+            .ok_or_else(|| RuleError::MissingField {
+                path: self.path(field),
+            })
+    }
+
+    fn optional_string(
+        &mut self,
+        field: &'a str,
+    ) -> Result<Option<String>, RuleError> {
+        match self.optional(field)? {
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(RuleError::invalid(self.path(field), "a string")),
+            None => Ok(None),
+        }
+    }
+
+    fn string(&mut self, field: &'a str) -> Result<String, RuleError> {
+        self.required(field)?;
+        Ok(self.optional_string(field)?.expect("Checked above"))
+    }
+
+    fn optional_integer<T>(
+        &mut self,
+        field: &'a str,
+    ) -> Result<Option<T>, RuleError>
+    where
+        T: TryFrom<i64>,
+    {
+        match self.optional(field)? {
+            Some(Value::Integer(i)) => {
+                T::try_from(*i).map(Some).map_err(|_| {
+                    RuleError::invalid(self.path(field), "an integer in range")
+                })
+            },
+            Some(_) => Err(RuleError::invalid(self.path(field), "an integer")),
+            None => Ok(None),
+        }
+    }
+
+    fn integer<T>(&mut self, field: &'a str) -> Result<T, RuleError>
+    where
+        T: TryFrom<i64>,
+    {
+        self.required(field)?;
+        Ok(self.optional_integer(field)?.expect("Checked above"))
+    }
+
+    fn optional_bool(
+        &mut self,
+        field: &'a str,
+    ) -> Result<Option<bool>, RuleError> {
+        match self.optional(field)? {
+            Some(Value::Boolean(b)) => Ok(Some(*b)),
+            Some(_) => Err(RuleError::invalid(self.path(field), "a boolean")),
+            None => Ok(None),
+        }
+    }
+
+    fn string_table(
+        &mut self,
+        field: &'a str,
+    ) -> Result<BTreeMap<String, String>, RuleError> {
+        let path = self.path(field);
+
+        match self.required(field)? {
+            Value::Table(table) => table
+                .iter()
+                .map(|(key, value)| match value {
+                    Value::String(s) => Ok((key.clone(), s.clone())),
+                    _ => Err(RuleError::invalid(
+                        format!("{}.{}", path, key),
+                        "a string",
+                    )),
+                })
+                .collect(),
+            _ => Err(RuleError::invalid(path, "a table of strings")),
+        }
+    }
+
+    fn optional_matcher(
+        &mut self,
+        field: &'a str,
+    ) -> Result<MatcherSpec, RuleError> {
+        match self.optional(field)? {
+            Some(value) => MatcherSpec::parse(self.path(field), value),
+            None => Ok(MatcherSpec::Always),
+        }
+    }
+
+    fn matcher(&mut self, field: &'a str) -> Result<MatcherSpec, RuleError> {
+        let value = self.required(field)?;
+        MatcherSpec::parse(self.path(field), value)
+    }
+
+    /// Make sure there weren't any fields we didn't use.
+    fn finish(self) -> Result<(), RuleError> {
+        match self
+            .table
+            .keys()
+            .find(|key| !self.used.contains(key.as_str()))
+        {
+            Some(key) => Err(RuleError::UnknownField {
+                path: self.path(key),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_message(src: &str) -> String {
+        Rules::from_toml(src).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_field() {
+        let inputs = vec![
+            (
+                "[[rule]]\ntype = \"frobnicate\"",
+                "rule[0].type: unknown rule type \"frobnicate\"",
+            ),
+            (
+                "[[rule]]\ntype = \"md_to_html\"\n[[rule]]\ntype = \"insert_markdown_before\"\nmatcher = { type = \"always\" }",
+                "rule[1].markdown: missing field",
+            ),
+            (
+                "[[rule]]\ntype = \"sort_lists\"\nmatcher = { type = \"and\", matchers = [{ type = \"heading\", level = 7 }] }",
+                "rule[0].matcher.matchers[0].level: expected a heading level between 1 and 6",
+            ),
+            (
+                "[[rule]]\ntype = \"check_tasks\"\nmatcher = { type = \"fuse\", matcher = { type = \"nope\" } }",
+                "rule[0].matcher.matcher.type: unknown matcher type \"nope\"",
+            ),
+            (
+                "[[rule]]\ntype = \"shift_headings\"\nby = \"one\"",
+                "rule[0].by: expected an integer",
+            ),
+            (
+                "[[rule]]\ntype = \"md_to_html\"\nmatchr = { type = \"always\" }",
+                "rule[0].matchr: unknown field",
+            ),
+            ("rules = []", "rules: unknown field"),
+        ];
+
+        for (src, expected) in inputs {
+            let got = error_message(src);
+            assert!(got.starts_with(expected), "{:?} != {:?}", got, expected);
+        }
+    }
+
+    #[test]
+    fn rules_are_applied_in_order() {
+        let rules = Rules::from_toml(
+            r#"
+            [[rule]]
+            type = "substitute"
+            variables = { version = "1.2.3" }
+
+            [[rule]]
+            type = "shift_headings"
+            by = 1
+
+            [[rule]]
+            type = "renumber_lists"
+            start = 1
+            matcher = { type = "then_start_of_next_line", matcher = { type = "text_containing", text = "Version" } }
+            "#,
+        )
+        .unwrap();
+        let src = "# Version {{version}}\n\n3. a\n4. b\n\nText\n\n7. c\n";
+
+        let rewritten = crate::rewrite(crate::parse(src), rules.to_rewriter());
+
+        assert_eq!(rules.len(), 3);
+        assert_eq!(
+            crate::to_markdown(rewritten),
+            "## Version 1.2.3\n\n1. a\n2. b\n\nText\n\n7. c\n"
+        );
+    }
+}
@@ -0,0 +1,702 @@
+//! [`Rewriter`]s written as [Rhai](https://rhai.rs/) scripts.
+
+use crate::{
+    rewriters::owned_event, CodeBlock, Heading, Matcher, Rewriter, Writer,
+};
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, LinkType, Tag};
+use rhai::{
+    module_resolvers::DummyModuleResolver, CallFnOptions, Dynamic, Engine,
+    EvalAltResult, Map, Position, Scope, AST,
+};
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// The most operations a script may perform for a single event, so an
+/// infinite loop can't hang the program.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A [`Rewriter`] which calls a `rewrite_event(event, writer)` function
+/// defined in a [Rhai](https://rhai.rs/) script.
+///
+/// The script is sandboxed, so it can't read files, import modules or run
+/// forever, and anything it `print()`s goes to stderr.
+///
+/// # Scripts
+///
+/// A script may define the following functions,
+///
+/// - `rewrite_event(event, writer)` (required) - called for every [`Event`],
+///   pushing zero or more events to the `writer`
+/// - `init()` - called once when the script is loaded
+/// - `finish(writer)` - called after the last event, giving the script a chance
+///   to flush anything it was holding onto
+///
+/// Rhai functions can't see variables from the rest of the script, so any
+/// state that needs to last between events should be stored on `this`, an
+/// object map which is shared by every call.
+///
+/// The following are available to scripts,
+///
+/// - Events have a `kind` (`"start"`, `"end"`, `"text"`, `"code"`, `"html"`,
+///   `"footnote_reference"`, `"soft_break"`, `"hard_break"`, `"rule"` or
+///   `"task_list_marker"`), plus a `tag`, `text` or `checked` property where it
+///   makes sense. The `text` can also be assigned to.
+/// - Events are created with `start(tag)`, `end(tag)`, `text(s)`, `code(s)`,
+///   `html(s)`, `footnote_reference(label)`, `soft_break()`, `hard_break()`,
+///   `rule()` and `task_list_marker(checked)`
+/// - Tags have a `name` (e.g. `"heading"` or `"block_quote"`), plus a `level`,
+///   `url`, `title`, `lang`, `start` or `label` property where it makes sense
+/// - Tags are created with `paragraph()`, `heading(level)`, `block_quote()`,
+///   `code_block(lang)`, `list()`, `ordered_list(start)`, `item()`,
+///   `footnote_definition(label)`, `emphasis()`, `strong()`, `strikethrough()`,
+///   `link(url, title)` and `image(url, title)`
+/// - Matchers are created with `match_always()`, `match_heading(level)`,
+///   `match_any_heading()`, `match_exact_text(s)`, `match_text_containing(s)`,
+///   `match_code_containing(s)`, `match_link_url_containing(s)`,
+///   `match_task_item(checked)` and `match_code_block(lang)`, combined with the
+///   `and(other)`, `falling_edge()`, `fuse()` and `then_start_of_next_line()`
+///   methods, and used with `matches(event)`
+/// - The writer has `push(event)`, `pass_through(event)` and
+///   `push_markdown(text)` methods, where `pass_through(event)` is used to
+///   emit the event being rewritten unchanged so it keeps its original span
+///
+/// # Errors
+///
+/// Any errors raised while rewriting are recorded in
+/// [`ScriptRewriter::errors()`] and the offending [`Event`] is passed
+/// through unchanged.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::ScriptRewriter;
+///
+/// let script = r#"
+///     fn init() {
+///         this.headings = match_any_heading();
+///     }
+///
+///     fn rewrite_event(event, writer) {
+///         if this.headings.matches(event) && event.kind == "text" {
+///             event.text = event.text.to_upper();
+///             writer.push(event);
+///         } else {
+///             writer.pass_through(event);
+///         }
+///
+///         if event.kind == "end" && event.tag.name == "heading" {
+///             writer.push_markdown("*Under construction*");
+///         }
+///     }
+/// "#;
+/// let rewriter = ScriptRewriter::compile(script).unwrap();
+///
+/// let src = "## Usage\n\nSome text.\n";
+/// let got = markedit::to_markdown(markedit::rewrite(markedit::parse(src), rewriter));
+///
+/// assert_eq!(got, "## USAGE\n\n*Under construction*\n\nSome text.\n");
+/// ```
+pub struct ScriptRewriter {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    this: Dynamic,
+    has_finish: bool,
+    errors: Vec<ScriptError>,
+}
+
+impl ScriptRewriter {
+    /// Compile a script, making sure it defines `rewrite_event()` and
+    /// calling its `init()` function.
+    pub fn compile(script: &str) -> Result<Self, ScriptError> {
+        let engine = sandboxed_engine();
+        let ast = engine.compile(script).map_err(|e| ScriptError::Compile {
+            line: e.1.line(),
+            message: e.0.to_string(),
+        })?;
+
+        let defines = |name: &str, params: usize| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == params)
+        };
+        if !defines("rewrite_event", 2) {
+            return Err(ScriptError::MissingRewriteEvent);
+        }
+        let has_init = defines("init", 0);
+        let has_finish = defines("finish", 1);
+
+        let mut rewriter = ScriptRewriter {
+            engine,
+            ast,
+            scope: Scope::new(),
+            this: Dynamic::from_map(Map::new()),
+            has_finish,
+            errors: Vec::new(),
+        };
+
+        // run any top-level statements so constants are available
+        rewriter
+            .engine
+            .run_ast_with_scope(&mut rewriter.scope, &rewriter.ast)
+            .map_err(|e| runtime_error(*e))?;
+
+        if has_init {
+            rewriter.call("init", ())?;
+        }
+
+        Ok(rewriter)
+    }
+
+    /// Read a script from a file and [`ScriptRewriter::compile()`] it.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ScriptError> {
+        let path = path.as_ref();
+        let script =
+            std::fs::read_to_string(path).map_err(|error| ScriptError::Io {
+                path: path.to_path_buf(),
+                error,
+            })?;
+
+        ScriptRewriter::compile(&script)
+    }
+
+    /// Any errors encountered while rewriting.
+    pub fn errors(&self) -> &[ScriptError] { &self.errors }
+
+    fn call(
+        &mut self,
+        name: &str,
+        args: impl rhai::FuncArgs,
+    ) -> Result<(), ScriptError> {
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+
+        self.engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut self.scope,
+                &self.ast,
+                name,
+                args,
+            )
+            .map(|_| ())
+            .map_err(|e| runtime_error(*e))
+    }
+}
+
+impl<'src> Rewriter<'src> for ScriptRewriter {
+    fn rewrite_event(&mut self, event: Event<'src>, writer: &mut Writer<'src>) {
+        let output = ScriptWriter::default();
+        let script_event = ScriptEvent(owned_event(event.clone()));
+
+        match self.call("rewrite_event", (script_event, output.clone())) {
+            Ok(()) => output.write_to(writer),
+            Err(e) => {
+                self.errors.push(e);
+                writer.pass_through(event);
+            },
+        }
+    }
+
+    fn finish(&mut self, writer: &mut Writer<'src>) {
+        if !self.has_finish {
+            return;
+        }
+
+        let output = ScriptWriter::default();
+
+        match self.call("finish", (output.clone(),)) {
+            Ok(()) => output.write_to(writer),
+            Err(e) => self.errors.push(e),
+        }
+    }
+}
+
+impl Debug for ScriptRewriter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptRewriter")
+            .field("this", &self.this)
+            .field("errors", &self.errors)
+            .finish()
+    }
+}
+
+/// Something went wrong while loading or running a script.
+#[derive(Debug)]
+pub enum ScriptError {
+    /// The script couldn't be read.
+    Io {
+        /// The file being read.
+        path: PathBuf,
+        /// The underlying error.
+        error: io::Error,
+    },
+    /// The script has a syntax error.
+    Compile {
+        /// The line the error was on, if known.
+        line: Option<usize>,
+        /// A description of the error.
+        message: String,
+    },
+    /// The script doesn't define a `rewrite_event(event, writer)` function.
+    MissingRewriteEvent,
+    /// An error was raised while the script was running.
+    Runtime {
+        /// The line the error was on, if known.
+        line: Option<usize>,
+        /// A description of the error.
+        message: String,
+    },
+}
+
+impl ScriptError {
+    /// The line in the script this error came from, if known.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            ScriptError::Compile { line, .. }
+            | ScriptError::Runtime { line, .. } => *line,
+            _ => None,
+        }
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io { path, .. } => {
+                write!(f, "Unable to read \"{}\"", path.display())
+            },
+            ScriptError::Compile { line, message }
+            | ScriptError::Runtime { line, message } => match line {
+                Some(line) => write!(f, "line {}: {}", line, message),
+                None => write!(f, "{}", message),
+            },
+            ScriptError::MissingRewriteEvent => write!(
+                f,
+                "The script doesn't define a \"rewrite_event(event, writer)\" function"
+            ),
+        }
+    }
+}
+
+impl Error for ScriptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScriptError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+fn runtime_error(error: EvalAltResult) -> ScriptError {
+    // errors inside a function are wrapped in an "error in function call"
+    // pointing at the caller, but we want the line that actually failed
+    let (error, position) = innermost(error, Position::NONE);
+
+    ScriptError::Runtime {
+        line: position.line(),
+        message: error.to_string(),
+    }
+}
+
+/// Unwrap nested errors, returning the innermost one and the deepest known
+/// position.
+fn innermost(
+    mut error: EvalAltResult,
+    outer: Position,
+) -> (EvalAltResult, Position) {
+    let position = match error.take_position() {
+        position if position.is_none() => outer,
+        position => position,
+    };
+
+    match error {
+        EvalAltResult::ErrorInFunctionCall(.., inner, _)
+        | EvalAltResult::ErrorInModule(.., inner, _) => {
+            innermost(*inner, position)
+        },
+        other => (other, position),
+    }
+}
+
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(64)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(1 << 20)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000)
+        .set_module_resolver(DummyModuleResolver::new())
+        .on_print(|text| eprintln!("{}", text))
+        .on_debug(|text, _, position| eprintln!("{:?}: {}", position, text));
+    engine.disable_symbol("eval");
+
+    register_events(&mut engine);
+    register_tags(&mut engine);
+    register_matchers(&mut engine);
+    register_writer(&mut engine);
+
+    engine
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// An [`Event`] as seen by a script.
+#[derive(Debug, Clone, PartialEq)]
+struct ScriptEvent(Event<'static>);
+
+/// A [`Tag`] as seen by a script.
+#[derive(Debug, Clone, PartialEq)]
+struct ScriptTag(Tag<'static>);
+
+/// A [`Writer`] which scripts can push events to.
+///
+/// Scripts receive their arguments by value, so the buffer is shared with
+/// the [`ScriptRewriter`]. Each event is stored alongside whether it was
+/// passed through.
+#[derive(Debug, Default, Clone)]
+struct ScriptWriter(Rc<RefCell<Vec<(Event<'static>, bool)>>>);
+
+impl ScriptWriter {
+    fn write_to<'src>(&self, writer: &mut Writer<'src>) {
+        for (event, passed_through) in self.0.borrow_mut().drain(..) {
+            if passed_through {
+                writer.pass_through(event);
+            } else {
+                writer.push(event);
+            }
+        }
+    }
+}
+
+/// A [`Matcher`] which scripts can store and call.
+///
+/// Like [`ScriptWriter`], clones share the same state.
+#[derive(Clone)]
+struct ScriptMatcher(Rc<RefCell<Box<dyn Matcher>>>);
+
+impl ScriptMatcher {
+    fn new<M: Matcher + 'static>(matcher: M) -> Self {
+        ScriptMatcher(Rc::new(RefCell::new(Box::new(matcher))))
+    }
+}
+
+impl Matcher for ScriptMatcher {
+    fn matches_event(&mut self, event: &Event<'_>) -> bool {
+        self.0.borrow_mut().matches_event(event)
+    }
+}
+
+fn cow(text: &str) -> CowStr<'static> { CowStr::from(text.to_string()) }
+
+fn string(text: &CowStr<'_>) -> Dynamic { Dynamic::from(text.to_string()) }
+
+fn register_events(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptEvent>("Event")
+        .register_fn("start", |tag: ScriptTag| ScriptEvent(Event::Start(tag.0)))
+        .register_fn("end", |tag: ScriptTag| ScriptEvent(Event::End(tag.0)))
+        .register_fn("text", |s: &str| ScriptEvent(Event::Text(cow(s))))
+        .register_fn("code", |s: &str| ScriptEvent(Event::Code(cow(s))))
+        .register_fn("html", |s: &str| ScriptEvent(Event::Html(cow(s))))
+        .register_fn("footnote_reference", |s: &str| {
+            ScriptEvent(Event::FootnoteReference(cow(s)))
+        })
+        .register_fn("soft_break", || ScriptEvent(Event::SoftBreak))
+        .register_fn("hard_break", || ScriptEvent(Event::HardBreak))
+        .register_fn("rule", || ScriptEvent(Event::Rule))
+        .register_fn("task_list_marker", |checked: bool| {
+            ScriptEvent(Event::TaskListMarker(checked))
+        })
+        .register_get("kind", |ev: &mut ScriptEvent| {
+            let kind = match ev.0 {
+                Event::Start(_) => "start",
+                Event::End(_) => "end",
+                Event::Text(_) => "text",
+                Event::Code(_) => "code",
+                Event::Html(_) => "html",
+                Event::FootnoteReference(_) => "footnote_reference",
+                Event::SoftBreak => "soft_break",
+                Event::HardBreak => "hard_break",
+                Event::Rule => "rule",
+                Event::TaskListMarker(_) => "task_list_marker",
+            };
+            kind.to_string()
+        })
+        .register_get("tag", |ev: &mut ScriptEvent| match &ev.0 {
+            Event::Start(tag) | Event::End(tag) => {
+                Dynamic::from(ScriptTag(tag.clone()))
+            },
+            _ => Dynamic::UNIT,
+        })
+        .register_get("text", |ev: &mut ScriptEvent| match &ev.0 {
+            Event::Text(s)
+            | Event::Code(s)
+            | Event::Html(s)
+            | Event::FootnoteReference(s) => string(s),
+            _ => Dynamic::UNIT,
+        })
+        .register_set(
+            "text",
+            |ev: &mut ScriptEvent, text: &str| -> ScriptResult<()> {
+                match &mut ev.0 {
+                    Event::Text(s)
+                    | Event::Code(s)
+                    | Event::Html(s)
+                    | Event::FootnoteReference(s) => {
+                        *s = cow(text);
+                        Ok(())
+                    },
+                    other => {
+                        Err(format!("{:?} events have no text", other).into())
+                    },
+                }
+            },
+        )
+        .register_get("checked", |ev: &mut ScriptEvent| match ev.0 {
+            Event::TaskListMarker(checked) => Dynamic::from(checked),
+            _ => Dynamic::UNIT,
+        })
+        .register_fn("==", |a: ScriptEvent, b: ScriptEvent| a == b)
+        .register_fn("!=", |a: ScriptEvent, b: ScriptEvent| a != b)
+        .register_fn("to_string", |ev: &mut ScriptEvent| format!("{:?}", ev.0))
+        .register_fn("to_debug", |ev: &mut ScriptEvent| format!("{:?}", ev.0));
+}
+
+fn register_tags(engine: &mut Engine) {
+    let link =
+        |url: &str, title: &str| (LinkType::Inline, cow(url), cow(title));
+
+    engine
+        .register_type_with_name::<ScriptTag>("Tag")
+        .register_fn("paragraph", || ScriptTag(Tag::Paragraph))
+        .register_fn("heading", |level: i64| -> ScriptResult<ScriptTag> {
+            match level {
+                1..=6 => Ok(ScriptTag(Tag::Heading(level as u32))),
+                _ => Err(format!("Invalid heading level, {}", level).into()),
+            }
+        })
+        .register_fn("block_quote", || ScriptTag(Tag::BlockQuote))
+        .register_fn("code_block", |lang: &str| {
+            ScriptTag(Tag::CodeBlock(CodeBlockKind::Fenced(cow(lang))))
+        })
+        .register_fn("list", || ScriptTag(Tag::List(None)))
+        .register_fn("ordered_list", |start: i64| -> ScriptResult<ScriptTag> {
+            if start < 0 {
+                return Err(format!("Invalid list start, {}", start).into());
+            }
+            Ok(ScriptTag(Tag::List(Some(start as u64))))
+        })
+        .register_fn("item", || ScriptTag(Tag::Item))
+        .register_fn("footnote_definition", |label: &str| {
+            ScriptTag(Tag::FootnoteDefinition(cow(label)))
+        })
+        .register_fn("emphasis", || ScriptTag(Tag::Emphasis))
+        .register_fn("strong", || ScriptTag(Tag::Strong))
+        .register_fn("strikethrough", || ScriptTag(Tag::Strikethrough))
+        .register_fn("link", move |url: &str, title: &str| {
+            let (kind, url, title) = link(url, title);
+            ScriptTag(Tag::Link(kind, url, title))
+        })
+        .register_fn("image", move |url: &str, title: &str| {
+            let (kind, url, title) = link(url, title);
+            ScriptTag(Tag::Image(kind, url, title))
+        })
+        .register_get("name", |tag: &mut ScriptTag| {
+            let name = match tag.0 {
+                Tag::Paragraph => "paragraph",
+                Tag::Heading(_) => "heading",
+                Tag::BlockQuote => "block_quote",
+                Tag::CodeBlock(_) => "code_block",
+                Tag::List(_) => "list",
+                Tag::Item => "item",
+                Tag::FootnoteDefinition(_) => "footnote_definition",
+                Tag::Table(_) => "table",
+                Tag::TableHead => "table_head",
+                Tag::TableRow => "table_row",
+                Tag::TableCell => "table_cell",
+                Tag::Emphasis => "emphasis",
+                Tag::Strong => "strong",
+                Tag::Strikethrough => "strikethrough",
+                Tag::Link(..) => "link",
+                Tag::Image(..) => "image",
+            };
+            name.to_string()
+        })
+        .register_get("level", |tag: &mut ScriptTag| match tag.0 {
+            Tag::Heading(level) => Dynamic::from(i64::from(level)),
+            _ => Dynamic::UNIT,
+        })
+        .register_get("url", |tag: &mut ScriptTag| match &tag.0 {
+            Tag::Link(_, url, _) | Tag::Image(_, url, _) => string(url),
+            _ => Dynamic::UNIT,
+        })
+        .register_get("title", |tag: &mut ScriptTag| match &tag.0 {
+            Tag::Link(_, _, title) | Tag::Image(_, _, title) => string(title),
+            _ => Dynamic::UNIT,
+        })
+        .register_get("lang", |tag: &mut ScriptTag| match &tag.0 {
+            Tag::CodeBlock(CodeBlockKind::Fenced(info)) => {
+                let lang = info.split_whitespace().next().unwrap_or_default();
+                Dynamic::from(lang.to_string())
+            },
+            _ => Dynamic::UNIT,
+        })
+        .register_get("start", |tag: &mut ScriptTag| match tag.0 {
+            Tag::List(Some(start)) => Dynamic::from(start as i64),
+            _ => Dynamic::UNIT,
+        })
+        .register_get("label", |tag: &mut ScriptTag| match &tag.0 {
+            Tag::FootnoteDefinition(label) => string(label),
+            _ => Dynamic::UNIT,
+        })
+        .register_fn("==", |a: ScriptTag, b: ScriptTag| a == b)
+        .register_fn("!=", |a: ScriptTag, b: ScriptTag| a != b)
+        .register_fn("to_string", |tag: &mut ScriptTag| format!("{:?}", tag.0))
+        .register_fn("to_debug", |tag: &mut ScriptTag| format!("{:?}", tag.0));
+}
+
+fn register_matchers(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptMatcher>("Matcher")
+        .register_fn("match_always", || ScriptMatcher::new(crate::Always))
+        .register_fn("match_heading", |level: i64| {
+            ScriptMatcher::new(Heading::with_level(level as u32))
+        })
+        .register_fn("match_any_heading", || {
+            ScriptMatcher::new(Heading::any_level())
+        })
+        .register_fn("match_exact_text", |text: String| {
+            ScriptMatcher::new(crate::exact_text(text))
+        })
+        .register_fn("match_text_containing", |text: String| {
+            ScriptMatcher::new(crate::text_containing(text))
+        })
+        .register_fn("match_code_containing", |text: String| {
+            ScriptMatcher::new(crate::code_containing(text))
+        })
+        .register_fn("match_link_url_containing", |url: String| {
+            ScriptMatcher::new(crate::link_with_url_containing(url))
+        })
+        .register_fn("match_task_item", |checked: bool| {
+            ScriptMatcher::new(crate::task_item(Some(checked)))
+        })
+        .register_fn("match_code_block", |lang: &str| {
+            ScriptMatcher::new(CodeBlock::fenced_with_lang(lang))
+        })
+        .register_fn("matches", |m: &mut ScriptMatcher, ev: ScriptEvent| {
+            m.matches_event(&ev.0)
+        })
+        .register_fn("and", |m: &mut ScriptMatcher, other: ScriptMatcher| {
+            ScriptMatcher::new(m.clone().and(other))
+        })
+        .register_fn("falling_edge", |m: &mut ScriptMatcher| {
+            ScriptMatcher::new(m.clone().falling_edge())
+        })
+        .register_fn("fuse", |m: &mut ScriptMatcher| {
+            ScriptMatcher::new(m.clone().fuse())
+        })
+        .register_fn("then_start_of_next_line", |m: &mut ScriptMatcher| {
+            ScriptMatcher::new(m.clone().then_start_of_next_line())
+        });
+}
+
+fn register_writer(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptWriter>("Writer")
+        .register_fn("push", |w: &mut ScriptWriter, ev: ScriptEvent| {
+            w.0.borrow_mut().push((ev.0, false));
+        })
+        .register_fn("pass_through", |w: &mut ScriptWriter, ev: ScriptEvent| {
+            w.0.borrow_mut().push((ev.0, true));
+        })
+        .register_fn("push_markdown", |w: &mut ScriptWriter, text: &str| {
+            w.0.borrow_mut()
+                .extend(crate::parse(text).map(|ev| (owned_event(ev), false)));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(script: &str, src: &str) -> (String, ScriptRewriter) {
+        let mut rewritten = ScriptRewriter::compile(script)
+            .unwrap()
+            .rewrite(crate::parse(src));
+        let got = crate::to_markdown(rewritten.by_ref());
+
+        (got, rewritten.into_rewriter())
+    }
+
+    #[test]
+    fn state_is_kept_on_this() {
+        let script = r#"
+            fn init() { this.count = 0; }
+
+            fn rewrite_event(event, writer) {
+                if event.kind == "text" {
+                    this.count += 1;
+                    event.text = `${this.count}: ${event.text}`;
+                }
+                writer.push(event);
+            }
+
+            fn finish(writer) {
+                writer.push(start(paragraph()));
+                writer.push(text(`${this.count} paragraphs`));
+                writer.push(end(paragraph()));
+            }
+        "#;
+
+        let (got, rewriter) = rewrite(script, "First\n\nSecond\n");
+
+        assert_eq!(got, "1: First\n\n2: Second\n\n2 paragraphs\n");
+        assert!(rewriter.errors().is_empty());
+    }
+
+    #[test]
+    fn runtime_errors_have_line_numbers() {
+        let script = "fn rewrite_event(event, writer) {\n    writer.push(event);\n    throw \"boom\";\n}\n";
+
+        let (got, rewriter) = rewrite(script, "Text\n");
+
+        // the original events are passed through unchanged
+        assert_eq!(got, "Text\n");
+        let errors = rewriter.errors();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].line(), Some(3));
+        assert_eq!(errors[0].to_string(), "line 3: Runtime error: boom");
+    }
+
+    #[test]
+    fn scripts_must_define_rewrite_event() {
+        let err = ScriptRewriter::compile("fn rewrite(event) {}").unwrap_err();
+        assert!(matches!(err, ScriptError::MissingRewriteEvent));
+
+        let err = ScriptRewriter::compile(
+            "fn rewrite_event(event, writer) {\n  let = 5;\n}",
+        )
+        .unwrap_err();
+        assert_eq!(err.line(), Some(2));
+    }
+
+    #[test]
+    fn infinite_loops_are_stopped() {
+        let script = "fn rewrite_event(event, writer) { loop {} }";
+
+        let (got, rewriter) = rewrite(script, "Text\n");
+
+        assert_eq!(got, "Text\n");
+        assert!(!rewriter.errors().is_empty());
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{Heading, Matcher};
+use pulldown_cmark::{Event, Tag};
+use std::{borrow::Borrow, collections::HashMap};
+
+/// Turn some heading text into the anchor GitHub would generate for it.
+///
+/// The text is lower-cased, punctuation is removed, and spaces are replaced
+/// with hyphens.
+///
+/// ```rust
+/// assert_eq!(markedit::slugify("Hello, World!"), "hello-world");
+/// assert_eq!(markedit::slugify("The `Matcher` trait"), "the-matcher-trait");
+/// assert_eq!(markedit::slugify("v0.3.1 - 2020-04-01"), "v031---2020-04-01");
+/// ```
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c.to_lowercase().collect::<String>())
+            } else if c.is_whitespace() {
+                Some(String::from("-"))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Get the anchor for each heading in a document, in the order they appear.
+///
+/// Duplicate headings get a numeric suffix so every anchor is unique (e.g.
+/// `examples`, `examples-1`, `examples-2`).
+///
+/// ```rust
+/// let src = "# Usage\n## Examples\n# API\n## Examples";
+///
+/// let slugs = markedit::heading_slugs(markedit::parse(src));
+///
+/// assert_eq!(slugs, vec!["usage", "examples", "api", "examples-1"]);
+/// ```
+pub fn heading_slugs<'src, I, E>(events: I) -> Vec<String>
+where
+    I: IntoIterator<Item = E>,
+    E: Borrow<Event<'src>>,
+{
+    let mut matcher = Heading::any_level();
+    let mut slugger = Slugger::default();
+    let mut slugs = Vec::new();
+    let mut text = String::new();
+
+    for event in events {
+        let event = event.borrow();
+
+        if !matcher.matches_event(event) {
+            continue;
+        }
+
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(t),
+            Event::End(Tag::Heading(_)) => {
+                slugs.push(slugger.slug(&text));
+                text.clear();
+            },
+            _ => {},
+        }
+    }
+
+    slugs
+}
+
+/// Generates unique slugs, the same way GitHub does for duplicate headings.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Slugger {
+    seen: HashMap<String, usize>,
+}
+
+impl Slugger {
+    pub(crate) fn slug(&mut self, text: &str) -> String {
+        let base = slugify(text);
+        let mut slug = base.clone();
+
+        while self.seen.contains_key(&slug) {
+            // Note: the counter is tracked against the base slug so
+            // "Foo", "Foo", "Foo 1" gives "foo", "foo-1", "foo-1-1"
+            let count = self.seen.entry(base.clone()).or_insert(0);
+            *count += 1;
+            slug = format!("{}-{}", base, count);
+        }
+
+        self.seen.insert(slug.clone(), 0);
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_slugs_are_unique() {
+        let mut slugger = Slugger::default();
+
+        let got: Vec<_> = vec!["Foo", "Foo", "Foo 1", "Foo"]
+            .into_iter()
+            .map(|text| slugger.slug(text))
+            .collect();
+
+        assert_eq!(got, vec!["foo", "foo-1", "foo-1-1", "foo-2"]);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::{markdown::escape_text, slugs::Slugger, Link, Pipeline};
+use pulldown_cmark::{Event, Tag};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    rc::Rc,
+};
+
+/// Break a document into one file per section, for tools like mdBook or a
+/// wiki.
+///
+/// A new [`Chunk`] is started at every heading with the given `level` or
+/// shallower, and anything before the first of those headings goes into its
+/// own chunk. Headings in each chunk are shifted so the chunk starts at h1,
+/// and `#anchor` links to a heading in another chunk are rewritten to point
+/// at that chunk's file.
+///
+/// # Examples
+///
+/// ```rust
+/// let src = "# Guide\n\nSee [usage](#usage).\n\n## Install\n\nRun the installer.\n\n## Usage\n\n### Examples\n\nBack to [installing](#install).\n";
+///
+/// let split = markedit::split_by_heading(markedit::parse(src), 2);
+///
+/// let files: Vec<_> = split.chunks.iter().map(|c| c.file_name()).collect();
+/// assert_eq!(files, &["guide.md", "install.md", "usage.md"]);
+///
+/// assert_eq!(
+///     split.chunks[0].to_markdown(),
+///     "# Guide\n\nSee [usage](usage.md#usage).\n",
+/// );
+/// assert_eq!(
+///     split.chunks[2].to_markdown(),
+///     "# Usage\n\n## Examples\n\nBack to [installing](install.md#install).\n",
+/// );
+///
+/// assert_eq!(
+///     split.summary(),
+///     "- [Guide](guide.md)\n  - [Install](install.md)\n  - [Usage](usage.md)\n",
+/// );
+/// ```
+pub fn split_by_heading<'src, I>(events: I, level: u32) -> Split<'src>
+where
+    I: IntoIterator<Item = Event<'src>>,
+{
+    let events: Vec<Event<'src>> = events.into_iter().collect();
+    let sections = sections(&events, level);
+
+    let mut file_slugs = Slugger::default();
+    let mut document_slugs = Slugger::default();
+    // maps each anchor in the original document to its chunk and the anchor
+    // it'll have inside that chunk
+    let mut anchors = HashMap::new();
+    let mut chunks = Vec::new();
+
+    for (ix, section) in sections.iter().enumerate() {
+        let headings = headings(section);
+        let mut chunk_slugs = Slugger::default();
+
+        for (_, text) in &headings {
+            anchors.insert(
+                document_slugs.slug(text),
+                (ix, chunk_slugs.slug(text)),
+            );
+        }
+
+        let (heading_level, title) = match section.first() {
+            Some(Event::Start(Tag::Heading(level))) => {
+                (*level, headings[0].1.trim().to_string())
+            },
+            _ => (1, String::from("Introduction")),
+        };
+
+        // titles like "???" don't have a slug, so use the section's number
+        let slug = if crate::slugify(&title).is_empty() {
+            file_slugs.slug(&format!("section-{}", ix + 1))
+        } else {
+            file_slugs.slug(&title)
+        };
+
+        chunks.push(Chunk {
+            slug,
+            title,
+            level: heading_level,
+            events: Vec::new(),
+        });
+    }
+
+    let anchors = Rc::new(anchors);
+    let file_names: Rc<Vec<String>> =
+        Rc::new(chunks.iter().map(Chunk::file_name).collect());
+
+    for (ix, (chunk, section)) in chunks.iter_mut().zip(sections).enumerate() {
+        let anchors = Rc::clone(&anchors);
+        let file_names = Rc::clone(&file_names);
+
+        let rewriter = Pipeline::new()
+            .with(crate::shift_headings(1 - chunk.level as i32))
+            .with(crate::rewrite_links(move |link: &mut Link<'src>| {
+                let anchor = match link.url.strip_prefix('#') {
+                    Some(anchor) => anchor,
+                    None => return,
+                };
+
+                if let Some((target, slug)) = anchors.get(anchor) {
+                    link.url = if *target == ix {
+                        format!("#{}", slug).into()
+                    } else {
+                        format!("{}#{}", file_names[*target], slug).into()
+                    };
+                }
+            }));
+
+        chunk.events = crate::rewrite(section, rewriter).collect();
+    }
+
+    Split { chunks }
+}
+
+/// Break the events into sections, each starting with a heading at `level`
+/// or shallower.
+fn sections<'src>(events: &[Event<'src>], level: u32) -> Vec<Vec<Event<'src>>> {
+    let mut sections = Vec::new();
+    let mut current = Vec::new();
+
+    for event in events {
+        if let Event::Start(Tag::Heading(l)) = event {
+            if *l <= level && !current.is_empty() {
+                sections.push(std::mem::take(&mut current));
+            }
+        }
+
+        current.push(event.clone());
+    }
+
+    if !current.is_empty() {
+        sections.push(current);
+    }
+
+    sections
+}
+
+/// The level and text of every heading in a section.
+pub(crate) fn headings(events: &[Event<'_>]) -> Vec<(u32, String)> {
+    let mut headings = Vec::new();
+    let mut text: Option<String> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Heading(_)) => text = Some(String::new()),
+            Event::Text(t) | Event::Code(t) => {
+                if let Some(ref mut text) = text {
+                    text.push_str(t);
+                }
+            },
+            Event::End(Tag::Heading(level)) => {
+                headings.push((*level, text.take().unwrap_or_default()));
+            },
+            _ => {},
+        }
+    }
+
+    headings
+}
+
+/// A document which has been broken up by [`split_by_heading()`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Split<'src> {
+    /// Each section of the document, in order.
+    pub chunks: Vec<Chunk<'src>>,
+}
+
+impl<'src> Split<'src> {
+    /// A nested Markdown list linking to every chunk.
+    ///
+    /// Add a `# Summary` heading and this can be used as mdBook's
+    /// `SUMMARY.md`.
+    pub fn summary(&self) -> String { self.to_string() }
+}
+
+impl<'src> Display for Split<'src> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let top_level = self.chunks.iter().map(|c| c.level).min().unwrap_or(1);
+
+        for chunk in &self.chunks {
+            let indent = (chunk.level - top_level) as usize * 2;
+            writeln!(
+                f,
+                "{:indent$}- [{}]({})",
+                "",
+                escape_text(&chunk.title, false, false, false),
+                chunk.file_name(),
+                indent = indent
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A single section from [`split_by_heading()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk<'src> {
+    /// A unique name for the chunk, generated from its title (or its position,
+    /// if the title has no letters or numbers).
+    pub slug: String,
+    /// The text of the chunk's first heading, or `"Introduction"` for any
+    /// text before the first heading.
+    pub title: String,
+    /// The level the chunk's first heading had in the original document.
+    pub level: u32,
+    /// The chunk's contents, with headings shifted so it starts at h1.
+    pub events: Vec<Event<'src>>,
+}
+
+impl<'src> Chunk<'src> {
+    /// The file this chunk should be saved to (e.g. `installation.md`).
+    pub fn file_name(&self) -> String { format!("{}.md", self.slug) }
+
+    /// Render the chunk as Markdown.
+    pub fn to_markdown(&self) -> String { crate::to_markdown(&self.events) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_before_the_first_heading_is_an_introduction() {
+        let src = "Some intro.\n\n## Usage\n\nText\n";
+
+        let split = split_by_heading(crate::parse(src), 2);
+
+        assert_eq!(split.chunks.len(), 2);
+        assert_eq!(split.chunks[0].slug, "introduction");
+        assert_eq!(split.chunks[0].to_markdown(), "Some intro.\n");
+        assert_eq!(split.chunks[1].to_markdown(), "# Usage\n\nText\n");
+    }
+
+    #[test]
+    fn duplicate_headings_get_their_own_files_and_anchors() {
+        let src = "# Examples\n\n## Notes\n\n# Examples\n\n## Notes\n\nSee [the first notes](#notes) and [these notes](#notes-1).\n";
+
+        let split = split_by_heading(crate::parse(src), 1);
+
+        let files: Vec<_> =
+            split.chunks.iter().map(|c| c.file_name()).collect();
+        assert_eq!(files, &["examples.md", "examples-1.md"]);
+        // the second "Notes" heading is the first one in its own file
+        assert_eq!(
+            split.chunks[1].to_markdown(),
+            "# Examples\n\n## Notes\n\nSee [the first notes](examples.md#notes) and [these notes](#notes).\n"
+        );
+    }
+
+    #[test]
+    fn unknown_anchors_and_external_links_are_left_alone() {
+        let src = "# A\n\n[x](#missing) [y](https://example.com#a)\n";
+
+        let split = split_by_heading(crate::parse(src), 1);
+
+        assert_eq!(
+            split.chunks[0].to_markdown(),
+            "# A\n\n[x](#missing) [y](https://example.com#a)\n"
+        );
+    }
+
+    #[test]
+    fn titles_without_a_slug_use_the_section_number() {
+        let src = "# Intro\n\n# ???\n\n# [*Links*]\n";
+
+        let split = split_by_heading(crate::parse(src), 1);
+
+        let files: Vec<_> =
+            split.chunks.iter().map(|c| c.file_name()).collect();
+        assert_eq!(files, &["intro.md", "section-2.md", "links.md"]);
+        assert_eq!(
+            split.summary(),
+            "- [Intro](intro.md)\n- [???](section-2.md)\n- [\\[Links\\]](links.md)\n"
+        );
+    }
+}
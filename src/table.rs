@@ -0,0 +1,478 @@
+use pulldown_cmark::{Alignment, CowStr, Event, Tag};
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+/// The [`Event`]s making up a single table cell's contents (not including the
+/// [`Tag::TableCell`] start and end tags).
+pub type Cell<'src> = Vec<Event<'src>>;
+
+/// A GitHub-flavoured Markdown table.
+///
+/// Editing a table one [`Event`] at a time is painful, so a [`Table`] lets
+/// you work with the table's header and rows directly. You'll normally get
+/// one from [`rewrite_tables()`][crate::rewrite_tables], but they can also be
+/// parsed from [`Event`]s with [`Table::from_events()`] or created from
+/// scratch with [`Table::from_rows()`] and [`Table::from_csv()`].
+///
+/// Remember that tables are an extension to CommonMark, so you'll need to
+/// parse with [`Options::ENABLE_TABLES`][pulldown_cmark::Options].
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::{pulldown_cmark::Alignment, Table};
+///
+/// let mut table = Table::from_rows(vec![
+///     vec!["Name", "Version"],
+///     vec!["serde", "1.0.110"],
+///     vec!["pulldown-cmark", "0.7.1"],
+/// ]);
+///
+/// table.sort_by_column(0);
+/// table.insert_column(1, "Optional", Alignment::Center);
+///
+/// assert_eq!(
+///     markedit::to_markdown(table.into_events()),
+///     "| Name | Optional | Version |
+/// | --- | :-: | --- |
+/// | pulldown-cmark |  | 0.7.1 |
+/// | serde |  | 1.0.110 |
+/// ",
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table<'src> {
+    /// How each column is aligned.
+    pub alignments: Vec<Alignment>,
+    /// The cells in the table's header.
+    pub header: Vec<Cell<'src>>,
+    /// The table's body, where each row is a list of cells.
+    pub rows: Vec<Vec<Cell<'src>>>,
+}
+
+impl<'src> Table<'src> {
+    /// Parse a table from the [`Event`]s between (and including) a
+    /// [`Tag::Table`]'s start and end tags.
+    ///
+    /// Returns `None` if the [`Event`]s aren't a table.
+    ///
+    /// ```rust
+    /// use markedit::{pulldown_cmark::Options, Table};
+    ///
+    /// let src = "| Name | Age |\n| :--- | --: |\n| Alice | 30 |";
+    ///
+    /// let events = markedit::parse_with_options(src, Options::ENABLE_TABLES);
+    /// let table = Table::from_events(events).unwrap();
+    ///
+    /// assert_eq!(table.num_columns(), 2);
+    /// assert_eq!(table.column_index("Age"), Some(1));
+    /// assert_eq!(table.cell_text(0, 0).unwrap(), "Alice");
+    /// ```
+    pub fn from_events<I>(events: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Event<'src>>,
+    {
+        let mut events = events.into_iter();
+        let alignments = match events.next()? {
+            Event::Start(Tag::Table(alignments)) => alignments,
+            _ => return None,
+        };
+
+        let mut table = Table {
+            alignments,
+            header: Vec::new(),
+            rows: Vec::new(),
+        };
+        let mut in_header = false;
+        let mut current_cell: Option<Cell<'src>> = None;
+
+        for event in events {
+            match event {
+                Event::Start(Tag::TableHead) => in_header = true,
+                Event::End(Tag::TableHead) => in_header = false,
+                Event::Start(Tag::TableRow) => table.rows.push(Vec::new()),
+                Event::Start(Tag::TableCell) => current_cell = Some(Vec::new()),
+                Event::End(Tag::TableCell) => {
+                    let cell = current_cell.take()?;
+
+                    if in_header {
+                        table.header.push(cell);
+                    } else {
+                        table.rows.last_mut()?.push(cell);
+                    }
+                },
+                Event::End(Tag::TableRow) => {},
+                Event::End(Tag::Table(_)) => return Some(table),
+                other => current_cell.as_mut()?.push(other),
+            }
+        }
+
+        // we never saw the end of the table
+        None
+    }
+
+    /// Create a table where every cell is plain text, using the first row as
+    /// the header.
+    pub fn from_rows<R, C, S>(rows: R) -> Self
+    where
+        R: IntoIterator<Item = C>,
+        C: IntoIterator<Item = S>,
+        S: Into<CowStr<'src>>,
+    {
+        let mut rows = rows.into_iter().map(|row| {
+            row.into_iter()
+                .map(|cell| text_cell(cell.into()))
+                .collect::<Vec<_>>()
+        });
+        let header: Vec<_> = rows.next().unwrap_or_default();
+
+        Table {
+            alignments: vec![Alignment::None; header.len()],
+            header,
+            rows: rows.collect(),
+        }
+    }
+
+    /// The number of columns in this table.
+    ///
+    /// This is the width of the widest row, in case some rows are missing
+    /// cells.
+    pub fn num_columns(&self) -> usize {
+        self.rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(self.header.len()))
+            .chain(std::iter::once(self.alignments.len()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Find the column whose header contains exactly this text.
+    pub fn column_index(&self, header: &str) -> Option<usize> {
+        self.header
+            .iter()
+            .position(|cell| cell_text(cell).trim() == header)
+    }
+
+    /// Get the text inside a body cell, ignoring any formatting.
+    pub fn cell_text(&self, row: usize, column: usize) -> Option<String> {
+        self.rows
+            .get(row)
+            .and_then(|row| row.get(column))
+            .map(|cell| cell_text(cell))
+    }
+
+    /// Sort the table's body using a comparison function.
+    pub fn sort_rows_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&[Cell<'src>], &[Cell<'src>]) -> Ordering,
+    {
+        self.rows.sort_by(|left, right| compare(left, right));
+    }
+
+    /// Sort the table's body alphabetically by the text in a column.
+    pub fn sort_by_column(&mut self, column: usize) {
+        self.rows.sort_by_cached_key(|row| {
+            row.get(column)
+                .map(|cell| cell_text(cell))
+                .unwrap_or_default()
+        });
+    }
+
+    /// Insert a new column with an empty cell in each row.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `index` is greater than [`Table::num_columns()`].
+    pub fn insert_column<S>(
+        &mut self,
+        index: usize,
+        header: S,
+        alignment: Alignment,
+    ) where
+        S: Into<CowStr<'src>>,
+    {
+        assert!(index <= self.num_columns(), "Column index out of bounds");
+        self.pad_rows();
+
+        self.alignments.insert(index, alignment);
+        self.header.insert(index, text_cell(header.into()));
+
+        for row in &mut self.rows {
+            row.insert(index, Vec::new());
+        }
+    }
+
+    /// Remove a column from the table.
+    ///
+    /// # Panics
+    ///
+    /// This will panic if `index` is out of bounds.
+    pub fn remove_column(&mut self, index: usize) {
+        assert!(index < self.num_columns(), "Column index out of bounds");
+        self.pad_rows();
+
+        self.alignments.remove(index);
+        self.header.remove(index);
+
+        for row in &mut self.rows {
+            row.remove(index);
+        }
+    }
+
+    /// Iterate over every cell in the table's body, with its row and column
+    /// index.
+    pub fn cells_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (usize, usize, &mut Cell<'src>)> + '_ {
+        self.rows.iter_mut().enumerate().flat_map(|(row_ix, row)| {
+            row.iter_mut()
+                .enumerate()
+                .map(move |(column_ix, cell)| (row_ix, column_ix, cell))
+        })
+    }
+
+    /// Make sure every row has the same number of cells.
+    fn pad_rows(&mut self) {
+        let columns = self.num_columns();
+
+        self.alignments.resize(columns, Alignment::None);
+        self.header.resize(columns, Vec::new());
+
+        for row in &mut self.rows {
+            row.resize(columns, Vec::new());
+        }
+    }
+
+    /// Turn the table back into [`Event`]s.
+    ///
+    /// Any rows which are missing cells will be padded with empty ones.
+    pub fn into_events(mut self) -> Vec<Event<'src>> {
+        self.pad_rows();
+
+        let mut events = Vec::new();
+
+        events.push(Event::Start(Tag::Table(self.alignments.clone())));
+        events.push(Event::Start(Tag::TableHead));
+        push_cells(&mut events, self.header);
+        events.push(Event::End(Tag::TableHead));
+
+        for row in self.rows {
+            events.push(Event::Start(Tag::TableRow));
+            push_cells(&mut events, row);
+            events.push(Event::End(Tag::TableRow));
+        }
+
+        events.push(Event::End(Tag::Table(self.alignments)));
+
+        events
+    }
+}
+
+impl Table<'static> {
+    /// Create a table from comma-separated values, using the first record as
+    /// the header.
+    ///
+    /// Fields may be quoted (with `""` being an escaped quote), which lets
+    /// them contain commas and newlines. Newlines are turned into `<br>`
+    /// tags because table cells can't span multiple lines.
+    ///
+    /// ```rust
+    /// use markedit::Table;
+    ///
+    /// let csv = "Name,Description\nmarkedit,\"Edit Markdown, quickly\"\n";
+    ///
+    /// let table = Table::from_csv(csv).unwrap();
+    ///
+    /// assert_eq!(
+    ///     markedit::to_markdown(table.into_events()),
+    ///     "| Name | Description |\n| --- | --- |\n| markedit | Edit Markdown, quickly |\n",
+    /// );
+    /// ```
+    pub fn from_csv(csv: &str) -> Result<Self, CsvError> {
+        let records = parse_csv(csv)?;
+        Ok(Table::from_rows(records))
+    }
+}
+
+/// Get the text inside a cell, ignoring any formatting.
+fn cell_text(cell: &[Event<'_>]) -> String {
+    let mut text = String::new();
+
+    for event in cell {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(t),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            _ => {},
+        }
+    }
+
+    text
+}
+
+fn text_cell(text: CowStr<'_>) -> Cell<'_> {
+    if !text.contains('\n') {
+        return if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![Event::Text(text)]
+        };
+    }
+
+    // tables can't contain newlines, so fall back to HTML line breaks
+    let mut cell = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            cell.push(Event::Html("<br>".into()));
+        }
+        cell.push(Event::Text(line.to_string().into()));
+    }
+
+    cell
+}
+
+fn push_cells<'src>(events: &mut Vec<Event<'src>>, cells: Vec<Cell<'src>>) {
+    for cell in cells {
+        events.push(Event::Start(Tag::TableCell));
+        events.extend(cell);
+        events.push(Event::End(Tag::TableCell));
+    }
+}
+
+fn parse_csv(csv: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut chars = csv.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() => {
+                let starting_line = line;
+
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        },
+                        Some('"') => break,
+                        Some(other) => {
+                            if other == '\n' {
+                                line += 1;
+                            }
+                            field.push(other);
+                        },
+                        None => {
+                            return Err(CsvError {
+                                line: starting_line,
+                            })
+                        },
+                    }
+                }
+            },
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' => {
+                line += 1;
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            },
+            other => field.push(other),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// A quoted CSV field was never closed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CsvError {
+    /// The line the quoted field started on.
+    pub line: usize,
+}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Unterminated quote starting on line {}", self.line)
+    }
+}
+
+impl Error for CsvError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Options;
+
+    #[test]
+    fn tables_round_trip_through_events() {
+        let src = "| Name | *Description* |
+| :-- | --: |
+| `markedit` | Edit **Markdown** |
+| short |
+";
+        let events: Vec<_> =
+            crate::parse_with_options(src, Options::ENABLE_TABLES).collect();
+
+        let table = Table::from_events(events.clone()).unwrap();
+
+        assert_eq!(table.alignments, vec![Alignment::Left, Alignment::Right]);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.cell_text(0, 0).unwrap(), "markedit");
+        assert_eq!(
+            crate::to_markdown(table.into_events()),
+            "| Name | *Description* |
+| :-- | --: |
+| `markedit` | Edit **Markdown** |
+| short |  |
+"
+        );
+    }
+
+    #[test]
+    fn parse_csv_records() {
+        let csv = "a,b,c\r\n1,\"two, \"\"quoted\"\"\",\n\"multi\nline\",,3";
+
+        let got = parse_csv(csv).unwrap();
+
+        assert_eq!(
+            got,
+            vec![
+                vec!["a", "b", "c"],
+                vec!["1", "two, \"quoted\"", ""],
+                vec!["multi\nline", "", "3"],
+            ]
+        );
+        assert_eq!(
+            parse_csv("a,b\n1,\"oops").unwrap_err(),
+            CsvError { line: 2 }
+        );
+    }
+
+    #[test]
+    fn remove_a_column() {
+        let mut table = Table::from_rows(vec![
+            vec!["a", "b", "c"],
+            vec!["1", "2", "3"],
+            vec!["4"],
+        ]);
+
+        table.remove_column(1);
+
+        assert_eq!(
+            crate::to_markdown(table.into_events()),
+            "| a | c |\n| --- | --- |\n| 1 | 3 |\n| 4 |  |\n"
+        );
+    }
+}
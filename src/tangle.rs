@@ -0,0 +1,320 @@
+use crate::InfoString;
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
+
+/// Extract the code blocks from a literate Markdown document into files.
+///
+/// A fenced code block is written to a file when its info string has a
+/// `file=...` attribute (e.g. ` ```rust file=src/main.rs `), or when it comes
+/// straight after a `<!-- file: src/main.rs -->` comment. Blocks with the same
+/// target are concatenated in the order they appear.
+///
+/// Code blocks with a `name=...` attribute define a named chunk, and any line
+/// consisting of just `<<name>>` will be replaced by that chunk's contents
+/// (keeping the line's indentation). Chunks may be referenced before they are
+/// defined and may refer to other chunks, and defining the same chunk more
+/// than once appends to it.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// let src = "The entrypoint:
+///
+/// ~~~rust file=src/main.rs
+/// fn main() {
+///     <<greeting>>
+/// }
+/// ~~~
+///
+/// Which prints a greeting:
+///
+/// ~~~rust name=greeting
+/// println!(\"Hello, World!\");
+/// ~~~
+///
+/// <!-- file: README.txt -->
+/// ~~~
+/// A tangled example.
+/// ~~~
+/// ";
+///
+/// let files = markedit::tangle(markedit::parse(src)).unwrap();
+///
+/// assert_eq!(files.len(), 2);
+/// assert_eq!(
+///     files[Path::new("src/main.rs")],
+///     "fn main() {\n    println!(\"Hello, World!\");\n}\n",
+/// );
+/// assert_eq!(files[Path::new("README.txt")], "A tangled example.\n");
+/// ```
+pub fn tangle<'src, I, E>(
+    events: I,
+) -> Result<BTreeMap<PathBuf, String>, TangleError>
+where
+    I: IntoIterator<Item = E>,
+    E: Borrow<Event<'src>>,
+{
+    let (files, chunks) = collect_blocks(events);
+
+    files
+        .into_iter()
+        .map(|(path, code)| {
+            let mut contents = String::new();
+            expand_into(&code, "", &chunks, &mut Vec::new(), &mut contents)?;
+            Ok((path, contents))
+        })
+        .collect()
+}
+
+/// The code block currently being read, and where its contents should go.
+#[derive(Debug, Default)]
+struct Block {
+    file: Option<PathBuf>,
+    name: Option<String>,
+    code: String,
+}
+
+fn collect_blocks<'src, I, E>(
+    events: I,
+) -> (BTreeMap<PathBuf, String>, HashMap<String, String>)
+where
+    I: IntoIterator<Item = E>,
+    E: Borrow<Event<'src>>,
+{
+    let mut files: BTreeMap<PathBuf, String> = BTreeMap::new();
+    let mut chunks: HashMap<String, String> = HashMap::new();
+    let mut file_from_comment = None;
+    let mut current_block: Option<Block> = None;
+
+    for event in events {
+        match event.borrow() {
+            Event::Html(html) => {
+                if let Some(path) = file_comment(html) {
+                    file_from_comment = Some(path);
+                }
+            },
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let info = InfoString::parse(info);
+                let file = info.get("file").map(PathBuf::from);
+
+                current_block = Some(Block {
+                    file: file.or_else(|| file_from_comment.take()),
+                    name: info.get("name").map(String::from),
+                    code: String::new(),
+                });
+            },
+            Event::Start(_) | Event::Rule => {
+                // a file comment only applies to the block straight after
+                // it, and indented code blocks can't be tangled
+                file_from_comment = None;
+            },
+            Event::Text(text) => {
+                if let Some(ref mut block) = current_block {
+                    block.code.push_str(text);
+                }
+            },
+            Event::End(Tag::CodeBlock(_)) => {
+                if let Some(block) = current_block.take() {
+                    if let Some(name) = block.name {
+                        chunks.entry(name).or_default().push_str(&block.code);
+                    }
+                    if let Some(file) = block.file {
+                        files.entry(file).or_default().push_str(&block.code);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    (files, chunks)
+}
+
+/// Parse a `<!-- file: path -->` comment.
+fn file_comment(html: &str) -> Option<PathBuf> {
+    let comment = html.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    let path = comment.trim().strip_prefix("file:")?.trim();
+
+    if path.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// If this line is a `<<name>>` chunk reference, get its indentation and the
+/// chunk's name.
+fn chunk_reference(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim();
+    let name = trimmed.strip_prefix("<<")?.strip_suffix(">>")?.trim();
+
+    if name.is_empty() {
+        None
+    } else {
+        let indent = &line[..line.len() - line.trim_start().len()];
+        Some((indent, name))
+    }
+}
+
+fn expand_into(
+    code: &str,
+    indent: &str,
+    chunks: &HashMap<String, String>,
+    stack: &mut Vec<String>,
+    out: &mut String,
+) -> Result<(), TangleError> {
+    for line in code.lines() {
+        let (reference_indent, name) = match chunk_reference(line) {
+            Some(reference) => reference,
+            None => {
+                if !line.is_empty() {
+                    out.push_str(indent);
+                }
+                out.push_str(line);
+                out.push('\n');
+                continue;
+            },
+        };
+
+        if let Some(ix) = stack.iter().position(|n| n == name) {
+            let mut chunks = stack[ix..].to_vec();
+            chunks.push(name.to_string());
+            return Err(TangleError::Cycle { chunks });
+        }
+
+        let body =
+            chunks.get(name).ok_or_else(|| TangleError::UnknownChunk {
+                name: name.to_string(),
+            })?;
+
+        stack.push(name.to_string());
+        let indent = format!("{}{}", indent, reference_indent);
+        expand_into(body, &indent, chunks, stack, out)?;
+        stack.pop();
+    }
+
+    Ok(())
+}
+
+/// The reasons [`tangle()`] may fail.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TangleError {
+    /// A code block referred to a chunk which was never defined.
+    UnknownChunk {
+        /// The chunk's name.
+        name: String,
+    },
+    /// A chunk ended up referring to itself.
+    Cycle {
+        /// The chain of chunk references, starting and ending with the same
+        /// chunk.
+        chunks: Vec<String>,
+    },
+}
+
+impl Display for TangleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TangleError::UnknownChunk { name } => {
+                write!(f, "Unknown chunk, \"{}\"", name)
+            },
+            TangleError::Cycle { chunks } => write!(
+                f,
+                "The \"{}\" chunk refers to itself ({})",
+                chunks[0],
+                chunks.join(" -> ")
+            ),
+        }
+    }
+}
+
+impl Error for TangleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn blocks_for_the_same_file_are_concatenated() {
+        let src = r#"
+```rust file=src/lib.rs
+<<imports>>
+pub fn first() {}
+```
+
+Some prose.
+
+```rust file=src/lib.rs
+pub fn second() {}
+```
+
+```rust name=imports
+use std::fmt;
+```
+
+```rust name=imports
+use std::io;
+```
+"#;
+
+        let files = tangle(crate::parse(src)).unwrap();
+
+        assert_eq!(
+            files[Path::new("src/lib.rs")],
+            "use std::fmt;\nuse std::io;\npub fn first() {}\npub fn second() {}\n"
+        );
+    }
+
+    #[test]
+    fn detect_unknown_chunks_and_cycles() {
+        let unknown = "```text file=out.txt\n<<missing>>\n```";
+        let cycle = r#"
+```text file=out.txt
+<<a>>
+```
+
+```text name=a
+  <<b>>
+```
+
+```text name=b
+<<a>>
+```
+"#;
+
+        assert_eq!(
+            tangle(crate::parse(unknown)).unwrap_err(),
+            TangleError::UnknownChunk {
+                name: String::from("missing")
+            }
+        );
+        assert_eq!(
+            tangle(crate::parse(cycle)).unwrap_err(),
+            TangleError::Cycle {
+                chunks: vec![
+                    String::from("a"),
+                    String::from("b"),
+                    String::from("a")
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn file_comments_only_apply_to_the_next_block() {
+        let src = "<!-- file: out.rs -->\n\nSome prose.\n\n```rust\nfn main() {}\n```\n";
+
+        let files = tangle(crate::parse(src)).unwrap();
+
+        assert!(files.is_empty());
+    }
+}
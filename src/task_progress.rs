@@ -0,0 +1,207 @@
+use pulldown_cmark::{Event, Tag};
+use std::{
+    borrow::Borrow,
+    fmt::{self, Display, Formatter},
+};
+
+/// Count how many task list items have been completed, broken down by
+/// top-level list and by section.
+///
+/// A section starts at a heading (of any level) and continues until the next
+/// heading, with any tasks before the first heading belonging to a section
+/// without a heading. Lists and sections without any tasks are skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// use markedit::pulldown_cmark::Options;
+///
+/// let src = "# Backend\n\n- [x] Database\n- [ ] API\n  - [x] Authentication\n\n# Frontend\n\n- [ ] Design\n";
+///
+/// let events = markedit::parse_with_options(src, Options::ENABLE_TASKLISTS);
+/// let report = markedit::task_progress(events);
+///
+/// assert_eq!(report.total.to_string(), "2/4");
+/// assert_eq!(report.sections.len(), 2);
+/// assert_eq!(report.sections[0].heading.as_deref(), Some("Backend"));
+/// assert_eq!(report.sections[0].progress.to_string(), "2/3");
+/// assert_eq!(report.lists[1].section.as_deref(), Some("Frontend"));
+/// assert!(!report.lists[1].progress.is_complete());
+/// ```
+pub fn task_progress<'src, I, E>(events: I) -> TaskReport
+where
+    I: IntoIterator<Item = E>,
+    E: Borrow<Event<'src>>,
+{
+    let mut report = TaskReport::default();
+    let mut current_section = SectionProgress::default();
+    let mut heading_text: Option<String> = None;
+    let mut list_depth = 0_usize;
+
+    for event in events {
+        match event.borrow() {
+            Event::Start(Tag::Heading(_)) => heading_text = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(ref mut heading) = heading_text {
+                    heading.push_str(text);
+                }
+            },
+            Event::End(Tag::Heading(_)) => {
+                let previous = std::mem::replace(
+                    &mut current_section,
+                    SectionProgress {
+                        heading: heading_text.take(),
+                        progress: Progress::default(),
+                    },
+                );
+                report.push_section(previous);
+            },
+            Event::Start(Tag::List(_)) => {
+                if list_depth == 0 {
+                    report.lists.push(ListProgress {
+                        section: current_section.heading.clone(),
+                        progress: Progress::default(),
+                    });
+                }
+                list_depth += 1;
+            },
+            Event::End(Tag::List(_)) => {
+                list_depth = list_depth.saturating_sub(1);
+
+                if list_depth == 0
+                    && report
+                        .lists
+                        .last()
+                        .is_some_and(|l| l.progress.total == 0)
+                {
+                    report.lists.pop();
+                }
+            },
+            Event::TaskListMarker(checked) => {
+                let mut progress =
+                    vec![&mut report.total, &mut current_section.progress];
+                if let Some(list) = report.lists.last_mut() {
+                    progress.push(&mut list.progress);
+                }
+
+                for p in progress {
+                    p.add(*checked);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    report.push_section(current_section);
+
+    report
+}
+
+/// The results from [`task_progress()`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct TaskReport {
+    /// Progress for each top-level list containing tasks (including tasks in
+    /// nested lists), in the order they appear.
+    pub lists: Vec<ListProgress>,
+    /// Progress for each section containing tasks, in the order they appear.
+    pub sections: Vec<SectionProgress>,
+    /// Progress across the entire document.
+    pub total: Progress,
+}
+
+impl TaskReport {
+    fn push_section(&mut self, section: SectionProgress) {
+        if section.progress.total > 0 {
+            self.sections.push(section);
+        }
+    }
+}
+
+/// How many tasks in a single list have been completed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct ListProgress {
+    /// The heading for the section this list is in.
+    pub section: Option<String>,
+    /// The list's progress.
+    pub progress: Progress,
+}
+
+/// How many tasks under a heading have been completed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct SectionProgress {
+    /// The section's heading text, or `None` for tasks before the first
+    /// heading.
+    pub heading: Option<String>,
+    /// The section's progress.
+    pub progress: Progress,
+}
+
+/// The number of completed tasks, out of a total.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// The number of checked tasks.
+    pub completed: usize,
+    /// The total number of tasks.
+    pub total: usize,
+}
+
+impl Progress {
+    /// The number of tasks which still need to be done.
+    pub fn remaining(&self) -> usize { self.total - self.completed }
+
+    /// Have all the tasks been completed?
+    pub fn is_complete(&self) -> bool { self.completed == self.total }
+
+    fn add(&mut self, checked: bool) {
+        self.total += 1;
+        if checked {
+            self.completed += 1;
+        }
+    }
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.completed, self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::Options;
+
+    #[test]
+    fn tasks_before_the_first_heading_have_no_section() {
+        let src = "- [x] intro task\n- not a task\n\n## Empty\n\n- plain\n";
+
+        let report = task_progress(crate::parse_with_options(
+            src,
+            Options::ENABLE_TASKLISTS,
+        ));
+
+        assert_eq!(
+            report,
+            TaskReport {
+                lists: vec![ListProgress {
+                    section: None,
+                    progress: Progress {
+                        completed: 1,
+                        total: 1
+                    },
+                }],
+                sections: vec![SectionProgress {
+                    heading: None,
+                    progress: Progress {
+                        completed: 1,
+                        total: 1
+                    },
+                }],
+                total: Progress {
+                    completed: 1,
+                    total: 1
+                },
+            }
+        );
+    }
+}
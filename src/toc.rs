@@ -0,0 +1,131 @@
+use crate::slugs::Slugger;
+use pulldown_cmark::{Event, Tag};
+use std::{
+    borrow::Borrow,
+    fmt::{self, Display, Formatter},
+};
+
+/// Generate a table of contents from a document's headings.
+///
+/// Each entry links to the heading's anchor, using the same slugs as
+/// [`heading_slugs()`][crate::heading_slugs].
+///
+/// # Examples
+///
+/// ```rust
+/// let src = "# Title\n\n## Installation\n\n## Usage\n\n### Examples\n";
+///
+/// let toc = markedit::table_of_contents(markedit::parse(src)).min_level(2);
+///
+/// assert_eq!(
+///     toc.to_string(),
+///     "- [Installation](#installation)\n- [Usage](#usage)\n  - [Examples](#examples)\n",
+/// );
+/// ```
+pub fn table_of_contents<'src, I, E>(events: I) -> TableOfContents
+where
+    I: IntoIterator<Item = E>,
+    E: Borrow<Event<'src>>,
+{
+    let mut slugger = Slugger::default();
+    let mut entries = Vec::new();
+    let mut heading: Option<String> = None;
+
+    for event in events {
+        match event.borrow() {
+            Event::Start(Tag::Heading(_)) => heading = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(ref mut heading) = heading {
+                    heading.push_str(text);
+                }
+            },
+            Event::End(Tag::Heading(level)) => {
+                let text = heading.take().unwrap_or_default();
+                entries.push(TocEntry {
+                    level: *level,
+                    slug: slugger.slug(&text),
+                    text: text.trim().to_string(),
+                });
+            },
+            _ => {},
+        }
+    }
+
+    TableOfContents { entries }
+}
+
+/// The headings in a document, as returned by [`table_of_contents()`].
+///
+/// Use [`Display`] to render it as a nested Markdown list, where each
+/// heading's text is escaped so it can't break the link.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct TableOfContents {
+    /// Every heading, in the order they appear.
+    pub entries: Vec<TocEntry>,
+}
+
+impl TableOfContents {
+    /// Only keep headings at this level or deeper (e.g. `2` to skip the
+    /// document's title).
+    pub fn min_level(mut self, level: u32) -> Self {
+        self.entries.retain(|e| e.level >= level);
+        self
+    }
+
+    /// Only keep headings at this level or shallower.
+    pub fn max_level(mut self, level: u32) -> Self {
+        self.entries.retain(|e| e.level <= level);
+        self
+    }
+
+    /// Are there any headings?
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+}
+
+impl Display for TableOfContents {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let top_level = self.entries.iter().map(|e| e.level).min().unwrap_or(1);
+
+        for entry in &self.entries {
+            let indent = (entry.level - top_level) as usize * 2;
+            writeln!(
+                f,
+                "{:indent$}- [{}](#{})",
+                "",
+                crate::markdown::escape_text(&entry.text, false, false, false),
+                entry.slug,
+                indent = indent
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A single heading in a [`TableOfContents`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TocEntry {
+    /// The heading's level, from 1 to 6.
+    pub level: u32,
+    /// The heading's plain text.
+    pub text: String,
+    /// The heading's anchor, without the leading `#`.
+    pub slug: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_text_is_escaped() {
+        let src = "# The `[x]` *and* `*` syntax\n";
+
+        let toc = table_of_contents(crate::parse(src));
+
+        assert_eq!(
+            toc.to_string(),
+            "- [The \\[x\\] and \\* syntax](#the-x-and--syntax)\n"
+        );
+    }
+}
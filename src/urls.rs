@@ -0,0 +1,119 @@
+//! Small helpers for picking apart the URLs found in links and images.
+
+/// Does this URL start with a scheme (e.g. `https:` or `mailto:`)?
+pub(crate) fn has_scheme(url: &str) -> bool {
+    match url.find(':') {
+        Some(colon) => {
+            let scheme = &url[..colon];
+            !scheme.is_empty()
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| {
+                    c.is_ascii_alphanumeric()
+                        || c == '+'
+                        || c == '-'
+                        || c == '.'
+                })
+        },
+        None => false,
+    }
+}
+
+/// Is this a relative URL pointing at something in the same "tree" (e.g.
+/// `../README.md` or `images/logo.png`)?
+///
+/// Absolute paths, protocol-relative URLs and fragment-only links are not
+/// considered relative.
+pub(crate) fn is_relative(url: &str) -> bool {
+    !url.is_empty()
+        && !has_scheme(url)
+        && !url.starts_with('/')
+        && !url.starts_with('#')
+}
+
+/// Split a path into the bit before any `?query` or `#fragment`, and
+/// everything after it.
+pub(crate) fn split_path(url: &str) -> (&str, &str) {
+    match url.find(['?', '#']) {
+        Some(ix) => url.split_at(ix),
+        None => (url, ""),
+    }
+}
+
+/// Split a URL into its path and an optional `#fragment` (with the leading
+/// `#` removed).
+pub(crate) fn split_fragment(url: &str) -> (&str, Option<&str>) {
+    match url.find('#') {
+        Some(hash) => (&url[..hash], Some(&url[hash + 1..])),
+        None => (url, None),
+    }
+}
+
+/// Decode any `%XX` escapes in a URL, leaving malformed escapes as-is.
+pub(crate) fn percent_decode(url: &str) -> String {
+    let bytes = url.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_relative_urls() {
+        let inputs = vec![
+            ("../README.md", true),
+            ("images/logo.png#top", true),
+            ("./file.md", true),
+            ("https://example.com/", false),
+            ("mailto:someone@example.com", false),
+            ("//example.com/", false),
+            ("/absolute/path", false),
+            ("#fragment", false),
+            ("", false),
+        ];
+
+        for (url, should_be) in inputs {
+            assert_eq!(is_relative(url), should_be, "{}", url);
+        }
+    }
+
+    #[test]
+    fn decode_percent_escapes() {
+        let inputs = vec![
+            ("my%20file.md", "my file.md"),
+            ("100%", "100%"),
+            ("%zz%41", "%zzA"),
+            ("%+1", "%+1"),
+            ("caf%C3%A9", "café"),
+        ];
+
+        for (url, should_be) in inputs {
+            assert_eq!(percent_decode(url), should_be);
+        }
+    }
+}